@@ -0,0 +1,299 @@
+use core_domain::authentication::{
+    authentication_error::{AuthenticationError, Result},
+    opaque_parameters::OpaqueParameters,
+};
+use opaque_ke::{
+    CredentialFinalization, CredentialRequest, RegistrationRequest, RegistrationUpload,
+    ServerLogin, ServerRegistration, ServerSetup, argon2::Params, rand::rngs::OsRng,
+};
+
+use crate::{
+    opaque_authentication::{P256CipherSuite, StandardCipherSuite},
+    server_identity::ServerIdentity,
+};
+
+const RISTRETTO255_SHA512_ID: u8 = 1;
+const P256_SHA256_ID: u8 = 2;
+const RISTRETTO255_SHA512_NAME: &str = "ristretto255-sha512";
+const P256_SHA256_NAME: &str = "p256-sha256";
+
+const UNKNOWN_CIPHER_SUITE: &str = "Unknown cipher suite";
+const INVALID_KSF_PARAMETERS: &str = "Invalid Argon2 parameters";
+const SERVER_SETUP_LENGTH_MISMATCH: &str = "Server setup length does not match its cipher suite";
+
+// Runs the same code with the concrete opaque-ke cipher suite behind each variant, as `$suite`.
+macro_rules! with_cipher_suite {
+    ($value:expr, $enum:ident, $suite:ident => $body:expr) => {
+        match $value {
+            $enum::Ristretto255Sha512 => {
+                type $suite = StandardCipherSuite;
+                $body
+            }
+            $enum::P256Sha256 => {
+                type $suite = P256CipherSuite;
+                $body
+            }
+        }
+    };
+    ($value:expr, $enum:ident, $inner:ident, $suite:ident => $body:expr) => {
+        match $value {
+            $enum::Ristretto255Sha512($inner) => {
+                type $suite = StandardCipherSuite;
+                $body
+            }
+            $enum::P256Sha256($inner) => {
+                type $suite = P256CipherSuite;
+                $body
+            }
+        }
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CipherSuiteId {
+    Ristretto255Sha512,
+    P256Sha256,
+}
+
+// Argon2 runs on the client, the server only records the costs each password file was made with
+// and hands them back at login so the client stretches the password the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KsfParameters {
+    pub memory_cost: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+#[derive(Clone)]
+pub enum ServerKey {
+    Ristretto255Sha512(ServerSetup<StandardCipherSuite>),
+    P256Sha256(ServerSetup<P256CipherSuite>),
+}
+
+pub enum ServerLoginState {
+    Ristretto255Sha512(ServerLogin<StandardCipherSuite>),
+    P256Sha256(ServerLogin<P256CipherSuite>),
+}
+
+impl CipherSuiteId {
+    pub fn id(&self) -> u8 {
+        match self {
+            CipherSuiteId::Ristretto255Sha512 => RISTRETTO255_SHA512_ID,
+            CipherSuiteId::P256Sha256 => P256_SHA256_ID,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            RISTRETTO255_SHA512_ID => Some(CipherSuiteId::Ristretto255Sha512),
+            P256_SHA256_ID => Some(CipherSuiteId::P256Sha256),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CipherSuiteId::Ristretto255Sha512 => RISTRETTO255_SHA512_NAME,
+            CipherSuiteId::P256Sha256 => P256_SHA256_NAME,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            RISTRETTO255_SHA512_NAME => Ok(CipherSuiteId::Ristretto255Sha512),
+            P256_SHA256_NAME => Ok(CipherSuiteId::P256Sha256),
+            _ => Err(AuthenticationError::Internal(format!("{} {}", UNKNOWN_CIPHER_SUITE, name))),
+        }
+    }
+
+    pub fn finish_registration(&self, client_registration_message: &[u8]) -> Result<Vec<u8>> {
+        with_cipher_suite!(self, CipherSuiteId, CS => {
+            let client_registration_finish_result =
+                RegistrationUpload::<CS>::deserialize(client_registration_message)
+                    .map_err(|error| AuthenticationError::Deserialization(error.to_string()))?;
+
+            Ok(ServerRegistration::<CS>::finish(client_registration_finish_result)
+                .serialize()
+                .to_vec())
+        })
+    }
+}
+
+impl KsfParameters {
+    pub fn new(memory_cost: u32, iterations: u32, parallelism: u32) -> Result<Self> {
+        Params::new(memory_cost, iterations, parallelism, None)
+            .map_err(|error| AuthenticationError::Internal(format!("{}: {}", INVALID_KSF_PARAMETERS, error)))?;
+
+        Ok(Self {
+            memory_cost,
+            iterations,
+            parallelism,
+        })
+    }
+}
+
+// The argon2 crate defaults, which opaque-ke uses when the client passes no parameters.
+impl Default for KsfParameters {
+    fn default() -> Self {
+        Self {
+            memory_cost: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+// Same layout as the parameters of an Argon2 PHC string, for example `m=19456,t=2,p=1`.
+impl std::fmt::Display for KsfParameters {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "m={},t={},p={}",
+            self.memory_cost, self.iterations, self.parallelism
+        )
+    }
+}
+
+impl ServerKey {
+    pub fn generate(cipher_suite: CipherSuiteId) -> Self {
+        let mut rng = OsRng;
+
+        match cipher_suite {
+            CipherSuiteId::Ristretto255Sha512 => ServerKey::Ristretto255Sha512(ServerSetup::new(&mut rng)),
+            CipherSuiteId::P256Sha256 => ServerKey::P256Sha256(ServerSetup::new(&mut rng)),
+        }
+    }
+
+    pub fn cipher_suite(&self) -> CipherSuiteId {
+        match self {
+            ServerKey::Ristretto255Sha512(_) => CipherSuiteId::Ristretto255Sha512,
+            ServerKey::P256Sha256(_) => CipherSuiteId::P256Sha256,
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        match self {
+            ServerKey::Ristretto255Sha512(server_setup) => server_setup.serialize().to_vec(),
+            ServerKey::P256Sha256(server_setup) => server_setup.serialize().to_vec(),
+        }
+    }
+
+    // The setup is read from the start of the input, bytes left over mean it was made for another cipher suite.
+    pub fn deserialize(cipher_suite: CipherSuiteId, serialized_setup: &[u8]) -> std::result::Result<Self, String> {
+        let server_key = match cipher_suite {
+            CipherSuiteId::Ristretto255Sha512 => ServerSetup::<StandardCipherSuite>::deserialize(serialized_setup)
+                .map(ServerKey::Ristretto255Sha512)
+                .map_err(|error| error.to_string())?,
+            CipherSuiteId::P256Sha256 => ServerSetup::<P256CipherSuite>::deserialize(serialized_setup)
+                .map(ServerKey::P256Sha256)
+                .map_err(|error| error.to_string())?,
+        };
+
+        if server_key.serialize().len() != serialized_setup.len() {
+            return Err(SERVER_SETUP_LENGTH_MISMATCH.to_string());
+        }
+
+        Ok(server_key)
+    }
+
+    pub fn start_registration(&self, username: &str, client_registration_message: &[u8]) -> Result<Vec<u8>> {
+        with_cipher_suite!(self, ServerKey, server_setup, CS => {
+            let client_registration_start_result =
+                RegistrationRequest::<CS>::deserialize(client_registration_message)
+                    .map_err(|error| AuthenticationError::Deserialization(error.to_string()))?;
+
+            let server_registration_start_result = ServerRegistration::<CS>::start(
+                server_setup,
+                client_registration_start_result,
+                username.as_bytes(),
+            )
+            .map_err(|error| AuthenticationError::Registration(error.to_string()))?;
+
+            Ok(server_registration_start_result.message.serialize().to_vec())
+        })
+    }
+
+    // Without a registration opaque-ke answers with a fake but well-formed response.
+    pub fn start_login(
+        &self,
+        registration: Option<&[u8]>,
+        username: &str,
+        client_login_message: &[u8],
+        server_identity: &ServerIdentity,
+    ) -> Result<(Vec<u8>, ServerLoginState)> {
+        let mut server_rng = OsRng;
+
+        with_cipher_suite!(self, ServerKey, server_setup, CS => {
+            let registration = registration
+                .map(ServerRegistration::<CS>::deserialize)
+                .transpose()
+                .map_err(|error| AuthenticationError::Deserialization(error.to_string()))?;
+
+            let client_login_start_result = CredentialRequest::<CS>::deserialize(client_login_message)
+                .map_err(|error| AuthenticationError::Deserialization(error.to_string()))?;
+
+            let server_login_start_result = ServerLogin::start(
+                &mut server_rng,
+                server_setup,
+                registration,
+                client_login_start_result,
+                username.as_bytes(),
+                server_identity.login_parameters(),
+            )
+            .map_err(|error| AuthenticationError::Login(error.to_string()))?;
+
+            Ok((
+                server_login_start_result.message.serialize().to_vec(),
+                server_login_start_result.state.into(),
+            ))
+        })
+    }
+
+    pub fn parameters(&self, ksf_parameters: &KsfParameters, server_identity: &ServerIdentity) -> OpaqueParameters {
+        OpaqueParameters {
+            cipher_suite: self.cipher_suite().name().to_string(),
+            ksf_parameters: ksf_parameters.to_string(),
+            server_identity: server_identity.identity.clone(),
+            context: server_identity.context.clone(),
+        }
+    }
+}
+
+impl ServerLoginState {
+    pub fn cipher_suite(&self) -> CipherSuiteId {
+        match self {
+            ServerLoginState::Ristretto255Sha512(_) => CipherSuiteId::Ristretto255Sha512,
+            ServerLoginState::P256Sha256(_) => CipherSuiteId::P256Sha256,
+        }
+    }
+
+    pub fn finish(self, client_login_message: &[u8], server_identity: &ServerIdentity) -> Result<Vec<u8>> {
+        with_cipher_suite!(self, ServerLoginState, server_login, CS => {
+            let server_login_finish_result = CredentialFinalization::<CS>::deserialize(client_login_message)
+                .and_then(|client_login_finish| {
+                    server_login.finish(client_login_finish, server_identity.login_parameters())
+                })
+                .map_err(|error| AuthenticationError::Login(error.to_string()))?;
+
+            Ok(server_login_finish_result.session_key.to_vec())
+        })
+    }
+}
+
+impl From<ServerLogin<StandardCipherSuite>> for ServerLoginState {
+    fn from(server_login: ServerLogin<StandardCipherSuite>) -> Self {
+        ServerLoginState::Ristretto255Sha512(server_login)
+    }
+}
+
+impl From<ServerLogin<P256CipherSuite>> for ServerLoginState {
+    fn from(server_login: ServerLogin<P256CipherSuite>) -> Self {
+        ServerLoginState::P256Sha256(server_login)
+    }
+}
+
+impl From<ServerSetup<StandardCipherSuite>> for ServerKey {
+    fn from(server_setup: ServerSetup<StandardCipherSuite>) -> Self {
+        ServerKey::Ristretto255Sha512(server_setup)
+    }
+}
@@ -0,0 +1,19 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use core_domain::authentication::authentication_error::{AuthenticationError, Result};
+
+pub trait Clock {
+    fn now(&self) -> Result<u64>;
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Result<u64> {
+        Ok(SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|error| AuthenticationError::Internal(error.to_string()))?
+            .as_secs())
+    }
+}
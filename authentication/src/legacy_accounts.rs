@@ -0,0 +1,123 @@
+use std::collections::BTreeSet;
+
+use core_domain::{
+    authentication::authentication_error::{AuthenticationError, Result},
+    domain::username::Username,
+    ports::file_storage::FileStorage,
+};
+
+use crate::{
+    password_file::PasswordFile,
+    recovery_file::{RecoveryFile, is_recovery_file_name},
+};
+
+const LIST_PAGE_SIZE: usize = 100;
+
+const INVALID_STORED_NAME: &str = "Stored name is not a valid username";
+
+// Accounts registered before usernames were canonical are stored under the name they used, "Alice" where a login
+// now looks for "alice". Their files move to the canonical name, gives how many moved. Nothing moves when a stored
+// name is not a valid username or two files would end up under the same name.
+
+// Password files and recovery credentials keep the name they were registered with, their OPRF key was derived from it.
+pub fn canonicalise_password_files<FS: FileStorage>(file_storage: &FS) -> Result<usize> {
+    canonicalise_files_with(file_storage, |file_name, content| {
+        if is_recovery_file_name(file_name) {
+            let mut recovery_file = RecoveryFile::deserialize(&content)?;
+            recovery_file.password_file = keep_credential_identifier(recovery_file.password_file, file_name);
+
+            Ok(recovery_file.serialize())
+        } else {
+            Ok(keep_credential_identifier(PasswordFile::deserialize(&content)?, file_name).serialize())
+        }
+    })
+}
+
+// Vaults and second factors are not bound to the name, they only move.
+pub fn canonicalise_files<FS: FileStorage>(file_storage: &FS) -> Result<usize> {
+    canonicalise_files_with(file_storage, |_, content| Ok(content))
+}
+
+fn canonicalise_files_with<FS: FileStorage>(
+    file_storage: &FS,
+    rewrite: impl Fn(&str, Vec<u8>) -> Result<Vec<u8>>,
+) -> Result<usize> {
+    let mut moves = Vec::new();
+    let mut canonical_names = BTreeSet::new();
+
+    let mut start_after = None;
+
+    loop {
+        let file_listing = file_storage
+            .list("", start_after.as_deref(), LIST_PAGE_SIZE)
+            .map_err(|error| AuthenticationError::PasswordFileRetrieve(error.to_string()))?;
+
+        for file_name in file_listing.file_names {
+            let canonical_name = Username::parse(&file_name)
+                .map_err(|error| AuthenticationError::Internal(format!("{} ({}): {}", INVALID_STORED_NAME, file_name, error)))?
+                .to_string();
+
+            if canonical_name != file_name {
+                moves.push((file_name, canonical_name));
+            }
+        }
+
+        match file_listing.next_start_after {
+            Some(next_start_after) => start_after = Some(next_start_after),
+            None => break,
+        }
+    }
+
+    let mut files = Vec::new();
+
+    for (file_name, canonical_name) in moves {
+        let content = rewrite(&file_name, retrieve(file_storage, &file_name)?)?;
+
+        let stored_content = match exists(file_storage, &canonical_name)? {
+            true => Some(retrieve(file_storage, &canonical_name)?),
+            false => None,
+        };
+
+        // A move that crashed before deleting the old name left the same content under the canonical one.
+        let already_moved = stored_content.as_ref() == Some(&content);
+
+        if !canonical_names.insert(canonical_name.clone()) || (stored_content.is_some() && !already_moved) {
+            return Err(AuthenticationError::AccountAlreadyExists(format!("{} ({})", canonical_name, file_name)));
+        }
+
+        files.push((file_name, canonical_name, content, already_moved));
+    }
+
+    for (file_name, canonical_name, content, already_moved) in &files {
+        if !already_moved {
+            file_storage
+                .save(canonical_name, content.clone())
+                .map_err(|error| AuthenticationError::PasswordFileSave(error.to_string()))?;
+        }
+
+        file_storage
+            .delete(file_name)
+            .map_err(|error| AuthenticationError::PasswordFileSave(error.to_string()))?;
+    }
+
+    Ok(files.len())
+}
+
+fn keep_credential_identifier(password_file: PasswordFile, file_name: &str) -> PasswordFile {
+    match password_file.credential_identifier {
+        Some(_) => password_file,
+        None => password_file.with_credential_identifier(file_name.to_string()),
+    }
+}
+
+fn retrieve<FS: FileStorage>(file_storage: &FS, file_name: &str) -> Result<Vec<u8>> {
+    file_storage
+        .retrieve(file_name)
+        .map_err(|error| AuthenticationError::PasswordFileRetrieve(error.to_string()))
+}
+
+fn exists<FS: FileStorage>(file_storage: &FS, file_name: &str) -> Result<bool> {
+    file_storage
+        .exists(file_name)
+        .map_err(|error| AuthenticationError::PasswordFileRetrieve(error.to_string()))
+}
@@ -1,5 +1,17 @@
+pub mod cipher_suite;
+pub mod clock;
+pub mod legacy_accounts;
+pub mod login_throttle;
+pub mod nonce_cache;
 pub mod opaque_authentication;
-mod session;
+pub mod password_file;
+pub mod pending_login;
+pub mod recovery_file;
+pub mod request_signature;
+pub mod server_identity;
+pub mod server_key;
+pub mod session;
+pub mod two_factor;
 
 #[cfg(test)]
 mod tests;
\ No newline at end of file
@@ -0,0 +1,297 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{ErrorKind, Write},
+    path::Path,
+};
+
+use core_domain::authentication::authentication_error::{AuthenticationError, Result};
+
+const TEMPORARY_EXTENSION: &str = "tmp";
+const SAVE_INTERVAL: u64 = 5;
+const USERNAME_KEY_PREFIX: &str = "user:";
+const CLIENT_IP_KEY_PREFIX: &str = "ip:";
+const SECOND_FACTOR_KEY_PREFIX: &str = "2fa:";
+
+const CORRUPT_THROTTLE_FILE: &str = "Login throttle file is corrupt";
+
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottlePolicy {
+    pub free_attempts: u32,
+    pub base_delay: u64,
+    pub max_delay: u64,
+    pub lockout_threshold: u32,
+    pub lockout_duration: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FailureCounter {
+    pub failures: u32,
+    pub last_failure_at: u64,
+    pub locked_until: u64,
+}
+
+pub struct LoginThrottle {
+    policy: ThrottlePolicy,
+    counters: HashMap<String, FailureCounter>,
+    path: Option<String>,
+    // Changes not written to the file yet, and when it was last written.
+    dirty: bool,
+    saved_at: u64,
+}
+
+impl ThrottlePolicy {
+    pub fn new(
+        free_attempts: u32,
+        base_delay: u64,
+        max_delay: u64,
+        lockout_threshold: u32,
+        lockout_duration: u64,
+    ) -> Self {
+        Self {
+            free_attempts,
+            base_delay,
+            max_delay,
+            lockout_threshold,
+            lockout_duration,
+        }
+    }
+}
+
+impl FailureCounter {
+    // The delay doubles with every failure past the free attempts, until it reaches the maximum delay.
+    pub fn retry_after(&self, now: u64, policy: &ThrottlePolicy) -> u64 {
+        if self.locked_until > now {
+            return self.locked_until - now;
+        }
+
+        if self.failures <= policy.free_attempts {
+            return 0;
+        }
+
+        let exponent = (self.failures - policy.free_attempts - 1).min(63);
+        let delay = policy
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(policy.max_delay);
+
+        self.last_failure_at.saturating_add(delay).saturating_sub(now)
+    }
+
+    pub fn is_forgotten(&self, now: u64, policy: &ThrottlePolicy) -> bool {
+        self.locked_until <= now && now.saturating_sub(self.last_failure_at) >= policy.lockout_duration
+    }
+}
+
+impl LoginThrottle {
+    pub fn new(policy: ThrottlePolicy) -> Self {
+        Self {
+            policy,
+            counters: HashMap::new(),
+            path: None,
+            dirty: false,
+            saved_at: 0,
+        }
+    }
+
+    // Counters are written to the file so that a restart does not hand out fresh attempts.
+    // Lockouts are written right away, other changes at most every few seconds and when the throttle is dropped.
+    pub fn load(path: &str, policy: ThrottlePolicy) -> Result<Self> {
+        let counters = match fs::read_to_string(path) {
+            Ok(content) => decode_counters(&content).map_err(|message| throttle_error(path, &message))?,
+            Err(error) if error.kind() == ErrorKind::NotFound => HashMap::new(),
+            Err(error) => return Err(throttle_error(path, &error.to_string())),
+        };
+
+        Ok(Self {
+            policy,
+            counters,
+            path: Some(path.to_string()),
+            dirty: false,
+            saved_at: 0,
+        })
+    }
+
+    pub fn counter(&self, key: &str) -> Option<FailureCounter> {
+        self.counters.get(key).copied()
+    }
+
+    pub fn check(&self, keys: &[String], now: u64) -> Result<()> {
+        let retry_after = keys
+            .iter()
+            .filter_map(|key| self.counters.get(key))
+            .filter(|counter| !counter.is_forgotten(now, &self.policy))
+            .map(|counter| counter.retry_after(now, &self.policy))
+            .max()
+            .unwrap_or(0);
+
+        if retry_after > 0 {
+            return Err(AuthenticationError::Throttled(retry_after));
+        }
+
+        Ok(())
+    }
+
+    pub fn record_failure(&mut self, keys: &[String], now: u64) -> Result<()> {
+        self.forget_old_failures(now);
+
+        let mut lockout_started = false;
+
+        for key in keys {
+            let counter = self.counters.entry(key.clone()).or_default();
+
+            counter.failures = counter.failures.saturating_add(1);
+            counter.last_failure_at = now;
+
+            if counter.failures >= self.policy.lockout_threshold {
+                lockout_started |= counter.locked_until <= now;
+                counter.locked_until = now.saturating_add(self.policy.lockout_duration);
+            }
+        }
+
+        self.dirty = true;
+
+        if lockout_started {
+            return self.flush(now);
+        }
+
+        self.flush_if_due(now)
+    }
+
+    pub fn record_success(&mut self, key: &str, now: u64) -> Result<()> {
+        self.forget_old_failures(now);
+
+        if self.counters.remove(key).is_none() {
+            return Ok(());
+        }
+
+        self.dirty = true;
+
+        self.flush_if_due(now)
+    }
+
+    pub fn flush(&mut self, now: u64) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        self.save()?;
+        self.dirty = false;
+        self.saved_at = now;
+
+        Ok(())
+    }
+
+    fn flush_if_due(&mut self, now: u64) -> Result<()> {
+        if now.saturating_sub(self.saved_at) < SAVE_INTERVAL {
+            return Ok(());
+        }
+
+        self.flush(now)
+    }
+
+    fn forget_old_failures(&mut self, now: u64) {
+        let policy = self.policy;
+
+        self.counters
+            .retain(|_, counter| !counter.is_forgotten(now, &policy));
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(parent) = Path::new(path).parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent).map_err(|error| throttle_error(path, &error.to_string()))?;
+        }
+
+        let temporary_path = Path::new(path).with_extension(TEMPORARY_EXTENSION);
+
+        let mut file =
+            File::create(&temporary_path).map_err(|error| throttle_error(path, &error.to_string()))?;
+
+        file.write_all(encode_counters(&self.counters).as_bytes())
+            .and_then(|_| file.sync_all())
+            .map_err(|error| throttle_error(path, &error.to_string()))?;
+
+        fs::rename(&temporary_path, path).map_err(|error| throttle_error(path, &error.to_string()))
+    }
+}
+
+impl Drop for LoginThrottle {
+    fn drop(&mut self) {
+        if self.dirty
+            && let Err(error) = self.save()
+        {
+            eprintln!("Error saving login throttle: {error}");
+        }
+    }
+}
+
+pub fn throttle_keys(username: &str, client_ip: Option<&str>) -> Vec<String> {
+    let mut keys = vec![username_key(username)];
+
+    if let Some(client_ip) = client_ip {
+        keys.push(format!("{}{}", CLIENT_IP_KEY_PREFIX, client_ip));
+    }
+
+    keys
+}
+
+pub fn username_key(username: &str) -> String {
+    format!("{}{}", USERNAME_KEY_PREFIX, username)
+}
+
+pub fn second_factor_key(username: &str) -> String {
+    format!("{}{}", SECOND_FACTOR_KEY_PREFIX, username)
+}
+
+// One counter per line, the key is hex encoded since usernames can contain anything.
+fn encode_counters(counters: &HashMap<String, FailureCounter>) -> String {
+    counters
+        .iter()
+        .map(|(key, counter)| {
+            format!(
+                "{} {} {} {}\n",
+                hex::encode(key),
+                counter.failures,
+                counter.last_failure_at,
+                counter.locked_until
+            )
+        })
+        .collect()
+}
+
+fn decode_counters(content: &str) -> std::result::Result<HashMap<String, FailureCounter>, String> {
+    let mut counters = HashMap::new();
+
+    for line in content.lines().filter(|line| !line.is_empty()) {
+        let fields: Vec<&str> = line.split(' ').collect();
+
+        let [key, failures, last_failure_at, locked_until] = fields[..] else {
+            return Err(CORRUPT_THROTTLE_FILE.to_string());
+        };
+
+        let key = hex::decode(key)
+            .ok()
+            .and_then(|key| String::from_utf8(key).ok())
+            .ok_or(CORRUPT_THROTTLE_FILE.to_string())?;
+
+        let counter = FailureCounter {
+            failures: failures.parse().map_err(|_| CORRUPT_THROTTLE_FILE.to_string())?,
+            last_failure_at: last_failure_at.parse().map_err(|_| CORRUPT_THROTTLE_FILE.to_string())?,
+            locked_until: locked_until.parse().map_err(|_| CORRUPT_THROTTLE_FILE.to_string())?,
+        };
+
+        counters.insert(key, counter);
+    }
+
+    Ok(counters)
+}
+
+fn throttle_error(path: &str, message: &str) -> AuthenticationError {
+    AuthenticationError::Internal(format!("{} ({})", message, path))
+}
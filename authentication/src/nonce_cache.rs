@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+use core_domain::authentication::authentication_error::{AuthenticationError, Result};
+
+const NONCE_CACHE_CAPACITY: usize = 1024;
+
+// Nonces seen by one session, each one is kept up to the last second the request carrying it passes the timestamp check.
+#[derive(Debug)]
+pub struct NonceCache {
+    capacity: usize,
+    expirations: HashMap<String, u64>,
+}
+
+impl NonceCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            expirations: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.expirations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expirations.is_empty()
+    }
+
+    pub fn remember(&mut self, nonce: &str, expires_at: u64, now: u64) -> Result<()> {
+        self.expirations.retain(|_, nonce_expires_at| *nonce_expires_at >= now);
+
+        if self.expirations.contains_key(nonce) {
+            return Err(AuthenticationError::ReplayedRequest(nonce.to_string()));
+        }
+
+        // A full cache cannot drop a live nonce without reopening the replay window, the client has to slow down.
+        if self.expirations.len() >= self.capacity {
+            let retry_after = self
+                .expirations
+                .values()
+                .min()
+                .map(|nonce_expires_at| nonce_expires_at - now + 1)
+                .unwrap_or(1);
+
+            return Err(AuthenticationError::Throttled(retry_after));
+        }
+
+        self.expirations.insert(nonce.to_string(), expires_at);
+
+        Ok(())
+    }
+}
+
+impl Default for NonceCache {
+    fn default() -> Self {
+        Self::new(NONCE_CACHE_CAPACITY)
+    }
+}
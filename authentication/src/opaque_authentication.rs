@@ -1,25 +1,70 @@
-use std::{
-    collections::HashMap,
-    time::{SystemTime, UNIX_EPOCH},
-};
+use std::collections::HashMap;
 
 use core_domain::{
-    authentication::authentication_error::{AuthenticationError, Result},
-    ports::{authentication::Authentication, file_storage::FileStorage},
+    authentication::{
+        authentication_error::{AuthenticationError, Result},
+        login_finish::LoginFinish,
+        login_start::LoginStart,
+        opaque_parameters::OpaqueParameters,
+        session_refresh::SessionRefresh,
+        session_summary::SessionSummary,
+        signed_request::SignedRequest,
+        two_factor_enrollment::TwoFactorEnrollment,
+    },
+    domain::username::Username,
+    file_storage::file_storage_error::FileStorageError,
+    ports::{authentication::Authentication, file_storage::FileStorage, session_store::SessionStore},
+    session_store::{session::Session, session_store_error::SessionStoreError},
 };
 use hkdf::Hkdf;
 use hmac::{Hmac, Mac};
 use opaque_ke::{
-    CipherSuite, CredentialFinalization, CredentialRequest, RegistrationRequest,
-    RegistrationUpload, ServerLogin, ServerLoginParameters, ServerLoginStartResult,
-    ServerRegistration, ServerSetup, argon2::Argon2, rand::rngs::OsRng,
+    CipherSuite,
+    argon2::Argon2,
+    rand::{RngCore, rngs::OsRng},
 };
-use sha2::Sha512;
+use sha2::{Sha256, Sha512};
 
-use crate::session::Session;
+use crate::{
+    cipher_suite::{CipherSuiteId, KsfParameters, ServerKey},
+    clock::{Clock, SystemClock},
+    login_throttle::{LoginThrottle, second_factor_key, throttle_keys, username_key},
+    nonce_cache::NonceCache,
+    password_file::PasswordFile,
+    pending_login::{LoginPolicy, PendingLogin},
+    recovery_file::{RecoveryFile, is_recovery_file_name, recovery_file_name},
+    request_signature::{self, RequestPolicy, signature_base},
+    server_identity::ServerIdentity,
+    server_key::ServerKeyring,
+    session::{SessionPolicy, token_hash},
+    two_factor::TwoFactorRecord,
+};
 
-const USERNAME_DID_NOT_START_LOGIN_PHASE: &'static str = "Username did not start login phase.";
+const LOGIN_ID_DID_NOT_START_LOGIN_PHASE: &str = "Login id did not start login phase.";
+const LOGIN_PHASE_EXPIRED: &str = "Login phase expired, start the login again.";
+const PENDING_LOGINS_LIMIT_REACHED: &str = "Too many logins in progress for this client or username, try again later.";
 const SESSION_SHOULD_BE_PRESENT: &'static str = "Session should be present, checks should have been performed before.";
+const SESSION_ALREADY_REFRESHED: &str = "Only the latest token of a session can refresh it.";
+const SECOND_FACTOR_NOT_VERIFIED: &str = "Send a TOTP or recovery code to /account/2fa/verify first.";
+const TWO_FACTOR_NOT_CONFIGURED: &str = "Two factor authentication is not configured on this server.";
+const TWO_FACTOR_ALREADY_ENABLED: &str = "Two factor authentication is already enabled.";
+const TWO_FACTOR_NOT_ENABLED: &str = "Two factor authentication is not enabled.";
+const TWO_FACTOR_NOT_ENROLLED: &str = "Start the enrollment first.";
+const INVALID_TWO_FACTOR_CODE: &str = "The code is wrong or was already used.";
+const LOGIN_NOT_FRESH: &str = "Log in again to confirm, the session is too old for this.";
+const DELETED_PASSWORD_FILE: &str = "password_file";
+const DELETED_RECOVERY_CREDENTIAL: &str = "recovery_credential";
+const DELETED_TWO_FACTOR: &str = "two_factor";
+const RECOVERY_NOT_SET_UP: &str = "Set up a recovery credential from a logged in session first.";
+const UNKNOWN_SERVER_KEY_VERSION: &str = "Password file was created with an unknown server key version";
+const CIPHER_SUITE_MISMATCH: &str = "Password file cipher suite does not match its server key version";
+const SESSION_TOKEN_INFO: &[u8] = b"opaque-session-token";
+const SESSION_REFRESH_INFO: &[u8] = b"opaque-session-refresh";
+const SESSION_REFRESH_SALT_LENGTH: usize = 32;
+const SESSION_ID_LENGTH: usize = 16;
+const LOGIN_ID_LENGTH: usize = 32;
+const CLIENT_LABEL_MAX_LENGTH: usize = 64;
+const LIST_PAGE_SIZE: usize = 100;
 
 pub type HmacSha512 = Hmac<Sha512>;
 
@@ -32,181 +77,733 @@ impl CipherSuite for StandardCipherSuite {
     type Ksf = Argon2<'static>;
 }
 
-pub struct OpaqueAuthentication<FS: FileStorage> {
+#[derive(Debug, Default)]
+pub struct P256CipherSuite;
+
+impl CipherSuite for P256CipherSuite {
+    type OprfCs = p256::NistP256;
+    type KeyExchange = opaque_ke::TripleDh<p256::NistP256, Sha256>;
+    type Ksf = Argon2<'static>;
+}
+
+pub struct OpaqueAuthentication<FS: FileStorage, SS: SessionStore, C: Clock = SystemClock> {
     file_storage: FS,
-    server_setup: ServerSetup<StandardCipherSuite>,
-    current_login_sessions: HashMap<String, ServerLoginStartResult<StandardCipherSuite>>,
-    logged_sessions: HashMap<String, Session>,
-    request_max_ttl: u64,
+    session_store: SS,
+    server_keyring: ServerKeyring,
+    current_login_sessions: HashMap<String, PendingLogin>,
+    nonce_caches: HashMap<String, NonceCache>,
+    request_policy: RequestPolicy,
+    session_policy: SessionPolicy,
+    login_policy: LoginPolicy,
+    login_throttle: LoginThrottle,
+    ksf_parameters: KsfParameters,
+    // Key versions and Argon2 costs of the existing password files, collected at the first unknown user login.
+    fake_login_candidates: Option<Vec<(u32, KsfParameters)>>,
+    server_identity: ServerIdentity,
+    two_factor_storage: Option<FS>,
+    two_factor_issuer: String,
+    started_at: u64,
+    clock: C,
 }
 
-impl<FS: FileStorage> OpaqueAuthentication<FS> {
-    fn create_session(&mut self, session_key: &[u8], username: &str) -> Result<()> {
-        let hkdf = Hkdf::<Sha512>::from_prk(session_key)
-            .map_err(|error| AuthenticationError::CreatingSession(error.to_string()))?;
+impl<FS: FileStorage, SS: SessionStore, C: Clock> OpaqueAuthentication<FS, SS, C> {
+    fn create_session(
+        &mut self,
+        cipher_suite: CipherSuiteId,
+        session_key: &[u8],
+        username: &str,
+        client_label: Option<&str>,
+        second_factor_pending: bool,
+        recovery: bool,
+    ) -> Result<()> {
+        let session_token = derive_session_token(cipher_suite, session_key)?;
+        let now = self.clock.now()?;
+
+        let session = Session {
+            second_factor_pending,
+            recovery,
+            ..Session::new(
+                session_key.to_vec(),
+                generate_identifier(SESSION_ID_LENGTH),
+                username.to_string(),
+                client_label.map(sanitize_client_label),
+                cipher_suite.name().to_string(),
+                now,
+            )
+        };
+
+        self.session_store
+            .save(&token_hash(&session_token), session)
+            .map_err(session_store_error_to_authentication_error)
+    }
+
+    fn find_session(&self, bearer_token: &str) -> Result<Option<Session>> {
+        self.session_store
+            .get(&token_hash(bearer_token))
+            .map_err(session_store_error_to_authentication_error)
+    }
+
+    fn existing_session(&self, bearer_token: &str) -> Result<Session> {
+        self.find_session(bearer_token)?
+            .ok_or(AuthenticationError::Internal(SESSION_SHOULD_BE_PRESENT.to_string()))
+    }
+
+    fn remove_sessions(&mut self, keep: &dyn Fn(&str, &Session) -> bool) -> Result<usize> {
+        let removed_token_hashes = self
+            .session_store
+            .retain(keep)
+            .map_err(session_store_error_to_authentication_error)?;
+
+        for token_hash in &removed_token_hashes {
+            self.nonce_caches.remove(token_hash);
+        }
+
+        Ok(removed_token_hashes.len())
+    }
+
+    fn two_factor_storage(&self) -> Result<&FS> {
+        self.two_factor_storage
+            .as_ref()
+            .ok_or(AuthenticationError::SecondFactorState(TWO_FACTOR_NOT_CONFIGURED.to_string()))
+    }
+
+    // Older servers could not delete files, a second factor they disabled is an empty file.
+    fn load_two_factor_record(&self, username: &str) -> Result<Option<TwoFactorRecord>> {
+        let Some(two_factor_storage) = &self.two_factor_storage else {
+            return Ok(None);
+        };
 
-        let mut token = vec![0u8; 64];
+        match two_factor_storage.retrieve(username) {
+            Ok(content) if content.is_empty() => Ok(None),
+            Ok(content) => TwoFactorRecord::deserialize(&content).map(Some),
+            Err(FileStorageError::FileNotFound(_)) => Ok(None),
+            Err(error) => Err(AuthenticationError::Internal(error.to_string())),
+        }
+    }
 
-        hkdf.expand(b"opaque-session-token", &mut token)
-            .map_err(|error| AuthenticationError::CreatingSession(error.to_string()))?;
+    fn save_two_factor_record(&self, username: &str, two_factor_record: &TwoFactorRecord) -> Result<()> {
+        self.two_factor_storage()?
+            .save(username, two_factor_record.serialize())
+            .map_err(|error| AuthenticationError::Internal(error.to_string()))
+    }
 
-        let session_token = hex::encode(token);
+    fn enabled_two_factor_record(&self, username: &str) -> Result<TwoFactorRecord> {
+        match self.load_two_factor_record(username)? {
+            Some(two_factor_record) if two_factor_record.enabled => Ok(two_factor_record),
+            _ => Err(AuthenticationError::SecondFactorState(TWO_FACTOR_NOT_ENABLED.to_string())),
+        }
+    }
 
-        self.logged_sessions.insert(
-            session_token.clone(),
-            Session::new(session_key.to_vec(), session_token, username.to_string()),
+    // Wrong codes count against the login throttle, a 6 digit code would not survive unlimited guesses.
+    fn check_two_factor_code(
+        &mut self,
+        username: &str,
+        two_factor_record: &mut TwoFactorRecord,
+        code: &str,
+        accept_recovery_code: bool,
+    ) -> Result<()> {
+        let now = self.clock.now()?;
+        let throttle_keys = [second_factor_key(username)];
+
+        self.login_throttle.check(&throttle_keys, now)?;
+
+        let verified = if accept_recovery_code {
+            two_factor_record.verify(code, now)
+        } else {
+            two_factor_record.verify_code(code, now)
+        };
+
+        if !verified {
+            self.login_throttle.record_failure(&throttle_keys, now)?;
+
+            return Err(AuthenticationError::InvalidSecondFactorCode(INVALID_TWO_FACTOR_CODE.to_string()));
+        }
+
+        self.login_throttle.record_success(&throttle_keys[0], now)
+    }
+
+    fn retrieve_password_file(&self, username: &str) -> Result<Vec<u8>> {
+        self.file_storage
+            .retrieve(username)
+            .map_err(|error| AuthenticationError::PasswordFileRetrieve(error.to_string()))
+    }
+
+    // The password file records the cipher suite and the Argon2 costs the user registered with,
+    // a login keeps using them even after the configuration moved on.
+    fn load_registration(&self, password_file: PasswordFile) -> Result<(&ServerKey, PasswordFile)> {
+        let Some(server_key) = self.server_keyring.get(password_file.key_version) else {
+            return Err(AuthenticationError::ServerKey(format!(
+                "{} ({})",
+                UNKNOWN_SERVER_KEY_VERSION, password_file.key_version
+            )));
+        };
+
+        if server_key.cipher_suite() != password_file.cipher_suite {
+            return Err(AuthenticationError::ServerKey(format!(
+                "{} ({})",
+                CIPHER_SUITE_MISMATCH, password_file.key_version
+            )));
+        }
+
+        Ok((server_key, password_file))
+    }
+
+    fn start_registration(&self, username: &str, client_registration_message: Vec<u8>) -> Result<Vec<u8>> {
+        self.server_keyring
+            .current()
+            .start_registration(username, &client_registration_message)
+    }
+
+    // New registrations always use the current server key, a password change moves the user onto it.
+    fn finish_registration(&self, client_registration_message: Vec<u8>) -> Result<PasswordFile> {
+        let cipher_suite = self.server_keyring.current().cipher_suite();
+
+        let registration = cipher_suite.finish_registration(&client_registration_message)?;
+
+        Ok(PasswordFile::new(
+            self.server_keyring.current_version(),
+            cipher_suite,
+            self.ksf_parameters,
+            registration,
+        ))
+    }
+
+    // Unknown users get the key version and Argon2 costs of existing password files, picked by a hash of the username
+    // keyed with the oldest server key. Like a real user, an unknown one keeps them across restarts and configuration changes.
+    fn fake_login_parameters(&self, username: &str) -> Result<(&ServerKey, KsfParameters)> {
+        let candidates = self.fake_login_candidates.as_deref().unwrap_or_default();
+
+        if candidates.is_empty() {
+            return Ok((self.server_keyring.current(), self.ksf_parameters));
+        }
+
+        let mut mac = HmacSha512::new_from_slice(&self.server_keyring.oldest().serialize())
+            .map_err(|error| AuthenticationError::Internal(error.to_string()))?;
+
+        mac.update(username.as_bytes());
+
+        let digest = mac.finalize().into_bytes();
+        let index = u64::from_be_bytes(digest[..8].try_into().unwrap()) % candidates.len() as u64;
+        let (key_version, ksf_parameters) = candidates[index as usize];
+
+        let Some(server_key) = self.server_keyring.get(key_version) else {
+            return Err(AuthenticationError::ServerKey(format!(
+                "{} ({})",
+                UNKNOWN_SERVER_KEY_VERSION, key_version
+            )));
+        };
+
+        Ok((server_key, ksf_parameters))
+    }
+
+    fn collect_fake_login_candidates(&self) -> Vec<(u32, KsfParameters)> {
+        let mut candidates: Vec<(u32, KsfParameters)> = Vec::new();
+
+        let mut start_after = None;
+
+        while let Ok(file_listing) = self.file_storage.list("", start_after.as_deref(), LIST_PAGE_SIZE) {
+            candidates.extend(
+                file_listing
+                    .file_names
+                    .iter()
+                    .filter(|username| !is_recovery_file_name(username))
+                    .filter_map(|username| self.retrieve_password_file(username).ok())
+                    .filter_map(|content| PasswordFile::deserialize(&content).ok())
+                    .filter_map(|password_file| self.load_registration(password_file).ok())
+                    .map(|(_, password_file)| (password_file.key_version, password_file.ksf_parameters)),
+            );
+
+            match file_listing.next_start_after {
+                Some(next_start_after) => start_after = Some(next_start_after),
+                None => break,
+            }
+        }
+
+        candidates.sort_by_key(|(key_version, ksf_parameters)| {
+            (
+                *key_version,
+                ksf_parameters.memory_cost,
+                ksf_parameters.iterations,
+                ksf_parameters.parallelism,
+            )
+        });
+        candidates.dedup();
+
+        candidates
+    }
+
+    fn load_recovery_file(&self, username: &str) -> Result<Option<RecoveryFile>> {
+        match self.file_storage.retrieve(&recovery_file_name(username)) {
+            Ok(content) => RecoveryFile::deserialize(&content).map(Some),
+            Err(FileStorageError::FileNotFound(_)) => Ok(None),
+            Err(error) => Err(AuthenticationError::PasswordFileRetrieve(error.to_string())),
+        }
+    }
+
+    // The password file the login runs against, None makes it fail like an unknown user.
+    fn load_login_password_file(&self, username: &str, recovery: bool) -> Result<Option<PasswordFile>> {
+        if is_recovery_file_name(username) {
+            return Ok(None);
+        }
+
+        if recovery {
+            return Ok(self
+                .load_recovery_file(username)?
+                .map(|recovery_file| recovery_file.password_file));
+        }
+
+        match self.file_storage.retrieve(username) {
+            Ok(password_file) => PasswordFile::deserialize(&password_file).map(Some),
+            Err(FileStorageError::FileNotFound(_)) => Ok(None),
+            Err(error) => Err(AuthenticationError::PasswordFileRetrieve(error.to_string())),
+        }
+    }
+
+    // A recovery login runs the same handshake against the recovery credential, it opens a recovery session.
+    fn start_login(
+        &mut self,
+        username: &str,
+        client_ip: Option<&str>,
+        client_login_message: Vec<u8>,
+        recovery: bool,
+    ) -> Result<LoginStart> {
+        let now = self.clock.now()?;
+
+        self.current_login_sessions
+            .retain(|_, pending_login| !pending_login.is_expired(now, &self.login_policy));
+
+        self.login_throttle
+            .check(&throttle_keys(username, client_ip), now)?;
+
+        // Counted per client and per username, so that one client flooding starts does not lock out everyone else.
+        let pending_for_username = self
+            .current_login_sessions
+            .values()
+            .filter(|pending_login| pending_login.username == username)
+            .count();
+
+        let pending_for_client = self
+            .current_login_sessions
+            .values()
+            .filter(|pending_login| client_ip.is_some() && pending_login.client_ip.as_deref() == client_ip)
+            .count();
+
+        if pending_for_username >= self.login_policy.max_pending
+            || pending_for_client >= self.login_policy.max_pending
+        {
+            return Err(AuthenticationError::TooManyPendingLogins(
+                PENDING_LOGINS_LIMIT_REACHED.to_string(),
+            ));
+        }
+
+        let password_file = self.load_login_password_file(username, recovery)?;
+
+        if password_file.is_none() && self.fake_login_candidates.is_none() {
+            self.fake_login_candidates = Some(self.collect_fake_login_candidates());
+        }
+
+        // Unknown users get a fake but well-formed response, they fail at login finish like a wrong password.
+        let (server_key, registration, ksf_parameters, credential_identifier) = match password_file {
+            Some(password_file) => {
+                let (server_key, password_file) = self.load_registration(password_file)?;
+
+                (
+                    server_key,
+                    Some(password_file.registration),
+                    password_file.ksf_parameters,
+                    password_file.credential_identifier,
+                )
+            }
+            None => {
+                let (server_key, ksf_parameters) = self.fake_login_parameters(username)?;
+
+                (server_key, None, ksf_parameters, None)
+            }
+        };
+
+        // A credential moved from a name that was not canonical keeps the identifier it was registered with.
+        let credential_identifier = credential_identifier.unwrap_or_else(|| {
+            if recovery {
+                recovery_file_name(username)
+            } else {
+                username.to_string()
+            }
+        });
+
+        let (message, server_login) = server_key.start_login(
+            registration.as_deref(),
+            &credential_identifier,
+            &client_login_message,
+            &self.server_identity,
+        )?;
+
+        let parameters = server_key.parameters(&ksf_parameters, &self.server_identity);
+
+        let login_id = generate_identifier(LOGIN_ID_LENGTH);
+
+        self.current_login_sessions.insert(
+            login_id.clone(),
+            PendingLogin::new(
+                username.to_string(),
+                client_ip.map(|client_ip| client_ip.to_string()),
+                server_login,
+                now,
+                recovery,
+            ),
         );
 
-        Ok(())
+        Ok(LoginStart {
+            login_id,
+            message,
+            parameters,
+        })
+    }
+
+    fn finish_login(
+        &mut self,
+        username: &str,
+        login_id: &str,
+        client_login_message: Vec<u8>,
+        client_label: Option<&str>,
+        recovery: bool,
+    ) -> Result<LoginFinish> {
+        let Some(pending_login) = self.current_login_sessions.remove(login_id) else {
+            return Err(AuthenticationError::Login(
+                LOGIN_ID_DID_NOT_START_LOGIN_PHASE.to_string(),
+            ));
+        };
+
+        if pending_login.username != username || pending_login.recovery != recovery {
+            return Err(AuthenticationError::Login(
+                LOGIN_ID_DID_NOT_START_LOGIN_PHASE.to_string(),
+            ));
+        }
+
+        let now = self.clock.now()?;
+
+        if pending_login.is_expired(now, &self.login_policy) {
+            return Err(AuthenticationError::Login(LOGIN_PHASE_EXPIRED.to_string()));
+        }
+
+        let throttle_keys = throttle_keys(username, pending_login.client_ip.as_deref());
+
+        self.login_throttle.check(&throttle_keys, now)?;
+
+        let cipher_suite = pending_login.server_login.cipher_suite();
+
+        let login_result = pending_login
+            .server_login
+            .finish(&client_login_message, &self.server_identity);
+
+        let session_key = match login_result {
+            Ok(session_key) => session_key,
+            Err(error) => {
+                self.login_throttle.record_failure(&throttle_keys, now)?;
+
+                return Err(error);
+            }
+        };
+
+        self.login_throttle
+            .record_success(&username_key(username), now)?;
+
+        let second_factor_required = self.load_two_factor_record(username)?.is_some_and(|record| record.enabled);
+
+        self.create_session(cipher_suite, &session_key, username, client_label, second_factor_required, recovery)?;
+
+        Ok(LoginFinish {
+            second_factor_required,
+        })
+    }
+
+    // Registering over an existing password file would hand the account, and its vault, to whoever asked.
+    fn ensure_account_does_not_exist(&self, username: &str) -> Result<()> {
+        if is_recovery_file_name(username) {
+            return Err(AuthenticationError::AccountAlreadyExists(username.to_string()));
+        }
+
+        match self.file_storage.exists(username) {
+            Ok(false) => Ok(()),
+            Ok(true) => Err(AuthenticationError::AccountAlreadyExists(username.to_string())),
+            Err(error) => Err(AuthenticationError::PasswordFileRetrieve(error.to_string())),
+        }
     }
 }
 
-impl<FS: FileStorage> OpaqueAuthentication<FS> {
-    pub fn new(file_storage: FS, request_max_ttl: u64) -> Self {
-        let mut rng = OsRng;
-        let server_setup = ServerSetup::<StandardCipherSuite>::new(&mut rng);
+// The session key is the output of the suite's hash, so it is used as the HKDF pseudorandom key with that same hash.
+fn derive_session_token(cipher_suite: CipherSuiteId, session_key: &[u8]) -> Result<String> {
+    let mut token = vec![0u8; 64];
+
+    let expanded = match cipher_suite {
+        CipherSuiteId::Ristretto255Sha512 => Hkdf::<Sha512>::from_prk(session_key)
+            .map(|hkdf| hkdf.expand(SESSION_TOKEN_INFO, &mut token)),
+        CipherSuiteId::P256Sha256 => Hkdf::<Sha256>::from_prk(session_key)
+            .map(|hkdf| hkdf.expand(SESSION_TOKEN_INFO, &mut token)),
+    };
+
+    expanded
+        .map_err(|error| AuthenticationError::CreatingSession(error.to_string()))?
+        .map_err(|error| AuthenticationError::CreatingSession(error.to_string()))?;
+
+    Ok(hex::encode(token))
+}
 
-        Self {
+// The client holds the current session key too, it derives the same key and token from the salt it gets back.
+// The new key is an output of the session's suite hash, like the key it replaces.
+fn derive_refreshed_session(cipher_suite: CipherSuiteId, session_key: &[u8], salt: &[u8]) -> Result<(Vec<u8>, String)> {
+    let (refreshed_session_key, expanded) = match cipher_suite {
+        CipherSuiteId::Ristretto255Sha512 => {
+            let mut refreshed_session_key = vec![0u8; 64];
+            let expanded = Hkdf::<Sha512>::new(Some(salt), session_key)
+                .expand(SESSION_REFRESH_INFO, &mut refreshed_session_key);
+
+            (refreshed_session_key, expanded)
+        }
+        CipherSuiteId::P256Sha256 => {
+            let mut refreshed_session_key = vec![0u8; 32];
+            let expanded = Hkdf::<Sha256>::new(Some(salt), session_key)
+                .expand(SESSION_REFRESH_INFO, &mut refreshed_session_key);
+
+            (refreshed_session_key, expanded)
+        }
+    };
+
+    expanded.map_err(|error| AuthenticationError::CreatingSession(error.to_string()))?;
+
+    let session_token = derive_session_token(cipher_suite, &refreshed_session_key)?;
+
+    Ok((refreshed_session_key, session_token))
+}
+
+// Tells whether there was a file to delete.
+fn delete_file<FS: FileStorage>(file_storage: &FS, file_name: &str) -> Result<bool> {
+    match file_storage.delete(file_name) {
+        Ok(_) => Ok(true),
+        Err(FileStorageError::FileNotFound(_)) => Ok(false),
+        Err(error) => Err(AuthenticationError::PasswordFileSave(error.to_string())),
+    }
+}
+
+fn session_store_error_to_authentication_error(session_store_error: SessionStoreError) -> AuthenticationError {
+    AuthenticationError::SessionStore(session_store_error.to_string())
+}
+
+fn generate_identifier(length: usize) -> String {
+    let mut identifier = vec![0u8; length];
+
+    OsRng.fill_bytes(&mut identifier);
+
+    hex::encode(identifier)
+}
+
+fn sanitize_client_label(client_label: &str) -> String {
+    client_label
+        .chars()
+        .filter(|character| !character.is_control())
+        .take(CLIENT_LABEL_MAX_LENGTH)
+        .collect()
+}
+
+impl<FS: FileStorage, SS: SessionStore> OpaqueAuthentication<FS, SS> {
+    pub fn new(
+        file_storage: FS,
+        session_store: SS,
+        server_keyring: ServerKeyring,
+        request_policy: RequestPolicy,
+        session_policy: SessionPolicy,
+        login_policy: LoginPolicy,
+        login_throttle: LoginThrottle,
+    ) -> Result<Self> {
+        Self::with_clock(
+            file_storage,
+            session_store,
+            server_keyring,
+            request_policy,
+            session_policy,
+            login_policy,
+            login_throttle,
+            SystemClock,
+        )
+    }
+}
+
+impl<FS: FileStorage, SS: SessionStore, C: Clock> OpaqueAuthentication<FS, SS, C> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_clock(
+        file_storage: FS,
+        session_store: SS,
+        server_keyring: ServerKeyring,
+        request_policy: RequestPolicy,
+        session_policy: SessionPolicy,
+        login_policy: LoginPolicy,
+        login_throttle: LoginThrottle,
+        clock: C,
+    ) -> Result<Self> {
+        // A start at 0 would turn off the replay guard for the sessions loaded from the store.
+        let started_at = clock.now()?;
+
+        Ok(Self {
             file_storage,
-            server_setup,
+            session_store,
+            server_keyring,
             current_login_sessions: HashMap::new(),
-            logged_sessions: HashMap::new(),
-            request_max_ttl,
-        }
+            nonce_caches: HashMap::new(),
+            request_policy,
+            session_policy,
+            login_policy,
+            login_throttle,
+            ksf_parameters: KsfParameters::default(),
+            fake_login_candidates: None,
+            server_identity: ServerIdentity::default(),
+            two_factor_storage: None,
+            two_factor_issuer: String::new(),
+            started_at,
+            clock,
+        })
+    }
+
+    // The Argon2 costs handed to clients for new registrations and password changes.
+    pub fn with_ksf_parameters(mut self, ksf_parameters: KsfParameters) -> Self {
+        self.ksf_parameters = ksf_parameters;
+
+        self
+    }
+
+    pub fn with_server_identity(mut self, server_identity: ServerIdentity) -> Self {
+        self.server_identity = server_identity;
+
+        self
+    }
+
+    // Where the TOTP secrets are kept, and the issuer authenticator apps show next to the username.
+    pub fn with_two_factor(mut self, two_factor_storage: FS, issuer: &str) -> Self {
+        self.two_factor_storage = Some(two_factor_storage);
+        self.two_factor_issuer = issuer.to_string();
+
+        self
     }
 }
 
-impl<FS: FileStorage> Authentication for OpaqueAuthentication<FS> {
+impl<FS: FileStorage, SS: SessionStore, C: Clock> Authentication for OpaqueAuthentication<FS, SS, C> {
+    fn opaque_parameters(&self) -> OpaqueParameters {
+        self.server_keyring
+            .current()
+            .parameters(&self.ksf_parameters, &self.server_identity)
+    }
+
     fn start_server_registration(
         &self,
-        username: &str,
+        username: &Username,
         client_registration_message: Vec<u8>,
     ) -> Result<Vec<u8>> {
-        let client_registration_start_result =
-            RegistrationRequest::deserialize(&client_registration_message)
-                .map_err(|error| AuthenticationError::Deserialization(error.to_string()))?;
-
-        let server_registration_start_result = ServerRegistration::<StandardCipherSuite>::start(
-            &self.server_setup,
-            client_registration_start_result,
-            username.as_bytes(),
-        )
-        .map_err(|error| AuthenticationError::Registration(error.to_string()))?;
-
-        Ok(server_registration_start_result
-            .message
-            .serialize()
-            .to_vec())
+        // No existence check here: a taken username would be told apart from a free one, which login start hides.
+        self.start_registration(username.as_str(), client_registration_message)
     }
 
     fn finish_server_registration(
         &self,
-        username: &str,
+        username: &Username,
         client_registration_message: Vec<u8>,
     ) -> Result<()> {
-        let client_registration_finish_result =
-            RegistrationUpload::<StandardCipherSuite>::deserialize(&client_registration_message)
-                .map_err(|error| AuthenticationError::Deserialization(error.to_string()))?;
+        self.ensure_account_does_not_exist(username.as_str())?;
 
-        let password_file = ServerRegistration::finish(client_registration_finish_result);
+        let password_file = self.finish_registration(client_registration_message)?;
 
         self.file_storage
-            .save(username, password_file.serialize().to_vec())
+            .save(username.as_str(), password_file.serialize())
             .map_err(|error| AuthenticationError::PasswordFileSave(error.to_string()))
     }
 
-    fn start_server_login(
-        &mut self,
-        username: &str,
-        client_login_message: Vec<u8>,
+    fn start_password_change(
+        &self,
+        username: &Username,
+        client_registration_message: Vec<u8>,
     ) -> Result<Vec<u8>> {
-        let password_file = self
-            .file_storage
-            .retrieve(username)
-            .map_err(|error| AuthenticationError::PasswordFileRetrieve(error.to_string()))?;
+        self.retrieve_password_file(username.as_str())?;
 
-        let password_file = ServerRegistration::<StandardCipherSuite>::deserialize(&password_file)
-            .map_err(|error| AuthenticationError::Deserialization(error.to_string()))?;
+        self.start_registration(username.as_str(), client_registration_message)
+    }
 
-        let mut server_rng = OsRng;
+    fn finish_password_change(
+        &self,
+        username: &Username,
+        client_registration_message: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        let previous_password_file = self.retrieve_password_file(username.as_str())?;
 
-        let client_login_start_result = CredentialRequest::deserialize(&client_login_message)
-            .map_err(|error| AuthenticationError::Deserialization(error.to_string()))?;
+        let password_file = self.finish_registration(client_registration_message)?;
 
-        let server_login_start_result = ServerLogin::start(
-            &mut server_rng,
-            &self.server_setup,
-            Some(password_file),
-            client_login_start_result,
-            username.as_bytes(),
-            ServerLoginParameters::default(),
-        )
-        .map_err(|error| AuthenticationError::Login(error.to_string()))?;
+        if let Err(error) = self.file_storage.save(username.as_str(), password_file.serialize()) {
+            self.restore_password_file(username, previous_password_file)?;
 
-        self.current_login_sessions
-            .insert(username.to_string(), server_login_start_result.clone());
+            return Err(AuthenticationError::PasswordFileSave(error.to_string()));
+        }
 
-        Ok(server_login_start_result.message.serialize().to_vec())
+        Ok(previous_password_file)
     }
 
-    fn finish_server_login(&mut self, username: &str, client_login_message: Vec<u8>) -> Result<()> {
-        let Some(server_login_start_result) = self.current_login_sessions.remove(username) else {
-            return Err(AuthenticationError::Login(
-                USERNAME_DID_NOT_START_LOGIN_PHASE.to_string(),
-            ));
-        };
-
-        let client_login_start_finish = CredentialFinalization::deserialize(&client_login_message)
-            .map_err(|error| AuthenticationError::Login(error.to_string()))?;
-
-        let server_login_finish_result = server_login_start_result
-            .state
-            .finish(client_login_start_finish, ServerLoginParameters::default())
-            .map_err(|error| AuthenticationError::Login(error.to_string()))?;
+    fn restore_password_file(&self, username: &Username, password_file: Vec<u8>) -> Result<()> {
+        self.file_storage
+            .save(username.as_str(), password_file)
+            .map_err(|error| AuthenticationError::PasswordFileSave(error.to_string()))
+    }
 
-        self.create_session(&server_login_finish_result.session_key, username)?;
+    fn start_server_login(
+        &mut self,
+        username: &Username,
+        client_ip: Option<&str>,
+        client_login_message: Vec<u8>,
+    ) -> Result<LoginStart> {
+        self.start_login(username.as_str(), client_ip, client_login_message, false)
+    }
 
-        Ok(())
+    fn finish_server_login(
+        &mut self,
+        username: &Username,
+        login_id: &str,
+        client_login_message: Vec<u8>,
+        client_label: Option<&str>,
+    ) -> Result<LoginFinish> {
+        self.finish_login(username.as_str(), login_id, client_login_message, client_label, false)
     }
 
     fn verify_bearer_token(&self, bearer_token: &str) -> bool {
 
-        let Some(_) = self.logged_sessions.get(bearer_token) else {
+        let Ok(Some(session)) = self.find_session(bearer_token) else {
+            return false;
+        };
+
+        let Ok(now) = self.clock.now() else {
             return false;
         };
 
-        return true;
+        !self.session_policy.is_expired(&session, now)
     }
 
-    fn verify_signature(
-        &self,
-        bearer_token: &str,
-        verb: &str,
-        uri: &str,
-        timestamp: &str,
-        signature: &str,
-    ) -> Result<bool> {
-        let raw_expected_signature = format!("{}|{}|{}", verb, uri, timestamp);
-
-        let Some(session) = self.logged_sessions.get(bearer_token) else {
+    fn verify_signature(&self, signed_request: &SignedRequest) -> Result<bool> {
+        let Some(session) = self.find_session(&signed_request.bearer_token)? else {
             return Ok(false);
         };
 
         let mut mac = HmacSha512::new_from_slice(&session.session_key)
             .map_err(|error| AuthenticationError::Internal(error.to_string()))?;
 
-        mac.update(raw_expected_signature.as_bytes());
+        mac.update(signature_base(signed_request).as_bytes());
 
-        let expected_signature = &hex::encode(mac.finalize().into_bytes().to_vec());
+        // Decoded first so the comparison is the constant-time one of the MAC, on the raw bytes.
+        let Ok(signature) = hex::decode(&signed_request.signature) else {
+            return Ok(false);
+        };
+
+        Ok(mac.verify_slice(&signature).is_ok())
+    }
 
-        return Ok(signature == expected_signature);
+    fn verify_content_digest(&self, content_digest: &str, body: &[u8]) -> bool {
+        content_digest == request_signature::content_digest(body)
     }
 
-    fn verify_request_timestamp(&self, request_creation_timestamp: &str) -> Result<bool> {
-        let current_timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|error| AuthenticationError::Internal(error.to_string()))?
-            .as_secs();
+    fn verify_request_timestamp(&self, request_creation_timestamp: &str) -> Result<()> {
+        let current_timestamp = self.clock.now()?;
 
         let request_creation_timestamp: u64 =
             request_creation_timestamp
@@ -215,17 +812,342 @@ impl<FS: FileStorage> Authentication for OpaqueAuthentication<FS> {
                     AuthenticationError::Internal(error.to_string())
                 })?;
 
-        return Ok(
-            self.request_max_ttl >= current_timestamp.saturating_sub(request_creation_timestamp)
-        );
+        // The client can resynchronise with `GET /time` and retry, the server time is part of the error for that.
+        if !self.request_policy.accepts(request_creation_timestamp, current_timestamp) {
+            return Err(AuthenticationError::ClockSkew(current_timestamp));
+        }
+
+        Ok(())
+    }
+
+    fn current_timestamp(&self) -> Result<u64> {
+        self.clock.now()
+    }
+
+    fn verify_nonce(&mut self, signed_request: &SignedRequest) -> Result<()> {
+        let now = self.clock.now()?;
+
+        let request_creation_timestamp: u64 = signed_request
+            .timestamp
+            .parse()
+            .map_err(|error: std::num::ParseIntError| AuthenticationError::Internal(error.to_string()))?;
+
+        let session = self.existing_session(&signed_request.bearer_token)?;
+
+        // Nonces are only kept in memory. For a session loaded from the store, a request signed before this server
+        // started may already have been accepted by the previous one.
+        if session.created_at < self.started_at && request_creation_timestamp < self.started_at {
+            return Err(AuthenticationError::ReplayedRequest(signed_request.nonce.clone()));
+        }
+
+        // Past this point the timestamp check rejects the request anyway, the nonce can be forgotten.
+        self.nonce_caches
+            .entry(token_hash(&signed_request.bearer_token))
+            .or_default()
+            .remember(
+            &signed_request.nonce,
+            self.request_policy.expires_at(request_creation_timestamp),
+            now,
+        )
+    }
+
+    fn get_username_from_session(&self, bearer_token: &str) -> Result<Username> {
+
+        Username::parse(&self.existing_session(bearer_token)?.username)
+            .map_err(|error| AuthenticationError::Internal(error.to_string()))
+    }
+
+    fn touch_session(&mut self, bearer_token: &str) -> Result<()> {
+        let now = self.clock.now()?;
+
+        let mut session = self.existing_session(bearer_token)?;
+
+        session.last_used_at = now;
+
+        self.session_store
+            .save(&token_hash(bearer_token), session)
+            .map_err(session_store_error_to_authentication_error)
+    }
+
+    fn purge_expired_sessions(&mut self) -> Result<usize> {
+        let now = self.clock.now()?;
+        let session_policy = self.session_policy;
+
+        // Abandoned handshakes are swept along, they would otherwise wait for the next login start.
+        self.current_login_sessions
+            .retain(|_, pending_login| !pending_login.is_expired(now, &self.login_policy));
+
+        // Failures recorded since the last write are saved here, they would otherwise wait for the next failure.
+        self.login_throttle.flush(now)?;
+
+        self.remove_sessions(&|_, session| !session_policy.is_expired(session, now))
+    }
+
+    // A refreshed session still has its previous token during the grace period, both go.
+    fn revoke_session(&mut self, bearer_token: &str) -> Result<()> {
+        let current_session = self.existing_session(bearer_token)?;
+
+        self.remove_sessions(&|_, session| {
+            session.username != current_session.username || session.session_id != current_session.session_id
+        })?;
+
+        Ok(())
+    }
+
+    fn revoke_other_sessions(&mut self, bearer_token: &str) -> Result<usize> {
+        let current_session = self.existing_session(bearer_token)?;
+
+        self.remove_sessions(&|_, session| {
+            session.username != current_session.username || session.session_id == current_session.session_id
+        })
+    }
+
+    fn revoke_user_sessions(&mut self, username: &Username) -> Result<usize> {
+        self.remove_sessions(&|_, session| session.username != username.as_str())
+    }
+
+    fn list_user_sessions(&self, bearer_token: &str) -> Result<Vec<SessionSummary>> {
+        let current_session = self.existing_session(bearer_token)?;
+
+        let now = self.clock.now()?;
+
+        let stored_sessions = self
+            .session_store
+            .list()
+            .map_err(session_store_error_to_authentication_error)?;
+
+        let mut sessions: Vec<SessionSummary> = stored_sessions
+            .iter()
+            .map(|(_, session)| session)
+            .filter(|session| {
+                session.username == current_session.username
+                    && session.retires_at.is_none()
+                    && !self.session_policy.is_expired(session, now)
+            })
+            .map(|session| SessionSummary {
+                session_id: session.session_id.clone(),
+                created_at: session.created_at,
+                last_used_at: session.last_used_at,
+                client_label: session.client_label.clone(),
+                current: session.session_id == current_session.session_id,
+            })
+            .collect();
+
+        sessions.sort_by_key(|session| session.created_at);
+
+        Ok(sessions)
     }
-    
-    fn get_username_from_session(&self, bearer_token: &str) -> Result<String> {
 
-        let Some(session) = self.logged_sessions.get(bearer_token) else {
-            return Err(AuthenticationError::Internal(SESSION_SHOULD_BE_PRESENT.to_string()));
+    fn revoke_user_session(&mut self, username: &Username, session_id: &str) -> Result<()> {
+        let removed_sessions =
+            self.remove_sessions(&|_, session| session.username != username.as_str() || session.session_id != session_id)?;
+
+        if removed_sessions == 0 {
+            return Err(AuthenticationError::SessionNotFound(session_id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    // The session keeps its id and its creation time, a refresh does not extend its absolute lifetime.
+    // The previous token keeps working for the grace period, so requests already in flight still go through.
+    fn refresh_session(&mut self, bearer_token: &str) -> Result<SessionRefresh> {
+        let now = self.clock.now()?;
+
+        let mut current_session = self.existing_session(bearer_token)?;
+
+        if current_session.retires_at.is_some() {
+            return Err(AuthenticationError::SessionRetired(SESSION_ALREADY_REFRESHED.to_string()));
+        }
+
+        let mut salt = vec![0u8; SESSION_REFRESH_SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+
+        let cipher_suite = CipherSuiteId::from_name(&current_session.cipher_suite)?;
+
+        let (refreshed_session_key, refreshed_session_token) =
+            derive_refreshed_session(cipher_suite, &current_session.session_key, &salt)?;
+
+        let refreshed_session = Session {
+            session_key: refreshed_session_key,
+            last_used_at: now,
+            retires_at: None,
+            ..current_session.clone()
         };
 
-        Ok(session.username.clone())
+        self.session_store
+            .save(&token_hash(&refreshed_session_token), refreshed_session)
+            .map_err(session_store_error_to_authentication_error)?;
+
+        let previous_token_expires_at = now.saturating_add(self.session_policy.refresh_grace_period);
+
+        current_session.retires_at = Some(previous_token_expires_at);
+
+        self.session_store
+            .save(&token_hash(bearer_token), current_session)
+            .map_err(session_store_error_to_authentication_error)?;
+
+        Ok(SessionRefresh {
+            salt,
+            previous_token_expires_at,
+        })
+    }
+
+    fn ensure_second_factor_verified(&self, bearer_token: &str) -> Result<()> {
+        if self.existing_session(bearer_token)?.second_factor_pending {
+            return Err(AuthenticationError::SecondFactorRequired(SECOND_FACTOR_NOT_VERIFIED.to_string()));
+        }
+
+        Ok(())
+    }
+
+    // Starting over replaces a secret that was never confirmed.
+    fn start_two_factor_enrollment(&mut self, username: &Username) -> Result<TwoFactorEnrollment> {
+        self.two_factor_storage()?;
+
+        if self.load_two_factor_record(username.as_str())?.is_some_and(|record| record.enabled) {
+            return Err(AuthenticationError::SecondFactorState(TWO_FACTOR_ALREADY_ENABLED.to_string()));
+        }
+
+        let two_factor_record = TwoFactorRecord::generate();
+
+        self.save_two_factor_record(username.as_str(), &two_factor_record)?;
+
+        let totp = two_factor_record.totp(&self.two_factor_issuer, username.as_str());
+
+        Ok(TwoFactorEnrollment {
+            secret: totp.get_secret_base32(),
+            otpauth_uri: totp.get_url(),
+        })
+    }
+
+    // The first valid code proves the authenticator app holds the secret, only then is the second factor enforced.
+    fn confirm_two_factor_enrollment(&mut self, username: &Username, code: &str) -> Result<Vec<String>> {
+        let mut two_factor_record = match self.load_two_factor_record(username.as_str())? {
+            Some(two_factor_record) if two_factor_record.enabled => {
+                return Err(AuthenticationError::SecondFactorState(TWO_FACTOR_ALREADY_ENABLED.to_string()));
+            }
+            Some(two_factor_record) => two_factor_record,
+            None => return Err(AuthenticationError::SecondFactorState(TWO_FACTOR_NOT_ENROLLED.to_string())),
+        };
+
+        self.check_two_factor_code(username.as_str(), &mut two_factor_record, code, false)?;
+
+        two_factor_record.enabled = true;
+
+        let recovery_codes = two_factor_record.generate_recovery_codes();
+
+        self.save_two_factor_record(username.as_str(), &two_factor_record)?;
+
+        Ok(recovery_codes)
+    }
+
+    fn verify_second_factor(&mut self, bearer_token: &str, code: &str) -> Result<()> {
+        let mut session = self.existing_session(bearer_token)?;
+
+        let mut two_factor_record = self.enabled_two_factor_record(&session.username)?;
+
+        self.check_two_factor_code(&session.username, &mut two_factor_record, code, true)?;
+
+        // Saved first, a used code or recovery code must not be accepted again.
+        self.save_two_factor_record(&session.username, &two_factor_record)?;
+
+        session.second_factor_pending = false;
+
+        self.session_store
+            .save(&token_hash(bearer_token), session)
+            .map_err(session_store_error_to_authentication_error)
+    }
+
+    fn disable_two_factor(&mut self, username: &Username, code: &str) -> Result<()> {
+        let mut two_factor_record = self.enabled_two_factor_record(username.as_str())?;
+
+        self.check_two_factor_code(username.as_str(), &mut two_factor_record, code, true)?;
+
+        delete_file(self.two_factor_storage()?, username.as_str())?;
+
+        Ok(())
+    }
+
+    fn start_recovery_registration(&self, username: &Username, client_registration_message: Vec<u8>) -> Result<Vec<u8>> {
+        self.retrieve_password_file(username.as_str())?;
+
+        self.start_registration(&recovery_file_name(username.as_str()), client_registration_message)
+    }
+
+    // Setting up recovery again replaces the previous credential and wrapped key.
+    fn finish_recovery_registration(
+        &self,
+        username: &Username,
+        client_registration_message: Vec<u8>,
+        wrapped_key: Vec<u8>,
+    ) -> Result<()> {
+        self.retrieve_password_file(username.as_str())?;
+
+        let password_file = self.finish_registration(client_registration_message)?;
+
+        self.file_storage
+            .save(&recovery_file_name(username.as_str()), RecoveryFile::new(password_file, wrapped_key).serialize())
+            .map_err(|error| AuthenticationError::PasswordFileSave(error.to_string()))
+    }
+
+    fn start_recovery_login(
+        &mut self,
+        username: &Username,
+        client_ip: Option<&str>,
+        client_login_message: Vec<u8>,
+    ) -> Result<LoginStart> {
+        self.start_login(username.as_str(), client_ip, client_login_message, true)
+    }
+
+    fn finish_recovery_login(
+        &mut self,
+        username: &Username,
+        login_id: &str,
+        client_login_message: Vec<u8>,
+        client_label: Option<&str>,
+    ) -> Result<LoginFinish> {
+        self.finish_login(username.as_str(), login_id, client_login_message, client_label, true)
+    }
+
+    fn is_recovery_session(&self, bearer_token: &str) -> Result<bool> {
+        Ok(self.existing_session(bearer_token)?.recovery)
+    }
+
+    fn recovery_key(&self, username: &Username) -> Result<Vec<u8>> {
+        self.load_recovery_file(username.as_str())?
+            .map(|recovery_file| recovery_file.wrapped_key)
+            .ok_or(AuthenticationError::RecoveryNotSetUp(RECOVERY_NOT_SET_UP.to_string()))
+    }
+
+    fn ensure_fresh_login(&self, bearer_token: &str) -> Result<()> {
+        let now = self.clock.now()?;
+
+        if !self.session_policy.is_fresh(&self.existing_session(bearer_token)?, now) {
+            return Err(AuthenticationError::FreshLoginRequired(LOGIN_NOT_FRESH.to_string()));
+        }
+
+        Ok(())
+    }
+
+    // Files already gone are skipped, a deletion that failed half way can be run again.
+    fn delete_credentials(&mut self, username: &Username) -> Result<Vec<String>> {
+        let mut deleted = Vec::new();
+
+        if delete_file(&self.file_storage, username.as_str())? {
+            deleted.push(DELETED_PASSWORD_FILE.to_string());
+        }
+
+        if delete_file(&self.file_storage, &recovery_file_name(username.as_str()))? {
+            deleted.push(DELETED_RECOVERY_CREDENTIAL.to_string());
+        }
+
+        if let Some(two_factor_storage) = &self.two_factor_storage
+            && delete_file(two_factor_storage, username.as_str())?
+        {
+            deleted.push(DELETED_TWO_FACTOR.to_string());
+        }
+
+        Ok(deleted)
     }
 }
@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+
+use core_domain::{
+    authentication::authentication_error::{AuthenticationError, Result},
+    ports::file_storage::FileStorage,
+};
+
+use crate::{
+    cipher_suite::{CipherSuiteId, KsfParameters},
+    recovery_file::{RecoveryFile, is_recovery_file_name},
+};
+
+const PASSWORD_FILE_MAGIC: &[u8; 4] = b"FVPF";
+const PASSWORD_FILE_FORMAT_VERSION: u8 = 1;
+const LEGACY_KEY_VERSION: u32 = 1;
+const LIST_PAGE_SIZE: usize = 100;
+
+const UNSUPPORTED_FORMAT_VERSION: &str = "Unsupported password file format version.";
+const UNKNOWN_CIPHER_SUITE: &str = "Password file was made with an unknown cipher suite.";
+const INVALID_CREDENTIAL_IDENTIFIER: &str = "Password file has an invalid credential identifier.";
+
+pub struct PasswordFile {
+    pub key_version: u32,
+    pub cipher_suite: CipherSuiteId,
+    pub ksf_parameters: KsfParameters,
+    pub registration: Vec<u8>,
+    // Only set for credentials registered under a name that was not canonical, the OPRF key was derived from it.
+    pub credential_identifier: Option<String>,
+}
+
+impl PasswordFile {
+    pub fn new(
+        key_version: u32,
+        cipher_suite: CipherSuiteId,
+        ksf_parameters: KsfParameters,
+        registration: Vec<u8>,
+    ) -> Self {
+        Self {
+            key_version,
+            cipher_suite,
+            ksf_parameters,
+            registration,
+            credential_identifier: None,
+        }
+    }
+
+    pub fn with_credential_identifier(mut self, credential_identifier: String) -> Self {
+        self.credential_identifier = Some(credential_identifier);
+        self
+    }
+
+    // After the format version: cipher suite, key version, Argon2 memory, iterations and parallelism,
+    // then a length prefixed credential identifier, empty when the username is the identifier.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut content = Vec::new();
+
+        content.extend_from_slice(PASSWORD_FILE_MAGIC);
+        content.push(PASSWORD_FILE_FORMAT_VERSION);
+        content.push(self.cipher_suite.id());
+        content.extend_from_slice(&self.key_version.to_be_bytes());
+        content.extend_from_slice(&self.ksf_parameters.memory_cost.to_be_bytes());
+        content.extend_from_slice(&self.ksf_parameters.iterations.to_be_bytes());
+        content.extend_from_slice(&self.ksf_parameters.parallelism.to_be_bytes());
+
+        match &self.credential_identifier {
+            Some(credential_identifier) => {
+                content.extend_from_slice(&(credential_identifier.len() as u16).to_be_bytes());
+                content.extend_from_slice(credential_identifier.as_bytes());
+            }
+            None => content.extend_from_slice(&0u16.to_be_bytes()),
+        }
+
+        content.extend_from_slice(&self.registration);
+
+        content
+    }
+
+    // Password files written before key rotation existed are raw registrations made with the first key,
+    // the standard suite and the default Argon2 costs. The username is the identifier of every password file so far.
+    pub fn deserialize(content: &[u8]) -> Result<Self> {
+        let Some(body) = content.strip_prefix(PASSWORD_FILE_MAGIC) else {
+            return Ok(Self::new(
+                LEGACY_KEY_VERSION,
+                CipherSuiteId::Ristretto255Sha512,
+                KsfParameters::default(),
+                content.to_vec(),
+            ));
+        };
+
+        let Some((&format_version, body)) = body.split_first() else {
+            return Err(unsupported_format_version());
+        };
+
+        if format_version != PASSWORD_FILE_FORMAT_VERSION {
+            return Err(unsupported_format_version());
+        }
+
+        let Some((&cipher_suite_id, mut body)) = body.split_first() else {
+            return Err(unsupported_format_version());
+        };
+
+        let Some(cipher_suite) = CipherSuiteId::from_id(cipher_suite_id) else {
+            return Err(AuthenticationError::Deserialization(UNKNOWN_CIPHER_SUITE.to_string()));
+        };
+
+        let key_version = read_u32(&mut body)?;
+
+        let ksf_parameters = KsfParameters {
+            memory_cost: read_u32(&mut body)?,
+            iterations: read_u32(&mut body)?,
+            parallelism: read_u32(&mut body)?,
+        };
+
+        let credential_identifier = read_string(&mut body)?;
+
+        let password_file = Self::new(key_version, cipher_suite, ksf_parameters, body.to_vec());
+
+        match credential_identifier.is_empty() {
+            true => Ok(password_file),
+            false => Ok(password_file.with_credential_identifier(credential_identifier)),
+        }
+    }
+}
+
+// Recovery credentials are counted too, a server key version they use must be kept.
+pub fn count_password_files_by_key_version<FS: FileStorage>(file_storage: &FS) -> Result<BTreeMap<u32, usize>> {
+    let mut counts = BTreeMap::new();
+
+    let mut start_after = None;
+
+    loop {
+        let file_listing = file_storage
+            .list("", start_after.as_deref(), LIST_PAGE_SIZE)
+            .map_err(|error| AuthenticationError::PasswordFileRetrieve(error.to_string()))?;
+
+        for file_name in &file_listing.file_names {
+            let content = file_storage
+                .retrieve(file_name)
+                .map_err(|error| AuthenticationError::PasswordFileRetrieve(error.to_string()))?;
+
+            let password_file = if is_recovery_file_name(file_name) {
+                RecoveryFile::deserialize(&content)?.password_file
+            } else {
+                PasswordFile::deserialize(&content)?
+            };
+
+            *counts.entry(password_file.key_version).or_insert(0) += 1;
+        }
+
+        match file_listing.next_start_after {
+            Some(next_start_after) => start_after = Some(next_start_after),
+            None => return Ok(counts),
+        }
+    }
+}
+
+fn read_u32(input: &mut &[u8]) -> Result<u32> {
+    let Some((bytes, rest)) = input.split_first_chunk::<4>() else {
+        return Err(unsupported_format_version());
+    };
+
+    *input = rest;
+
+    Ok(u32::from_be_bytes(*bytes))
+}
+
+fn read_string(input: &mut &[u8]) -> Result<String> {
+    let Some((length, rest)) = input.split_first_chunk::<2>() else {
+        return Err(unsupported_format_version());
+    };
+
+    let length = u16::from_be_bytes(*length) as usize;
+
+    if rest.len() < length {
+        return Err(unsupported_format_version());
+    }
+
+    let (string, rest) = rest.split_at(length);
+
+    *input = rest;
+
+    String::from_utf8(string.to_vec())
+        .map_err(|_| AuthenticationError::Deserialization(INVALID_CREDENTIAL_IDENTIFIER.to_string()))
+}
+
+fn unsupported_format_version() -> AuthenticationError {
+    AuthenticationError::Deserialization(UNSUPPORTED_FORMAT_VERSION.to_string())
+}
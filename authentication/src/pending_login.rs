@@ -0,0 +1,47 @@
+use crate::cipher_suite::ServerLoginState;
+
+pub struct PendingLogin {
+    pub username: String,
+    pub client_ip: Option<String>,
+    pub server_login: ServerLoginState,
+    pub started_at: u64,
+    pub recovery: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LoginPolicy {
+    pub pending_ttl: u64,
+    // Applies to each client IP and to each username on its own.
+    pub max_pending: usize,
+}
+
+impl PendingLogin {
+    pub fn new(
+        username: String,
+        client_ip: Option<String>,
+        server_login: ServerLoginState,
+        started_at: u64,
+        recovery: bool,
+    ) -> Self {
+        Self {
+            username,
+            client_ip,
+            server_login,
+            started_at,
+            recovery,
+        }
+    }
+
+    pub fn is_expired(&self, now: u64, login_policy: &LoginPolicy) -> bool {
+        now.saturating_sub(self.started_at) > login_policy.pending_ttl
+    }
+}
+
+impl LoginPolicy {
+    pub fn new(pending_ttl: u64, max_pending: usize) -> Self {
+        Self {
+            pending_ttl,
+            max_pending,
+        }
+    }
+}
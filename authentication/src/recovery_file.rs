@@ -0,0 +1,71 @@
+use core_domain::authentication::authentication_error::{AuthenticationError, Result};
+
+use crate::password_file::PasswordFile;
+
+const RECOVERY_FILE_MAGIC: &[u8; 4] = b"FVRF";
+const RECOVERY_FORMAT_VERSION: u8 = 1;
+const RECOVERY_FILE_SUFFIX: &str = ".recovery";
+
+const CORRUPT_RECOVERY_FILE: &str = "Recovery file is corrupt.";
+
+// The recovery credential and the vault key the client wrapped with the recovery secret share one file,
+// replacing one cannot leave it next to the other of a previous setup.
+pub struct RecoveryFile {
+    pub password_file: PasswordFile,
+    pub wrapped_key: Vec<u8>,
+}
+
+impl RecoveryFile {
+    pub fn new(password_file: PasswordFile, wrapped_key: Vec<u8>) -> Self {
+        Self {
+            password_file,
+            wrapped_key,
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let password_file = self.password_file.serialize();
+
+        let mut content = Vec::new();
+
+        content.extend_from_slice(RECOVERY_FILE_MAGIC);
+        content.push(RECOVERY_FORMAT_VERSION);
+        content.extend_from_slice(&(password_file.len() as u32).to_be_bytes());
+        content.extend_from_slice(&password_file);
+        content.extend_from_slice(&self.wrapped_key);
+
+        content
+    }
+
+    pub fn deserialize(content: &[u8]) -> Result<Self> {
+        let body = content
+            .strip_prefix(RECOVERY_FILE_MAGIC)
+            .and_then(|body| body.strip_prefix(&[RECOVERY_FORMAT_VERSION]))
+            .ok_or_else(corrupt_recovery_file)?;
+
+        let (length, body) = body.split_first_chunk::<4>().ok_or_else(corrupt_recovery_file)?;
+        let length = u32::from_be_bytes(*length) as usize;
+
+        if body.len() < length {
+            return Err(corrupt_recovery_file());
+        }
+
+        let (password_file, wrapped_key) = body.split_at(length);
+
+        Ok(Self::new(PasswordFile::deserialize(password_file)?, wrapped_key.to_vec()))
+    }
+}
+
+// Also the OPAQUE credential identifier of the recovery registration, so its OPRF key differs from the primary one.
+pub fn recovery_file_name(username: &str) -> String {
+    format!("{}{}", username, RECOVERY_FILE_SUFFIX)
+}
+
+// Names ending like a recovery file are not usernames, an account named so would share another one's recovery file.
+pub fn is_recovery_file_name(file_name: &str) -> bool {
+    file_name.ends_with(RECOVERY_FILE_SUFFIX)
+}
+
+fn corrupt_recovery_file() -> AuthenticationError {
+    AuthenticationError::Deserialization(CORRUPT_RECOVERY_FILE.to_string())
+}
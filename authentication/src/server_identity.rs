@@ -0,0 +1,28 @@
+use opaque_ke::{Identifiers, ServerLoginParameters};
+
+// Both end up in the OPAQUE transcript, a client expecting another identity or context fails the handshake.
+// The identity is also sealed in the envelope at registration, so it has to stay the same for existing users.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServerIdentity {
+    pub identity: Option<String>,
+    pub context: Option<String>,
+}
+
+impl ServerIdentity {
+    pub fn new(identity: Option<String>, context: Option<String>) -> Self {
+        Self {
+            identity: identity.filter(|identity| !identity.is_empty()),
+            context: context.filter(|context| !context.is_empty()),
+        }
+    }
+
+    pub fn login_parameters(&self) -> ServerLoginParameters<'_, '_> {
+        ServerLoginParameters {
+            context: self.context.as_deref().map(str::as_bytes),
+            identifiers: Identifiers {
+                client: None,
+                server: self.identity.as_deref().map(str::as_bytes),
+            },
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Read, Write},
+    path::Path,
+};
+
+use core_domain::authentication::authentication_error::{AuthenticationError, Result};
+
+use crate::cipher_suite::{CipherSuiteId, ServerKey};
+
+const SERVER_KEY_MAGIC: &[u8; 4] = b"FVSK";
+const SERVER_KEY_FORMAT_VERSION: u8 = 1;
+const FIRST_KEY_VERSION: u32 = 1;
+const TEMPORARY_EXTENSION: &str = "tmp";
+
+#[cfg(unix)]
+const SERVER_KEY_FILE_MODE: u32 = 0o600;
+
+const NOT_A_SERVER_KEY_FILE: &str = "File is not a server key file (wrong magic bytes).";
+const UNSUPPORTED_FORMAT_VERSION: &str = "Unsupported server key file format version.";
+const WRONG_CIPHER_SUITE: &str = "Server key file was generated for an unknown cipher suite.";
+const CORRUPT_SERVER_KEY: &str = "Server key file is corrupt";
+const TRUNCATED_SERVER_KEY: &str = "Server key file is truncated.";
+const MISSING_CURRENT_KEY: &str = "Server key file does not contain its current key version.";
+const MISSING_SERVER_KEY: &str = "Server key file does not exist.";
+const RESTART_BEFORE_ROTATION: &str =
+    "The server has not started with the current key version yet, restart it before rotating again.";
+#[cfg(unix)]
+const PERMISSIONS_TOO_OPEN: &str = "Server key file is accessible by other users, expected mode 0600.";
+
+#[derive(Clone)]
+pub struct ServerKeyring {
+    current_version: u32,
+    // The version the server last started with, it registers users with that key until it restarts.
+    in_use_version: u32,
+    server_setups: BTreeMap<u32, ServerKey>,
+}
+
+impl ServerKeyring {
+    pub fn new(server_key: ServerKey) -> Self {
+        Self {
+            current_version: FIRST_KEY_VERSION,
+            in_use_version: FIRST_KEY_VERSION,
+            server_setups: BTreeMap::from([(FIRST_KEY_VERSION, server_key)]),
+        }
+    }
+
+    pub fn current_version(&self) -> u32 {
+        self.current_version
+    }
+
+    pub fn in_use_version(&self) -> u32 {
+        self.in_use_version
+    }
+
+    pub fn current(&self) -> &ServerKey {
+        &self.server_setups[&self.current_version]
+    }
+
+    pub fn oldest(&self) -> &ServerKey {
+        &self.server_setups[&self.versions()[0]]
+    }
+
+    pub fn get(&self, version: u32) -> Option<&ServerKey> {
+        self.server_setups.get(&version)
+    }
+
+    pub fn versions(&self) -> Vec<u32> {
+        self.server_setups.keys().copied().collect()
+    }
+
+    pub fn mark_current_in_use(&mut self) {
+        self.in_use_version = self.current_version;
+    }
+
+    // The previous keys stay available, existing password files still need them to log in.
+    // A second rotation waits for a restart, the running server may still register users with the key it loaded.
+    pub fn rotate(&mut self) -> Result<u32> {
+        self.rotate_to(self.current().cipher_suite())
+    }
+
+    // Users registered with the previous cipher suite keep logging in with it until they change their password.
+    pub fn rotate_to(&mut self, cipher_suite: CipherSuiteId) -> Result<u32> {
+        if self.in_use_version != self.current_version {
+            return Err(AuthenticationError::ServerKey(RESTART_BEFORE_ROTATION.to_string()));
+        }
+
+        let new_version = self.current_version + 1;
+
+        self.server_setups
+            .insert(new_version, ServerKey::generate(cipher_suite));
+        self.current_version = new_version;
+
+        Ok(new_version)
+    }
+
+    // The key of the running server is kept as well, password files it saves after this still reference it.
+    pub fn retain_used_versions(&mut self, used_versions: &BTreeSet<u32>) -> Vec<u32> {
+        let removed_versions: Vec<u32> = self
+            .server_setups
+            .keys()
+            .copied()
+            .filter(|version| {
+                *version != self.current_version
+                    && *version != self.in_use_version
+                    && !used_versions.contains(version)
+            })
+            .collect();
+
+        for version in &removed_versions {
+            self.server_setups.remove(version);
+        }
+
+        removed_versions
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let temporary_path = Path::new(path).with_extension(TEMPORARY_EXTENSION);
+
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+
+            options.mode(SERVER_KEY_FILE_MODE);
+        }
+
+        let mut file = options
+            .open(&temporary_path)
+            .map_err(|error| server_key_error(path, &error.to_string()))?;
+
+        file.write_all(&encode_keyring(self))
+            .and_then(|_| file.sync_all())
+            .map_err(|error| server_key_error(path, &error.to_string()))?;
+
+        fs::rename(&temporary_path, path).map_err(|error| server_key_error(path, &error.to_string()))
+    }
+}
+
+// Every password file depends on these keys, an unreadable key file must never be replaced silently.
+// The starting server records that it now uses the current version.
+// When the configured cipher suite changes, a key for it becomes the current version.
+pub fn load_or_create_server_keyring(path: &str, cipher_suite: CipherSuiteId) -> Result<ServerKeyring> {
+    let mut server_keyring = match File::open(path) {
+        Ok(file) => load_server_keyring(path, file)?,
+        Err(error) if error.kind() == ErrorKind::NotFound => return create_server_keyring(path, cipher_suite),
+        Err(error) => return Err(server_key_error(path, &error.to_string())),
+    };
+
+    let in_use_version = server_keyring.in_use_version;
+
+    server_keyring.mark_current_in_use();
+
+    if server_keyring.current().cipher_suite() != cipher_suite {
+        server_keyring.rotate_to(cipher_suite)?;
+        server_keyring.mark_current_in_use();
+    }
+
+    if server_keyring.in_use_version != in_use_version {
+        server_keyring.save(path)?;
+    }
+
+    Ok(server_keyring)
+}
+
+// For admin commands: the key file is neither created, marked in use nor rotated to the configured cipher suite by them.
+pub fn load_existing_server_keyring(path: &str) -> Result<ServerKeyring> {
+    match File::open(path) {
+        Ok(file) => load_server_keyring(path, file),
+        Err(error) if error.kind() == ErrorKind::NotFound => Err(server_key_error(path, MISSING_SERVER_KEY)),
+        Err(error) => Err(server_key_error(path, &error.to_string())),
+    }
+}
+
+fn load_server_keyring(path: &str, mut file: File) -> Result<ServerKeyring> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let metadata = file
+            .metadata()
+            .map_err(|error| server_key_error(path, &error.to_string()))?;
+
+        if metadata.permissions().mode() & 0o077 != 0 {
+            return Err(server_key_error(path, PERMISSIONS_TOO_OPEN));
+        }
+    }
+
+    let mut content = Vec::new();
+
+    file.read_to_end(&mut content)
+        .map_err(|error| server_key_error(path, &error.to_string()))?;
+
+    decode_keyring(&content).map_err(|message| server_key_error(path, &message))
+}
+
+fn create_server_keyring(path: &str, cipher_suite: CipherSuiteId) -> Result<ServerKeyring> {
+    if let Some(parent) = Path::new(path).parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent).map_err(|error| server_key_error(path, &error.to_string()))?;
+    }
+
+    let server_keyring = ServerKeyring::new(ServerKey::generate(cipher_suite));
+
+    server_keyring.save(path)?;
+
+    Ok(server_keyring)
+}
+
+// The file lists its keys by version and cipher suite, with the current version and the one the running server started with.
+fn encode_keyring(server_keyring: &ServerKeyring) -> Vec<u8> {
+    let mut content = Vec::new();
+
+    content.extend_from_slice(SERVER_KEY_MAGIC);
+    content.push(SERVER_KEY_FORMAT_VERSION);
+    content.extend_from_slice(&server_keyring.current_version.to_be_bytes());
+    content.extend_from_slice(&server_keyring.in_use_version.to_be_bytes());
+    content.extend_from_slice(&(server_keyring.server_setups.len() as u32).to_be_bytes());
+
+    for (version, server_key) in &server_keyring.server_setups {
+        let serialized_setup = server_key.serialize();
+
+        content.extend_from_slice(&version.to_be_bytes());
+        content.push(server_key.cipher_suite().id());
+        content.extend_from_slice(&(serialized_setup.len() as u32).to_be_bytes());
+        content.extend_from_slice(&serialized_setup);
+    }
+
+    content
+}
+
+fn decode_keyring(content: &[u8]) -> std::result::Result<ServerKeyring, String> {
+    let Some(body) = content.strip_prefix(SERVER_KEY_MAGIC) else {
+        return Err(NOT_A_SERVER_KEY_FILE.to_string());
+    };
+
+    let Some((&format_version, mut body)) = body.split_first() else {
+        return Err(TRUNCATED_SERVER_KEY.to_string());
+    };
+
+    if format_version != SERVER_KEY_FORMAT_VERSION {
+        return Err(UNSUPPORTED_FORMAT_VERSION.to_string());
+    }
+
+    let current_version = read_u32(&mut body)?;
+    let in_use_version = read_u32(&mut body)?;
+    let count = read_u32(&mut body)?;
+
+    let mut server_setups = BTreeMap::new();
+
+    for _ in 0..count {
+        let version = read_u32(&mut body)?;
+
+        let Some((&cipher_suite_id, rest)) = body.split_first() else {
+            return Err(TRUNCATED_SERVER_KEY.to_string());
+        };
+
+        let Some(cipher_suite) = CipherSuiteId::from_id(cipher_suite_id) else {
+            return Err(WRONG_CIPHER_SUITE.to_string());
+        };
+
+        body = rest;
+
+        let length = read_u32(&mut body)? as usize;
+
+        if body.len() < length {
+            return Err(TRUNCATED_SERVER_KEY.to_string());
+        }
+
+        let (serialized_setup, rest) = body.split_at(length);
+
+        server_setups.insert(version, decode_server_setup(cipher_suite, serialized_setup)?);
+        body = rest;
+    }
+
+    if !server_setups.contains_key(&current_version) {
+        return Err(MISSING_CURRENT_KEY.to_string());
+    }
+
+    Ok(ServerKeyring {
+        current_version,
+        in_use_version,
+        server_setups,
+    })
+}
+
+fn decode_server_setup(cipher_suite: CipherSuiteId, serialized_setup: &[u8]) -> std::result::Result<ServerKey, String> {
+    ServerKey::deserialize(cipher_suite, serialized_setup)
+        .map_err(|error| format!("{}: {}", CORRUPT_SERVER_KEY, error))
+}
+
+fn read_u32(input: &mut &[u8]) -> std::result::Result<u32, String> {
+    let Some((bytes, rest)) = input.split_first_chunk::<4>() else {
+        return Err(TRUNCATED_SERVER_KEY.to_string());
+    };
+
+    *input = rest;
+
+    Ok(u32::from_be_bytes(*bytes))
+}
+
+fn server_key_error(path: &str, message: &str) -> AuthenticationError {
+    AuthenticationError::ServerKey(format!("{} ({})", message, path))
+}
@@ -1,17 +1,39 @@
-#[derive(Debug, Default)]
-pub struct Session {
-    pub session_key: Vec<u8>,
-    #[allow(dead_code)]
-    pub session_token: String,
-    pub username: String
+use sha2::{Digest, Sha256};
+
+use core_domain::session_store::session::Session;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SessionPolicy {
+    pub absolute_ttl: u64,
+    pub idle_ttl: u64,
+    pub refresh_grace_period: u64,
+    pub fresh_login_window: u64,
 }
 
-impl Session {
-    pub fn new(session_key: Vec<u8>, session_token: String, username: String) -> Self {
+impl SessionPolicy {
+    pub fn new(absolute_ttl: u64, idle_ttl: u64, refresh_grace_period: u64, fresh_login_window: u64) -> Self {
         Self {
-            session_key,
-            session_token,
-            username
+            absolute_ttl,
+            idle_ttl,
+            refresh_grace_period,
+            fresh_login_window,
         }
     }
-}
\ No newline at end of file
+
+    pub fn is_expired(&self, session: &Session, now: u64) -> bool {
+        now.saturating_sub(session.created_at) > self.absolute_ttl
+            || now.saturating_sub(session.last_used_at) > self.idle_ttl
+            || session.retires_at.is_some_and(|retires_at| now >= retires_at)
+    }
+
+    // A refresh keeps the creation time, only a new login makes a session fresh again.
+    pub fn is_fresh(&self, session: &Session, now: u64) -> bool {
+        now.saturating_sub(session.created_at) <= self.fresh_login_window
+    }
+}
+
+// Sessions are stored under a hash of their token, the map lookup then reveals nothing about the token through timing
+// and the raw token is never kept in memory.
+pub fn token_hash(bearer_token: &str) -> String {
+    hex::encode(Sha256::digest(bearer_token.as_bytes()))
+}
@@ -1 +1,11 @@
-mod opaque_authentication_tests;
\ No newline at end of file
+mod cipher_suite_tests;
+mod legacy_accounts_tests;
+mod login_throttle_tests;
+mod nonce_cache_tests;
+mod opaque_authentication_tests;
+mod password_file_tests;
+mod recovery_file_tests;
+mod request_signature_tests;
+mod server_identity_tests;
+mod server_key_tests;
+mod two_factor_tests;
\ No newline at end of file
@@ -0,0 +1,89 @@
+use crate::cipher_suite::{CipherSuiteId, KsfParameters};
+
+#[test]
+fn should_parse_cipher_suite_names() {
+    // A-rrange
+
+    let names = ["ristretto255-sha512", "p256-sha256"];
+
+    // A-ct
+
+    let results: Vec<_> = names.iter().map(|name| CipherSuiteId::from_name(name)).collect();
+
+    // A-ssert
+
+    assert_eq!(results[0].as_ref().unwrap(), &CipherSuiteId::Ristretto255Sha512);
+    assert_eq!(results[1].as_ref().unwrap(), &CipherSuiteId::P256Sha256);
+
+    for (result, name) in results.iter().zip(names) {
+        assert_eq!(result.as_ref().unwrap().name(), name);
+    }
+}
+
+#[test]
+fn should_not_parse_unknown_cipher_suite_name() {
+    // A-rrange
+
+    let name = "curve25519-sha256";
+
+    // A-ct
+
+    let result = CipherSuiteId::from_name(name);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_map_cipher_suite_ids() {
+    // A-rrange
+
+    let cipher_suites = [CipherSuiteId::Ristretto255Sha512, CipherSuiteId::P256Sha256];
+
+    // A-ct
+
+    let results: Vec<_> = cipher_suites
+        .iter()
+        .map(|cipher_suite| CipherSuiteId::from_id(cipher_suite.id()))
+        .collect();
+
+    // A-ssert
+
+    assert_eq!(results, vec![Some(CipherSuiteId::Ristretto255Sha512), Some(CipherSuiteId::P256Sha256)]);
+    assert_eq!(CipherSuiteId::from_id(0), None);
+}
+
+#[test]
+fn should_format_ksf_parameters() {
+    // A-rrange
+
+    let ksf_parameters = KsfParameters::new(65536, 3, 4).unwrap();
+
+    // A-ct
+
+    let result = ksf_parameters.to_string();
+
+    // A-ssert
+
+    assert_eq!(result, "m=65536,t=3,p=4");
+    assert_eq!(KsfParameters::default().to_string(), "m=19456,t=2,p=1");
+}
+
+#[test]
+fn should_not_accept_invalid_ksf_parameters() {
+    // A-rrange
+
+    let invalid_parameters = [(0, 2, 1), (19456, 0, 1), (19456, 2, 0), (4, 2, 1)];
+
+    // A-ct
+
+    let results: Vec<_> = invalid_parameters
+        .iter()
+        .map(|(memory_cost, iterations, parallelism)| KsfParameters::new(*memory_cost, *iterations, *parallelism))
+        .collect();
+
+    // A-ssert
+
+    assert!(results.iter().all(|result| result.is_err()));
+}
@@ -0,0 +1,215 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use core_domain::{
+    authentication::authentication_error::AuthenticationError,
+    file_storage::{
+        file_listing::FileListing,
+        file_metadata::FileMetadata,
+        file_storage_error::{FileStorageError, Result},
+    },
+    ports::file_storage::FileStorage,
+};
+
+use crate::{
+    cipher_suite::{CipherSuiteId, KsfParameters},
+    legacy_accounts::{canonicalise_files, canonicalise_password_files},
+    password_file::PasswordFile,
+    recovery_file::RecoveryFile,
+};
+
+#[test]
+fn should_canonicalise_files() {
+    // A-rrange
+
+    let mock_file_storage = MockFileStorage::new(String::new());
+
+    mock_file_storage.save("Alice", b"alice vault".to_vec()).unwrap();
+    mock_file_storage.save("bob", b"bob vault".to_vec()).unwrap();
+
+    // A-ct
+
+    let result = canonicalise_files(&mock_file_storage);
+    let second_result = canonicalise_files(&mock_file_storage);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), 1);
+    assert_eq!(second_result.unwrap(), 0);
+    assert_eq!(mock_file_storage.retrieve("alice").unwrap(), b"alice vault");
+    assert_eq!(mock_file_storage.retrieve("bob").unwrap(), b"bob vault");
+    assert!(!mock_file_storage.exists("Alice").unwrap());
+}
+
+#[test]
+fn should_keep_credential_identifier_of_canonicalised_password_files() {
+    // A-rrange
+
+    let mock_file_storage = MockFileStorage::new(String::new());
+
+    mock_file_storage.save("Alice", generate_password_file().serialize()).unwrap();
+    mock_file_storage
+        .save("Alice.recovery", RecoveryFile::new(generate_password_file(), vec![43]).serialize())
+        .unwrap();
+
+    // A-ct
+
+    let result = canonicalise_password_files(&mock_file_storage);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), 2);
+
+    let password_file = PasswordFile::deserialize(&mock_file_storage.retrieve("alice").unwrap()).unwrap();
+    let recovery_file = RecoveryFile::deserialize(&mock_file_storage.retrieve("alice.recovery").unwrap()).unwrap();
+
+    assert_eq!(password_file.credential_identifier, Some(String::from("Alice")));
+    assert_eq!(password_file.registration, vec![42]);
+    assert_eq!(recovery_file.password_file.credential_identifier, Some(String::from("Alice.recovery")));
+    assert_eq!(recovery_file.wrapped_key, vec![43]);
+}
+
+#[test]
+fn should_not_canonicalise_file_over_existing_account() {
+    // A-rrange
+
+    let mock_file_storage = MockFileStorage::new(String::new());
+
+    mock_file_storage.save("Alice", b"legacy vault".to_vec()).unwrap();
+    mock_file_storage.save("alice", b"current vault".to_vec()).unwrap();
+
+    // A-ct
+
+    let result = canonicalise_files(&mock_file_storage);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::AccountAlreadyExists(_)) => {}
+        _ => panic!("Test result should be AccountAlreadyExists."),
+    }
+
+    assert_eq!(mock_file_storage.retrieve("Alice").unwrap(), b"legacy vault");
+    assert_eq!(mock_file_storage.retrieve("alice").unwrap(), b"current vault");
+}
+
+#[test]
+fn should_not_canonicalise_colliding_files() {
+    // A-rrange
+
+    let mock_file_storage = MockFileStorage::new(String::new());
+
+    mock_file_storage.save("ALICE", b"first vault".to_vec()).unwrap();
+    mock_file_storage.save("Alice", b"second vault".to_vec()).unwrap();
+    mock_file_storage.save("Bob", b"bob vault".to_vec()).unwrap();
+
+    // A-ct
+
+    let result = canonicalise_files(&mock_file_storage);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::AccountAlreadyExists(_)) => {}
+        _ => panic!("Test result should be AccountAlreadyExists."),
+    }
+
+    assert!(mock_file_storage.exists("ALICE").unwrap());
+    assert!(mock_file_storage.exists("Alice").unwrap());
+    assert!(mock_file_storage.exists("Bob").unwrap());
+    assert!(!mock_file_storage.exists("alice").unwrap());
+}
+
+#[test]
+fn should_finish_interrupted_canonicalisation() {
+    // A-rrange
+
+    let mock_file_storage = MockFileStorage::new(String::new());
+
+    mock_file_storage.save("Alice", b"alice vault".to_vec()).unwrap();
+    mock_file_storage.save("alice", b"alice vault".to_vec()).unwrap();
+
+    // A-ct
+
+    let result = canonicalise_files(&mock_file_storage);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), 1);
+    assert_eq!(mock_file_storage.retrieve("alice").unwrap(), b"alice vault");
+    assert!(!mock_file_storage.exists("Alice").unwrap());
+}
+
+#[test]
+fn should_not_canonicalise_invalid_stored_name() {
+    // A-rrange
+
+    let mock_file_storage = MockFileStorage::new(String::new());
+
+    mock_file_storage.save("Alice", b"alice vault".to_vec()).unwrap();
+    mock_file_storage.save("notes backup", b"notes".to_vec()).unwrap();
+
+    // A-ct
+
+    let result = canonicalise_files(&mock_file_storage);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Internal(_)) => {}
+        _ => panic!("Test result should be Internal."),
+    }
+
+    assert!(mock_file_storage.exists("Alice").unwrap());
+    assert!(!mock_file_storage.exists("alice").unwrap());
+}
+
+fn generate_password_file() -> PasswordFile {
+    PasswordFile::new(1, CipherSuiteId::Ristretto255Sha512, KsfParameters::default(), vec![42])
+}
+
+struct MockFileStorage {
+    files: RefCell<BTreeMap<String, Vec<u8>>>,
+}
+
+impl FileStorage for MockFileStorage {
+    fn new(_: String) -> Self {
+        Self {
+            files: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    fn retrieve(&self, file_name: &str) -> Result<Vec<u8>> {
+        self.files
+            .borrow()
+            .get(file_name)
+            .cloned()
+            .ok_or_else(|| FileStorageError::FileNotFound(file_name.to_string()))
+    }
+
+    fn save(&self, file_name: &str, content: Vec<u8>) -> Result<()> {
+        self.files.borrow_mut().insert(file_name.to_string(), content);
+
+        Ok(())
+    }
+
+    fn delete(&self, file_name: &str) -> Result<()> {
+        self.files
+            .borrow_mut()
+            .remove(file_name)
+            .map(|_| ())
+            .ok_or_else(|| FileStorageError::FileNotFound(file_name.to_string()))
+    }
+
+    fn exists(&self, file_name: &str) -> Result<bool> {
+        Ok(self.files.borrow().contains_key(file_name))
+    }
+
+    // Pages of one name, the moves have to walk through all of them.
+    fn list(&self, prefix: &str, start_after: Option<&str>, _: usize) -> Result<FileListing> {
+        Ok(FileListing::page(self.files.borrow().keys().cloned().collect(), prefix, start_after, 1))
+    }
+
+    fn metadata(&self, file_name: &str) -> Result<FileMetadata> {
+        Err(FileStorageError::FileNotFound(file_name.to_string()))
+    }
+}
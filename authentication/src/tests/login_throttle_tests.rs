@@ -0,0 +1,304 @@
+use core_domain::authentication::authentication_error::AuthenticationError;
+use tempfile::TempDir;
+
+use crate::login_throttle::{LoginThrottle, ThrottlePolicy, throttle_keys, username_key};
+
+#[test]
+fn should_allow_free_attempts() {
+    // A-rrange
+
+    let mut login_throttle = LoginThrottle::new(generate_throttle_policy());
+    let keys = throttle_keys("username", Some("127.0.0.1"));
+
+    for _ in 0..3 {
+        login_throttle.record_failure(&keys, 1_000).unwrap();
+    }
+
+    // A-ct
+
+    let result = login_throttle.check(&keys, 1_000);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn should_back_off_exponentially() {
+    // A-rrange
+
+    let mut login_throttle = LoginThrottle::new(generate_throttle_policy());
+    let keys = throttle_keys("username", None);
+
+    for _ in 0..6 {
+        login_throttle.record_failure(&keys, 1_000).unwrap();
+    }
+
+    // A-ct
+
+    let result = login_throttle.check(&keys, 1_000);
+    let result_after_delay = login_throttle.check(&keys, 1_004);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Throttled(retry_after)) => assert_eq!(retry_after, 4),
+        _ => panic!("Test result should be Throttled."),
+    }
+
+    assert!(result_after_delay.is_ok());
+}
+
+#[test]
+fn should_cap_backoff_delay() {
+    // A-rrange
+
+    let mut login_throttle = LoginThrottle::new(ThrottlePolicy::new(0, 1, 60, 100, 900));
+    let keys = throttle_keys("username", None);
+
+    for _ in 0..50 {
+        login_throttle.record_failure(&keys, 1_000).unwrap();
+    }
+
+    // A-ct
+
+    let result = login_throttle.check(&keys, 1_000);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Throttled(retry_after)) => assert_eq!(retry_after, 60),
+        _ => panic!("Test result should be Throttled."),
+    }
+}
+
+#[test]
+fn should_lock_out_after_threshold() {
+    // A-rrange
+
+    let mut login_throttle = LoginThrottle::new(generate_throttle_policy());
+    let keys = throttle_keys("username", None);
+
+    for now in 1_000..1_010 {
+        login_throttle.record_failure(&keys, now).unwrap();
+    }
+
+    // A-ct
+
+    let result = login_throttle.check(&keys, 1_100);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Throttled(retry_after)) => assert_eq!(retry_after, 1_009 + 900 - 1_100),
+        _ => panic!("Test result should be Throttled."),
+    }
+}
+
+#[test]
+fn should_not_overflow_with_large_lockout_duration() {
+    // A-rrange
+
+    let mut login_throttle = LoginThrottle::new(ThrottlePolicy::new(0, u64::MAX, u64::MAX, 1, u64::MAX));
+    let keys = throttle_keys("username", None);
+
+    login_throttle.record_failure(&keys, 1_000).unwrap();
+
+    // A-ct
+
+    let result = login_throttle.check(&keys, 1_100);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Throttled(retry_after)) => assert_eq!(retry_after, u64::MAX - 1_100),
+        _ => panic!("Test result should be Throttled."),
+    }
+}
+
+#[test]
+fn should_throttle_client_ip_across_usernames() {
+    // A-rrange
+
+    let mut login_throttle = LoginThrottle::new(generate_throttle_policy());
+
+    for index in 0..10 {
+        login_throttle
+            .record_failure(&throttle_keys(&format!("user{}", index), Some("10.0.0.1")), 1_000)
+            .unwrap();
+    }
+
+    // A-ct
+
+    let result = login_throttle.check(&throttle_keys("another user", Some("10.0.0.1")), 1_000);
+    let other_client_result = login_throttle.check(&throttle_keys("another user", Some("10.0.0.2")), 1_000);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Throttled(_)) => {}
+        _ => panic!("Test result should be Throttled."),
+    }
+
+    assert!(other_client_result.is_ok());
+}
+
+#[test]
+fn should_forget_failures_after_lockout_duration() {
+    // A-rrange
+
+    let mut login_throttle = LoginThrottle::new(generate_throttle_policy());
+    let keys = throttle_keys("username", None);
+
+    for _ in 0..10 {
+        login_throttle.record_failure(&keys, 1_000).unwrap();
+    }
+
+    // A-ct
+
+    login_throttle.record_failure(&throttle_keys("other", None), 1_900).unwrap();
+
+    // A-ssert
+
+    assert!(login_throttle.check(&keys, 1_900).is_ok());
+    assert!(login_throttle.counter(&username_key("username")).is_none());
+}
+
+#[test]
+fn should_reset_username_counter_on_success() {
+    // A-rrange
+
+    let mut login_throttle = LoginThrottle::new(generate_throttle_policy());
+    let keys = throttle_keys("username", Some("127.0.0.1"));
+
+    for _ in 0..5 {
+        login_throttle.record_failure(&keys, 1_000).unwrap();
+    }
+
+    // A-ct
+
+    login_throttle.record_success(&username_key("username"), 1_100).unwrap();
+
+    // A-ssert
+
+    assert!(login_throttle.counter(&username_key("username")).is_none());
+    assert_eq!(login_throttle.counter("ip:127.0.0.1").unwrap().failures, 5);
+}
+
+#[test]
+fn should_keep_counters_across_restarts() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("throttle").join("login_failures");
+    let path = path.to_str().unwrap();
+
+    let mut login_throttle = LoginThrottle::load(path, generate_throttle_policy()).unwrap();
+    let keys = throttle_keys("user name\nwith separators", Some("::1"));
+
+    for _ in 0..10 {
+        login_throttle.record_failure(&keys, 1_000).unwrap();
+    }
+
+    let counter = login_throttle.counter("ip:::1");
+
+    drop(login_throttle);
+
+    // A-ct
+
+    let reloaded_login_throttle = LoginThrottle::load(path, generate_throttle_policy()).unwrap();
+
+    // A-ssert
+
+    match reloaded_login_throttle.check(&keys, 1_000) {
+        Err(AuthenticationError::Throttled(retry_after)) => assert_eq!(retry_after, 900),
+        _ => panic!("Test result should be Throttled."),
+    }
+
+    assert_eq!(reloaded_login_throttle.counter("ip:::1"), counter);
+}
+
+#[test]
+fn should_save_lockout_right_away() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("login_failures");
+    let path = path.to_str().unwrap();
+
+    let mut login_throttle = LoginThrottle::load(path, generate_throttle_policy()).unwrap();
+    let keys = throttle_keys("username", None);
+
+    for _ in 0..10 {
+        login_throttle.record_failure(&keys, 1_000).unwrap();
+    }
+
+    // A-ct
+
+    let reloaded_login_throttle = LoginThrottle::load(path, generate_throttle_policy()).unwrap();
+
+    // A-ssert
+
+    match reloaded_login_throttle.check(&keys, 1_000) {
+        Err(AuthenticationError::Throttled(retry_after)) => assert_eq!(retry_after, 900),
+        _ => panic!("Test result should be Throttled."),
+    }
+}
+
+#[test]
+fn should_batch_saves_of_failures_below_lockout() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("login_failures");
+    let path = path.to_str().unwrap();
+
+    let mut login_throttle = LoginThrottle::load(path, generate_throttle_policy()).unwrap();
+    let keys = throttle_keys("username", None);
+
+    login_throttle.record_failure(&keys, 1_000).unwrap();
+    login_throttle.record_failure(&keys, 1_001).unwrap();
+
+    let batched_counter = LoginThrottle::load(path, generate_throttle_policy())
+        .unwrap()
+        .counter("user:username");
+
+    // A-ct
+
+    login_throttle.flush(1_002).unwrap();
+
+    // A-ssert
+
+    let flushed_counter = LoginThrottle::load(path, generate_throttle_policy())
+        .unwrap()
+        .counter("user:username");
+
+    assert_eq!(batched_counter.unwrap().failures, 1);
+    assert_eq!(flushed_counter.unwrap().failures, 2);
+}
+
+#[test]
+fn should_not_load_corrupt_throttle_file() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("login_failures");
+    let path = path.to_str().unwrap();
+
+    std::fs::write(path, "not a counter").unwrap();
+
+    // A-ct
+
+    let result = LoginThrottle::load(path, generate_throttle_policy());
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Internal(_)) => {}
+        _ => panic!("Test result should be Internal."),
+    }
+}
+
+fn generate_throttle_policy() -> ThrottlePolicy {
+    ThrottlePolicy::new(3, 1, 300, 10, 900)
+}
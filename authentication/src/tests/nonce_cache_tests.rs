@@ -0,0 +1,81 @@
+use core_domain::authentication::authentication_error::AuthenticationError;
+
+use crate::nonce_cache::NonceCache;
+
+#[test]
+fn should_remember_new_nonce() {
+    // A-rrange
+
+    let mut nonce_cache = NonceCache::new(2);
+
+    // A-ct
+
+    let result = nonce_cache.remember("nonce", 1_005, 1_000);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(nonce_cache.len(), 1);
+}
+
+#[test]
+fn should_reject_repeated_nonce() {
+    // A-rrange
+
+    let mut nonce_cache = NonceCache::new(2);
+
+    nonce_cache.remember("nonce", 1_005, 1_000).unwrap();
+
+    // A-ct
+
+    let result = nonce_cache.remember("nonce", 1_006, 1_005);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::ReplayedRequest(nonce)) => assert_eq!(nonce, "nonce"),
+        _ => panic!("Test result should be ReplayedRequest."),
+    }
+}
+
+#[test]
+fn should_forget_expired_nonces() {
+    // A-rrange
+
+    let mut nonce_cache = NonceCache::new(2);
+
+    nonce_cache.remember("first", 1_005, 1_000).unwrap();
+    nonce_cache.remember("second", 1_005, 1_000).unwrap();
+
+    // A-ct
+
+    let result = nonce_cache.remember("third", 1_010, 1_006);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(nonce_cache.len(), 1);
+}
+
+#[test]
+fn should_throttle_when_full_of_live_nonces() {
+    // A-rrange
+
+    let mut nonce_cache = NonceCache::new(2);
+
+    nonce_cache.remember("first", 1_003, 1_000).unwrap();
+    nonce_cache.remember("second", 1_005, 1_000).unwrap();
+
+    // A-ct
+
+    let result = nonce_cache.remember("third", 1_005, 1_001);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Throttled(retry_after)) => assert_eq!(retry_after, 3),
+        _ => panic!("Test result should be Throttled."),
+    }
+
+    assert_eq!(nonce_cache.len(), 2);
+}
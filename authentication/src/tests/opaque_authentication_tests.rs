@@ -2,39 +2,68 @@ use std::{
     fs::{self, File, OpenOptions},
     io::{BufReader, Read, Write},
     path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use core_domain::{
-    file_storage::file_storage_error::Result,
-    ports::{authentication::Authentication, file_storage::FileStorage},
+    authentication::{
+        authentication_error::AuthenticationError, login_start::LoginStart,
+        signed_request::SignedRequest,
+    },
+    domain::username::Username,
+    file_storage::{
+        file_listing::FileListing,
+        file_metadata::FileMetadata,
+        file_storage_error::{FileStorageError, Result},
+    },
+    ports::{authentication::Authentication, file_storage::FileStorage, session_store::SessionStore},
+};
+use session_store::{
+    file_session_store::FileSessionStore, memory_session_store::MemorySessionStore,
+    session_store_key::load_or_create_session_store_key,
 };
 use hkdf::Hkdf;
 use hmac::Mac;
 use opaque_ke::{
-    ClientLogin, ClientLoginFinishParameters, ClientRegistration,
-    ClientRegistrationFinishParameters, CredentialResponse, RegistrationResponse,
+    ClientLogin, ClientLoginFinishParameters, ClientLoginStartResult, ClientRegistration,
+    ClientRegistrationFinishParameters, CredentialResponse, Identifiers, RegistrationResponse,
     ServerRegistration, ServerSetup,
-    rand::{rngs::OsRng, seq::SliceRandom},
+    rand::{RngCore, rngs::OsRng},
+};
+use sha2::{Sha256, Sha512};
+use tempfile::TempDir;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::{
+    cipher_suite::{CipherSuiteId, KsfParameters, ServerKey},
+    clock::Clock,
+    legacy_accounts::canonicalise_password_files,
+    opaque_authentication::{HmacSha512, OpaqueAuthentication, P256CipherSuite, StandardCipherSuite},
+    password_file::PasswordFile,
+    login_throttle::{LoginThrottle, ThrottlePolicy},
+    pending_login::LoginPolicy,
+    request_signature::{RequestPolicy, content_digest, signature_base},
+    server_identity::ServerIdentity,
+    server_key::ServerKeyring,
+    session::{SessionPolicy, token_hash},
 };
-use sha2::Sha512;
-
-use crate::opaque_authentication::{HmacSha512, OpaqueAuthentication, StandardCipherSuite};
 
-const TESTS_FILE_PATH: &'static str = "tests_files/";
+const TOTP_TIME: u64 = 1_700_000_010;
 
 #[test]
 fn should_start_server_registration() {
     // A-rrange
 
-    let request_max_ttl = 5;
+    let request_policy = generate_request_policy();
 
     let username = "username";
     let password = "password";
 
-    let mock_file_storage = generate_mock_file_storage();
-
-    let test_path = &mock_file_storage.path.clone();
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
 
     let mut client_rng = OsRng;
 
@@ -42,12 +71,12 @@ fn should_start_server_registration() {
         ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes())
             .unwrap();
 
-    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, request_max_ttl);
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
 
     // A-ct
 
     let result = opaque_authentication.start_server_registration(
-        username,
+        &generate_username(username),
         client_registration_start_result
             .message
             .serialize()
@@ -65,22 +94,18 @@ fn should_start_server_registration() {
         }
         Err(_) => panic!("Result should be OK."),
     }
-
-    cleanup(&test_path);
 }
 
 #[test]
 fn should_finish_server_registration() {
     // A-rrange
 
-    let request_max_ttl = 5;
+    let request_policy = generate_request_policy();
 
     let username = "username";
     let password = "password";
 
-    let mock_file_storage = generate_mock_file_storage();
-
-    let test_path = &mock_file_storage.path.clone();
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
 
     let mut rng = OsRng;
     let server_setup = ServerSetup::<StandardCipherSuite>::new(&mut rng);
@@ -108,12 +133,12 @@ fn should_finish_server_registration() {
         )
         .unwrap();
 
-    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, request_max_ttl);
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
 
     // A-ct
 
     let result = opaque_authentication.finish_server_registration(
-        username,
+        &generate_username(username),
         client_finish_registration_result
             .message
             .serialize()
@@ -123,26 +148,22 @@ fn should_finish_server_registration() {
     // A-ssert
 
     assert!(result.is_ok());
-
-    cleanup(&test_path);
 }
 
 #[test]
 fn should_start_server_login() {
     // A-rrange
 
-    let request_max_ttl = 5;
+    let request_policy = generate_request_policy();
 
     let username = "username";
     let password = "password";
 
-    let mock_file_storage = generate_mock_file_storage();
-
-    let test_path = &mock_file_storage.path.clone();
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
 
     let mut client_rng = OsRng;
 
-    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, request_max_ttl);
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
 
     let client_registration_start_result =
         ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes())
@@ -150,7 +171,7 @@ fn should_start_server_login() {
 
     let server_registration_start_result = opaque_authentication
         .start_server_registration(
-            username,
+            &generate_username(username),
             client_registration_start_result
                 .message
                 .serialize()
@@ -170,7 +191,7 @@ fn should_start_server_login() {
 
     opaque_authentication
         .finish_server_registration(
-            username,
+            &generate_username(username),
             client_finish_registration_result
                 .message
                 .serialize()
@@ -184,7 +205,8 @@ fn should_start_server_login() {
     // A-ct
 
     let result = opaque_authentication.start_server_login(
-        username,
+        &generate_username(username),
+        None,
         client_login_start_result.message.serialize().to_vec(),
     );
 
@@ -194,31 +216,27 @@ fn should_start_server_login() {
 
     match result {
         Ok(result) => {
-            let response = CredentialResponse::<StandardCipherSuite>::deserialize(&result);
+            let response = CredentialResponse::<StandardCipherSuite>::deserialize(&result.message);
             assert!(response.is_ok());
         }
         Err(_) => panic!("Result should be OK."),
     }
-
-    cleanup(&test_path);
 }
 
 #[test]
 fn should_finish_server_login() {
     // A-rrange
 
-    let request_max_ttl = 5;
+    let request_policy = generate_request_policy();
 
     let username = "username";
     let password = "password";
 
-    let mock_file_storage = generate_mock_file_storage();
-
-    let test_path = &mock_file_storage.path.clone();
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
 
     let mut client_rng = OsRng;
 
-    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, request_max_ttl);
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
 
     let client_registration_start_result =
         ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes())
@@ -226,7 +244,7 @@ fn should_finish_server_login() {
 
     let server_registration_start_result = opaque_authentication
         .start_server_registration(
-            username,
+            &generate_username(username),
             client_registration_start_result
                 .message
                 .serialize()
@@ -246,7 +264,7 @@ fn should_finish_server_login() {
 
     opaque_authentication
         .finish_server_registration(
-            username,
+            &generate_username(username),
             client_finish_registration_result
                 .message
                 .serialize()
@@ -259,7 +277,8 @@ fn should_finish_server_login() {
 
     let server_login_start_result = opaque_authentication
         .start_server_login(
-            username,
+            &generate_username(username),
+            None,
             client_login_start_result.message.serialize().to_vec(),
         )
         .unwrap();
@@ -269,7 +288,7 @@ fn should_finish_server_login() {
         .finish(
             &mut client_rng,
             password.as_bytes(),
-            CredentialResponse::<StandardCipherSuite>::deserialize(&server_login_start_result)
+            CredentialResponse::<StandardCipherSuite>::deserialize(&server_login_start_result.message)
                 .unwrap(),
             ClientLoginFinishParameters::default(),
         )
@@ -278,33 +297,31 @@ fn should_finish_server_login() {
     // A-ct
 
     let result = opaque_authentication.finish_server_login(
-        username,
+        &generate_username(username),
+        &server_login_start_result.login_id,
         client_login_finish_result.message.serialize().to_vec(),
+        None,
     );
 
     // A-ssert
 
     assert!(result.is_ok());
-
-    cleanup(&test_path);
 }
 
 #[test]
 fn should_verify_bearer_token() {
     // A-rrange
 
-    let request_max_ttl = 5;
+    let request_policy = generate_request_policy();
 
     let username = "username";
     let password = "password";
 
-    let mock_file_storage = generate_mock_file_storage();
-
-    let test_path = &mock_file_storage.path.clone();
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
 
     let mut client_rng = OsRng;
 
-    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, request_max_ttl);
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
 
     let client_registration_start_result =
         ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes())
@@ -312,7 +329,7 @@ fn should_verify_bearer_token() {
 
     let server_registration_start_result = opaque_authentication
         .start_server_registration(
-            username,
+            &generate_username(username),
             client_registration_start_result
                 .message
                 .serialize()
@@ -332,7 +349,7 @@ fn should_verify_bearer_token() {
 
     opaque_authentication
         .finish_server_registration(
-            username,
+            &generate_username(username),
             client_finish_registration_result
                 .message
                 .serialize()
@@ -345,7 +362,8 @@ fn should_verify_bearer_token() {
 
     let server_login_start_result = opaque_authentication
         .start_server_login(
-            username,
+            &generate_username(username),
+            None,
             client_login_start_result.message.serialize().to_vec(),
         )
         .unwrap();
@@ -355,7 +373,7 @@ fn should_verify_bearer_token() {
         .finish(
             &mut client_rng,
             password.as_bytes(),
-            CredentialResponse::<StandardCipherSuite>::deserialize(&server_login_start_result)
+            CredentialResponse::<StandardCipherSuite>::deserialize(&server_login_start_result.message)
                 .unwrap(),
             ClientLoginFinishParameters::default(),
         )
@@ -363,8 +381,10 @@ fn should_verify_bearer_token() {
 
     opaque_authentication
         .finish_server_login(
-            username,
+            &generate_username(username),
+            &server_login_start_result.login_id,
             client_login_finish_result.message.serialize().to_vec(),
+            None,
         )
         .unwrap();
 
@@ -377,26 +397,22 @@ fn should_verify_bearer_token() {
     // A-ssert
 
     assert!(result);
-
-    cleanup(&test_path);
 }
 
 #[test]
 fn should_verify_signature() {
     // A-rrange
 
-    let request_max_ttl = 5;
+    let request_policy = generate_request_policy();
 
     let username = "username";
     let password = "password";
 
-    let mock_file_storage = generate_mock_file_storage();
-
-    let test_path = &mock_file_storage.path.clone();
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
 
     let mut client_rng = OsRng;
 
-    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, request_max_ttl);
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
 
     let client_registration_start_result =
         ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes())
@@ -404,7 +420,7 @@ fn should_verify_signature() {
 
     let server_registration_start_result = opaque_authentication
         .start_server_registration(
-            username,
+            &generate_username(username),
             client_registration_start_result
                 .message
                 .serialize()
@@ -424,7 +440,7 @@ fn should_verify_signature() {
 
     opaque_authentication
         .finish_server_registration(
-            username,
+            &generate_username(username),
             client_finish_registration_result
                 .message
                 .serialize()
@@ -437,7 +453,8 @@ fn should_verify_signature() {
 
     let server_login_start_result = opaque_authentication
         .start_server_login(
-            username,
+            &generate_username(username),
+            None,
             client_login_start_result.message.serialize().to_vec(),
         )
         .unwrap();
@@ -447,7 +464,7 @@ fn should_verify_signature() {
         .finish(
             &mut client_rng,
             password.as_bytes(),
-            CredentialResponse::<StandardCipherSuite>::deserialize(&server_login_start_result)
+            CredentialResponse::<StandardCipherSuite>::deserialize(&server_login_start_result.message)
                 .unwrap(),
             ClientLoginFinishParameters::default(),
         )
@@ -455,54 +472,46 @@ fn should_verify_signature() {
 
     opaque_authentication
         .finish_server_login(
-            username,
+            &generate_username(username),
+            &server_login_start_result.login_id,
             client_login_finish_result.message.serialize().to_vec(),
+            None,
         )
         .unwrap();
 
-    let verb = "GET";
-    let uri = "http://localhost";
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs()
         .to_string();
 
-    let client_session_token = create_session(&client_login_finish_result.session_key);
-    let signature = create_client_signature(
-        &format!("{}|{}|{}", verb, uri, timestamp),
+    let signed_request = create_signed_request(
         &client_login_finish_result.session_key,
+        "GET",
+        "/vault",
+        &timestamp,
+        b"",
     );
 
     // A-ct
 
-    let result = opaque_authentication.verify_signature(
-        &client_session_token,
-        verb,
-        uri,
-        &timestamp,
-        &signature,
-    );
+    let result = opaque_authentication.verify_signature(&signed_request);
 
     // A-ssert
 
     assert!(result.is_ok());
     assert!(result.unwrap());
-
-    cleanup(&test_path);
 }
 
 #[test]
 fn should_verify_request_timestamp() {
     // A-rrange
 
-    let request_max_ttl = 5;
-
-    let mock_file_storage = generate_mock_file_storage();
+    let request_policy = generate_request_policy();
 
-    let test_path = &mock_file_storage.path.clone();
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
 
-    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, request_max_ttl);
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
 
     let current_timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -517,22 +526,17 @@ fn should_verify_request_timestamp() {
     // A-ssert
 
     assert!(result.is_ok());
-    assert!(result.unwrap());
-
-    cleanup(test_path);
 }
 
 #[test]
 fn should_not_verify_request_timestamp() {
     // A-rrange
 
-    let request_max_ttl = 5;
-
-    let mock_file_storage = generate_mock_file_storage();
+    let request_policy = generate_request_policy();
 
-    let test_path = &mock_file_storage.path.clone();
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
 
-    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, request_max_ttl);
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
 
     let ten_seconds = Duration::new(10, 0);
 
@@ -548,28 +552,122 @@ fn should_not_verify_request_timestamp() {
 
     // A-ssert
 
-    assert!(result.is_ok());
-    assert!(!result.unwrap());
+    match result {
+        Err(AuthenticationError::ClockSkew(_)) => {}
+        _ => panic!("Test result should be ClockSkew."),
+    }
+}
+
+#[test]
+fn should_not_start_with_failing_clock() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    // A-ct
+
+    let result = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        generate_request_policy(),
+        generate_session_policy(),
+        generate_login_policy(),
+        generate_login_throttle(),
+        FailingClock,
+    );
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Internal(_)) => {}
+        _ => panic!("Test result should be Internal."),
+    }
+}
+
+#[test]
+fn should_verify_request_timestamp_within_clock_skew() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        generate_login_policy(),
+        generate_login_throttle(),
+        MockClock::new(1_000),
+    )
+    .unwrap();
+
+    // A-ct
+
+    let ahead_result = opaque_authentication.verify_request_timestamp("1002");
+    let behind_result = opaque_authentication.verify_request_timestamp("993");
+
+    // A-ssert
+
+    assert!(ahead_result.is_ok());
+    assert!(behind_result.is_ok());
+}
 
-    cleanup(test_path);
+#[test]
+fn should_not_verify_future_request_timestamp() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        generate_login_policy(),
+        generate_login_throttle(),
+        MockClock::new(1_000),
+    )
+    .unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.verify_request_timestamp("1003");
+    let far_future_result = opaque_authentication.verify_request_timestamp(&u64::MAX.to_string());
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::ClockSkew(server_time)) => assert_eq!(server_time, 1_000),
+        _ => panic!("Test result should be ClockSkew."),
+    }
+
+    match far_future_result {
+        Err(AuthenticationError::ClockSkew(_)) => {}
+        _ => panic!("Test result should be ClockSkew."),
+    }
 }
 
 #[test]
 fn should_give_username() {
     // A-rrange
 
-    let request_max_ttl = 5;
+    let request_policy = generate_request_policy();
 
     let username = "username";
     let password = "password";
 
-    let mock_file_storage = generate_mock_file_storage();
-
-    let test_path = &mock_file_storage.path.clone();
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
 
     let mut client_rng = OsRng;
 
-    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, request_max_ttl);
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
 
     let client_registration_start_result =
         ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes())
@@ -577,7 +675,7 @@ fn should_give_username() {
 
     let server_registration_start_result = opaque_authentication
         .start_server_registration(
-            username,
+            &generate_username(username),
             client_registration_start_result
                 .message
                 .serialize()
@@ -597,7 +695,7 @@ fn should_give_username() {
 
     opaque_authentication
         .finish_server_registration(
-            username,
+            &generate_username(username),
             client_finish_registration_result
                 .message
                 .serialize()
@@ -610,7 +708,8 @@ fn should_give_username() {
 
     let server_login_start_result = opaque_authentication
         .start_server_login(
-            username,
+            &generate_username(username),
+            None,
             client_login_start_result.message.serialize().to_vec(),
         )
         .unwrap();
@@ -620,7 +719,7 @@ fn should_give_username() {
         .finish(
             &mut client_rng,
             password.as_bytes(),
-            CredentialResponse::<StandardCipherSuite>::deserialize(&server_login_start_result)
+            CredentialResponse::<StandardCipherSuite>::deserialize(&server_login_start_result.message)
                 .unwrap(),
             ClientLoginFinishParameters::default(),
         )
@@ -628,8 +727,10 @@ fn should_give_username() {
 
     opaque_authentication
         .finish_server_login(
-            username,
+            &generate_username(username),
+            &server_login_start_result.login_id,
             client_login_finish_result.message.serialize().to_vec(),
+            None,
         )
         .unwrap();
 
@@ -642,96 +743,3519 @@ fn should_give_username() {
     // A-ssert
 
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), username);
+    assert_eq!(result.unwrap(), generate_username(username));
+}
+
+#[test]
+fn should_save_password_file_with_current_key_version() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let username = "username";
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let test_path = &mock_file_storage.path.clone();
+
+    let mut client_rng = OsRng;
 
-    cleanup(&test_path);
+    let mut server_keyring = generate_server_keyring();
+    server_keyring.rotate().unwrap();
+
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), server_keyring, request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    // A-ct
+
+    register(&opaque_authentication, &mut client_rng, username, password);
+
+    // A-ssert
+
+    let content = MockFileStorage::new(test_path.clone()).retrieve(username).unwrap();
+    let password_file = PasswordFile::deserialize(&content).unwrap();
+
+    assert_eq!(password_file.key_version, 2);
 }
 
-pub struct MockFileStorage {
-    pub path: String,
+#[test]
+fn should_finish_server_login_with_previous_key_version() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let username = "username";
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let test_path = &mock_file_storage.path.clone();
+
+    let mut client_rng = OsRng;
+
+    let mut server_keyring = generate_server_keyring();
+
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), server_keyring.clone(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, username, password);
+
+    server_keyring.rotate().unwrap();
+
+    let mut opaque_authentication = OpaqueAuthentication::new(MockFileStorage::new(test_path.clone()), MemorySessionStore::new(), server_keyring, request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes()).unwrap();
+
+    let server_login_start_result = opaque_authentication
+        .start_server_login(
+            &generate_username(username),
+            None,
+            client_login_start_result.message.serialize().to_vec(),
+        )
+        .unwrap();
+
+    let client_login_finish_result = client_login_start_result
+        .state
+        .finish(
+            &mut client_rng,
+            password.as_bytes(),
+            CredentialResponse::<StandardCipherSuite>::deserialize(&server_login_start_result.message)
+                .unwrap(),
+            ClientLoginFinishParameters::default(),
+        )
+        .unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.finish_server_login(
+        &generate_username(username),
+        &server_login_start_result.login_id,
+        client_login_finish_result.message.serialize().to_vec(),
+        None,
+    );
+
+    // A-ssert
+
+    assert!(result.is_ok());
 }
 
-impl FileStorage for MockFileStorage {
-    fn new(path: String) -> Self {
-        Self { path }
-    }
+#[test]
+fn should_not_start_server_login_with_removed_key_version() {
+    // A-rrange
 
-    fn retrieve(&self, file_name: &str) -> Result<Vec<u8>> {
-        let file_path = Path::new(&self.path).join(file_name);
+    let request_policy = generate_request_policy();
+
+    let username = "username";
+    let password = "password";
 
-        let file = File::open(&file_path).unwrap();
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
 
-        let mut reader = BufReader::new(file);
-        let mut buffer = Vec::new();
+    let test_path = &mock_file_storage.path.clone();
 
-        reader.read_to_end(&mut buffer).unwrap();
+    let mut client_rng = OsRng;
 
-        Ok(buffer)
-    }
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
 
-    fn save(&self, file_name: &str, vault: Vec<u8>) -> Result<()> {
-        if !Path::new(&self.path).exists() {
-            fs::create_dir_all(&self.path).unwrap();
-        }
+    register(&opaque_authentication, &mut client_rng, username, password);
 
-        let file_path = Path::new(&self.path).join(file_name);
+    let mut server_keyring = generate_server_keyring();
+    server_keyring.rotate().unwrap();
+    server_keyring.mark_current_in_use();
+    server_keyring.retain_used_versions(&Default::default());
 
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(&file_path)
-            .unwrap();
+    let mut opaque_authentication = OpaqueAuthentication::new(MockFileStorage::new(test_path.clone()), MemorySessionStore::new(), server_keyring, request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
 
-        file.write_all(&vault).unwrap();
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes()).unwrap();
 
-        Ok(())
+    // A-ct
+
+    let result = opaque_authentication.start_server_login(
+        &generate_username(username),
+        None,
+        client_login_start_result.message.serialize().to_vec(),
+    );
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::ServerKey(_)) => {}
+        _ => panic!("Test result should be ServerKey."),
     }
 }
 
-fn create_session(session_key: &[u8]) -> String {
-    let hkdf = Hkdf::<Sha512>::from_prk(session_key).unwrap();
+#[test]
+fn should_not_verify_bearer_token_after_absolute_ttl() {
+    // A-rrange
 
-    let mut token = vec![0u8; 64];
+    let request_policy = generate_request_policy();
 
-    hkdf.expand(b"opaque-session-token", &mut token).unwrap();
+    let username = "username";
+    let password = "password";
 
-    hex::encode(token)
-}
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
 
-fn create_client_signature(raw_signature: &str, session_key: &[u8]) -> String {
-    let mut mac = HmacSha512::new_from_slice(&session_key).unwrap();
+    let mut client_rng = OsRng;
 
-    mac.update(raw_signature.as_bytes());
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        SessionPolicy::new(60, 60, 30, 300),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
 
-    hex::encode(mac.finalize().into_bytes().to_vec())
-}
+    register(&opaque_authentication, &mut client_rng, username, password);
 
-fn cleanup(path: &str) {
+    let session_key = login(&mut opaque_authentication, &mut client_rng, username, password);
+    let client_session_token = create_session(&session_key);
 
-    if Path::new(path).exists() {
-        fs::remove_dir_all(path).unwrap();
+    for _ in 0..3 {
+        mock_clock.advance(30);
+        opaque_authentication.touch_session(&client_session_token).unwrap();
     }
 
-    let parent_path = Path::new(TESTS_FILE_PATH);
+    // A-ct
 
-    if parent_path.exists() && parent_path.read_dir().unwrap().next().is_none() {
-        fs::remove_dir_all(TESTS_FILE_PATH).unwrap();
-    }
+    let result = opaque_authentication.verify_bearer_token(&client_session_token);
+
+    // A-ssert
+
+    assert!(!result);
 }
 
-fn generate_mock_file_storage() -> MockFileStorage {
+#[test]
+fn should_not_verify_bearer_token_after_idle_ttl() {
+    // A-rrange
 
-    let mut rng = OsRng;
+    let request_policy = generate_request_policy();
 
-    let range: Vec<i32> = (1..100000).collect();
+    let username = "username";
+    let password = "password";
 
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos()
-        .to_string();
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        SessionPolicy::new(3_600, 60, 30, 300),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, username, password);
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, username, password);
+    let client_session_token = create_session(&session_key);
+
+    mock_clock.advance(61);
+
+    // A-ct
+
+    let result = opaque_authentication.verify_bearer_token(&client_session_token);
+
+    // A-ssert
+
+    assert!(!result);
+}
+
+#[test]
+fn should_verify_bearer_token_when_session_is_used() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let username = "username";
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        SessionPolicy::new(3_600, 60, 30, 300),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, username, password);
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, username, password);
+    let client_session_token = create_session(&session_key);
 
-    let id = range.choose(&mut rng).unwrap();
+    mock_clock.advance(50);
+    opaque_authentication.touch_session(&client_session_token).unwrap();
+    mock_clock.advance(50);
 
-    MockFileStorage::new(format!("{}/{}-{}", TESTS_FILE_PATH, id, timestamp))
-}
\ No newline at end of file
+    // A-ct
+
+    let result = opaque_authentication.verify_bearer_token(&client_session_token);
+
+    // A-ssert
+
+    assert!(result);
+}
+
+#[test]
+fn should_purge_expired_sessions() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let username = "username";
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        SessionPolicy::new(3_600, 60, 30, 300),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, username, password);
+
+    login(&mut opaque_authentication, &mut client_rng, username, password);
+
+    mock_clock.advance(30);
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, username, password);
+    let client_session_token = create_session(&session_key);
+
+    mock_clock.advance(31);
+
+    // A-ct
+
+    let result = opaque_authentication.purge_expired_sessions();
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), 1);
+    assert!(opaque_authentication.verify_bearer_token(&client_session_token));
+}
+
+#[test]
+fn should_revoke_session() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let username = "username";
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, username, password);
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, username, password);
+    let client_session_token = create_session(&session_key);
+
+    // A-ct
+
+    let result = opaque_authentication.revoke_session(&client_session_token);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert!(!opaque_authentication.verify_bearer_token(&client_session_token));
+}
+
+#[test]
+fn should_revoke_user_sessions() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "alice", password);
+    register(&opaque_authentication, &mut client_rng, "bob", password);
+
+    let first_session_key = login(&mut opaque_authentication, &mut client_rng, "alice", password);
+    let second_session_key = login(&mut opaque_authentication, &mut client_rng, "alice", password);
+    let other_user_session_key = login(&mut opaque_authentication, &mut client_rng, "bob", password);
+
+    // A-ct
+
+    let result = opaque_authentication.revoke_user_sessions(&generate_username("alice"));
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), 2);
+    assert!(!opaque_authentication.verify_bearer_token(&create_session(&first_session_key)));
+    assert!(!opaque_authentication.verify_bearer_token(&create_session(&second_session_key)));
+    assert!(opaque_authentication.verify_bearer_token(&create_session(&other_user_session_key)));
+}
+
+#[test]
+fn should_list_user_sessions() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "alice", password);
+    register(&opaque_authentication, &mut client_rng, "bob", password);
+
+    let current_session_key = login(&mut opaque_authentication, &mut client_rng, "alice", password);
+
+    mock_clock.advance(10);
+
+    login(&mut opaque_authentication, &mut client_rng, "alice", password);
+    login(&mut opaque_authentication, &mut client_rng, "bob", password);
+
+    let client_session_token = create_session(&current_session_key);
+
+    // A-ct
+
+    let result = opaque_authentication.list_user_sessions(&client_session_token);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    let sessions = result.unwrap();
+
+    assert_eq!(sessions.len(), 2);
+    assert!(sessions[0].current);
+    assert!(!sessions[1].current);
+    assert_eq!(sessions[0].created_at, 1_000);
+    assert_eq!(sessions[1].created_at, 1_010);
+    assert_eq!(sessions[0].client_label.as_deref(), Some("test client"));
+    assert_ne!(sessions[0].session_id, sessions[1].session_id);
+    assert!(sessions.iter().all(|session| session.session_id != client_session_token));
+}
+
+#[test]
+fn should_revoke_user_session() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "alice", password);
+
+    let current_session_key = login(&mut opaque_authentication, &mut client_rng, "alice", password);
+    let other_session_key = login(&mut opaque_authentication, &mut client_rng, "alice", password);
+
+    let client_session_token = create_session(&current_session_key);
+
+    let other_session_id = opaque_authentication
+        .list_user_sessions(&client_session_token)
+        .unwrap()
+        .into_iter()
+        .find(|session| !session.current)
+        .unwrap()
+        .session_id;
+
+    // A-ct
+
+    let result = opaque_authentication.revoke_user_session(&generate_username("alice"), &other_session_id);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert!(opaque_authentication.verify_bearer_token(&client_session_token));
+    assert!(!opaque_authentication.verify_bearer_token(&create_session(&other_session_key)));
+}
+
+#[test]
+fn should_not_revoke_session_of_another_user() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "alice", password);
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "alice", password);
+
+    let client_session_token = create_session(&session_key);
+
+    let session_id = opaque_authentication
+        .list_user_sessions(&client_session_token)
+        .unwrap()[0]
+        .session_id
+        .clone();
+
+    // A-ct
+
+    let result = opaque_authentication.revoke_user_session(&generate_username("bob"), &session_id);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::SessionNotFound(_)) => {}
+        _ => panic!("Test result should be SessionNotFound."),
+    }
+
+    assert!(opaque_authentication.verify_bearer_token(&client_session_token));
+}
+
+#[test]
+fn should_finish_concurrent_logins_of_same_user() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let username = "username";
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, username, password);
+
+    let (first_client_login, first_login_start) = start_login(&mut opaque_authentication, &mut client_rng, username, password);
+    let (second_client_login, second_login_start) = start_login(&mut opaque_authentication, &mut client_rng, username, password);
+
+    let first_finalization = finish_client_login(first_client_login, &mut client_rng, password, &first_login_start);
+    let second_finalization = finish_client_login(second_client_login, &mut client_rng, password, &second_login_start);
+
+    // A-ct
+
+    let second_result = opaque_authentication.finish_server_login(&generate_username(username), &second_login_start.login_id, second_finalization, None);
+    let first_result = opaque_authentication.finish_server_login(&generate_username(username), &first_login_start.login_id, first_finalization, None);
+
+    // A-ssert
+
+    assert!(first_result.is_ok());
+    assert!(second_result.is_ok());
+    assert_ne!(first_login_start.login_id, second_login_start.login_id);
+}
+
+#[test]
+fn should_not_finish_server_login_with_unknown_login_id() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let username = "username";
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, username, password);
+
+    let (client_login, login_start) = start_login(&mut opaque_authentication, &mut client_rng, username, password);
+
+    let finalization = finish_client_login(client_login, &mut client_rng, password, &login_start);
+
+    // A-ct
+
+    let result = opaque_authentication.finish_server_login(&generate_username(username), username, finalization, None);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Login(_)) => {}
+        _ => panic!("Test result should be Login."),
+    }
+}
+
+#[test]
+fn should_not_finish_server_login_started_by_another_username() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "alice", password);
+
+    let (client_login, login_start) = start_login(&mut opaque_authentication, &mut client_rng, "alice", password);
+
+    let finalization = finish_client_login(client_login, &mut client_rng, password, &login_start);
+
+    // A-ct
+
+    let result = opaque_authentication.finish_server_login(&generate_username("bob"), &login_start.login_id, finalization, None);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Login(_)) => {}
+        _ => panic!("Test result should be Login."),
+    }
+}
+
+#[test]
+fn should_not_finish_expired_server_login() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let username = "username";
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        LoginPolicy::new(30, 100),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, username, password);
+
+    let (client_login, login_start) = start_login(&mut opaque_authentication, &mut client_rng, username, password);
+
+    let finalization = finish_client_login(client_login, &mut client_rng, password, &login_start);
+
+    mock_clock.advance(31);
+
+    // A-ct
+
+    let result = opaque_authentication.finish_server_login(&generate_username(username), &login_start.login_id, finalization, None);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Login(_)) => {}
+        _ => panic!("Test result should be Login."),
+    }
+}
+
+#[test]
+fn should_not_start_server_login_when_too_many_logins_are_pending() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let username = "username";
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        LoginPolicy::new(30, 1),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, username, password);
+
+    start_login(&mut opaque_authentication, &mut client_rng, username, password);
+
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes()).unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.start_server_login(
+        &generate_username(username),
+        None,
+        client_login_start_result.message.serialize().to_vec(),
+    );
+
+    mock_clock.advance(31);
+
+    let result_after_expiry = opaque_authentication.start_server_login(
+        &generate_username(username),
+        None,
+        client_login_start_result.message.serialize().to_vec(),
+    );
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::TooManyPendingLogins(_)) => {}
+        _ => panic!("Test result should be TooManyPendingLogins."),
+    }
+
+    assert!(result_after_expiry.is_ok());
+}
+
+#[test]
+fn should_not_block_other_username_when_one_client_floods_login_starts() {
+    // A-rrange
+
+    let password = "password";
+    let flooding_ip = "203.0.113.7";
+    let other_ip = "198.51.100.1";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        generate_request_policy(),
+        generate_session_policy(),
+        LoginPolicy::new(30, 2),
+        generate_login_throttle(),
+        MockClock::new(1_000),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "alice", password);
+    register(&opaque_authentication, &mut client_rng, "bob", password);
+
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes()).unwrap();
+    let client_login_message = client_login_start_result.message.serialize().to_vec();
+
+    for _ in 0..2 {
+        opaque_authentication
+            .start_server_login(&generate_username("alice"), Some(flooding_ip), client_login_message.clone())
+            .unwrap();
+    }
+
+    let flooding_client_result =
+        opaque_authentication.start_server_login(&generate_username("bob"), Some(flooding_ip), client_login_message.clone());
+
+    let flooded_username_result =
+        opaque_authentication.start_server_login(&generate_username("alice"), Some(other_ip), client_login_message.clone());
+
+    // A-ct
+
+    let result = opaque_authentication.start_server_login(&generate_username("bob"), Some(other_ip), client_login_message);
+
+    // A-ssert
+
+    match flooding_client_result {
+        Err(AuthenticationError::TooManyPendingLogins(_)) => {}
+        _ => panic!("Test result should be TooManyPendingLogins."),
+    }
+
+    match flooded_username_result {
+        Err(AuthenticationError::TooManyPendingLogins(_)) => {}
+        _ => panic!("Test result should be TooManyPendingLogins."),
+    }
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn should_not_finish_server_registration_over_existing_account() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let username = "username";
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let test_path = &mock_file_storage.path.clone();
+
+    let mut client_rng = OsRng;
+
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, username, password);
+
+    let original_password_file = fs::read(Path::new(test_path).join(username)).unwrap();
+
+    let attacker_server_setup = ServerSetup::<StandardCipherSuite>::new(&mut client_rng);
+
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, b"attacker password")
+            .unwrap();
+
+    let server_registration_start_result = ServerRegistration::<StandardCipherSuite>::start(
+        &attacker_server_setup,
+        client_registration_start_result.message,
+        username.as_bytes(),
+    )
+    .unwrap();
+
+    let client_finish_registration_result = client_registration_start_result
+        .state
+        .finish(
+            &mut client_rng,
+            b"attacker password",
+            server_registration_start_result.message,
+            ClientRegistrationFinishParameters::default(),
+        )
+        .unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.finish_server_registration(
+        &generate_username(username),
+        client_finish_registration_result
+            .message
+            .serialize()
+            .to_vec(),
+    );
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::AccountAlreadyExists(_)) => {}
+        _ => panic!("Test result should be AccountAlreadyExists."),
+    }
+
+    assert_eq!(fs::read(Path::new(test_path).join(username)).unwrap(), original_password_file);
+}
+
+#[test]
+fn should_start_server_registration_for_existing_account_like_new_one() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let username = "username";
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, username, password);
+
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes())
+            .unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.start_server_registration(
+        &generate_username(username),
+        client_registration_start_result
+            .message
+            .serialize()
+            .to_vec(),
+    );
+
+    // A-ssert
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn should_change_password() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let username = "username";
+    let old_password = "old password";
+    let new_password = "new password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let test_path = &mock_file_storage.path.clone();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, username, old_password);
+
+    let original_password_file = fs::read(Path::new(test_path).join(username)).unwrap();
+
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, new_password.as_bytes())
+            .unwrap();
+
+    let server_registration_start_result = opaque_authentication
+        .start_password_change(
+            &generate_username(username),
+            client_registration_start_result.message.serialize().to_vec(),
+        )
+        .unwrap();
+
+    let client_finish_registration_result = client_registration_start_result
+        .state
+        .finish(
+            &mut client_rng,
+            new_password.as_bytes(),
+            RegistrationResponse::deserialize(&server_registration_start_result).unwrap(),
+            ClientRegistrationFinishParameters::default(),
+        )
+        .unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.finish_password_change(
+        &generate_username(username),
+        client_finish_registration_result.message.serialize().to_vec(),
+    );
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), original_password_file);
+
+    let (client_login, login_start) = start_login(&mut opaque_authentication, &mut client_rng, username, old_password);
+
+    let old_password_login = client_login.state.finish(
+        &mut client_rng,
+        old_password.as_bytes(),
+        CredentialResponse::<StandardCipherSuite>::deserialize(&login_start.message).unwrap(),
+        ClientLoginFinishParameters::default(),
+    );
+
+    assert!(old_password_login.is_err());
+
+    login(&mut opaque_authentication, &mut client_rng, username, new_password);
+}
+
+#[test]
+fn should_not_start_password_change_for_unknown_account() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, b"password").unwrap();
+
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.start_password_change(
+        &generate_username("username"),
+        client_registration_start_result.message.serialize().to_vec(),
+    );
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::PasswordFileRetrieve(_)) => {}
+        _ => panic!("Test result should be PasswordFileRetrieve."),
+    }
+}
+
+#[test]
+fn should_restore_password_file() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let username = "username";
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let test_path = &mock_file_storage.path.clone();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, username, password);
+
+    let original_password_file = fs::read(Path::new(test_path).join(username)).unwrap();
+
+    fs::write(Path::new(test_path).join(username), b"replaced").unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.restore_password_file(&generate_username(username), original_password_file);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    login(&mut opaque_authentication, &mut client_rng, username, password);
+}
+
+#[test]
+fn should_revoke_other_sessions() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let password = "password";
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "alice", password);
+    register(&opaque_authentication, &mut client_rng, "bob", password);
+
+    let current_session_key = login(&mut opaque_authentication, &mut client_rng, "alice", password);
+    let other_session_key = login(&mut opaque_authentication, &mut client_rng, "alice", password);
+    let other_user_session_key = login(&mut opaque_authentication, &mut client_rng, "bob", password);
+
+    // A-ct
+
+    let result = opaque_authentication.revoke_other_sessions(&create_session(&current_session_key));
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), 1);
+    assert!(opaque_authentication.verify_bearer_token(&create_session(&current_session_key)));
+    assert!(!opaque_authentication.verify_bearer_token(&create_session(&other_session_key)));
+    assert!(opaque_authentication.verify_bearer_token(&create_session(&other_user_session_key)));
+}
+
+#[test]
+fn should_start_server_login_for_unknown_user() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(&mut client_rng, b"password").unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.start_server_login(
+        &generate_username("unknown"),
+        None,
+        client_login_start_result.message.serialize().to_vec(),
+    );
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    let login_start = result.unwrap();
+
+    assert!(CredentialResponse::<StandardCipherSuite>::deserialize(&login_start.message).is_ok());
+
+    let client_login_finish_result = client_login_start_result.state.finish(
+        &mut client_rng,
+        b"password",
+        CredentialResponse::<StandardCipherSuite>::deserialize(&login_start.message).unwrap(),
+        ClientLoginFinishParameters::default(),
+    );
+
+    assert!(client_login_finish_result.is_err());
+}
+
+#[test]
+fn should_fail_unknown_user_login_like_wrong_password() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "alice", "password");
+    register(&opaque_authentication, &mut client_rng, "bob", "password");
+
+    // A well-formed finalization from another handshake, a client never gets one for a failed login.
+    let (client_login, login_start) = start_login(&mut opaque_authentication, &mut client_rng, "bob", "password");
+    let foreign_finalization = finish_client_login(client_login, &mut client_rng, "password", &login_start);
+
+    let (_, wrong_password_login_start) = start_login(&mut opaque_authentication, &mut client_rng, "alice", "wrong password");
+    let (_, unknown_user_login_start) = start_login(&mut opaque_authentication, &mut client_rng, "unknown", "password");
+
+    // A-ct
+
+    let wrong_password_result = opaque_authentication.finish_server_login(
+        &generate_username("alice"),
+        &wrong_password_login_start.login_id,
+        foreign_finalization.clone(),
+        None,
+    );
+
+    let unknown_user_result = opaque_authentication.finish_server_login(
+        &generate_username("unknown"),
+        &unknown_user_login_start.login_id,
+        foreign_finalization,
+        None,
+    );
+
+    // A-ssert
+
+    match (wrong_password_result, unknown_user_result) {
+        (Err(AuthenticationError::Login(wrong_password_error)), Err(AuthenticationError::Login(unknown_user_error))) => {
+            assert_eq!(wrong_password_error, unknown_user_error)
+        }
+        _ => panic!("Test results should both be Login."),
+    }
+}
+
+#[test]
+fn should_throttle_login_after_failed_attempts() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        generate_login_policy(),
+        LoginThrottle::new(ThrottlePolicy::new(0, 60, 300, 10, 900)),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "alice", "password");
+    register(&opaque_authentication, &mut client_rng, "bob", "password");
+
+    let (client_login, login_start) = start_login(&mut opaque_authentication, &mut client_rng, "bob", "password");
+    let foreign_finalization = finish_client_login(client_login, &mut client_rng, "password", &login_start);
+
+    let (_, wrong_password_login_start) = start_login(&mut opaque_authentication, &mut client_rng, "alice", "wrong password");
+
+    opaque_authentication
+        .finish_server_login(&generate_username("alice"), &wrong_password_login_start.login_id, foreign_finalization, None)
+        .unwrap_err();
+
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(&mut client_rng, b"password").unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.start_server_login(
+        &generate_username("alice"),
+        None,
+        client_login_start_result.message.serialize().to_vec(),
+    );
+
+    mock_clock.advance(60);
+
+    let result_after_delay = opaque_authentication.start_server_login(
+        &generate_username("alice"),
+        None,
+        client_login_start_result.message.serialize().to_vec(),
+    );
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Throttled(retry_after)) => assert_eq!(retry_after, 60),
+        _ => panic!("Test result should be Throttled."),
+    }
+
+    assert!(result_after_delay.is_ok());
+}
+
+#[test]
+fn should_not_finish_login_started_before_lockout() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        generate_login_policy(),
+        LoginThrottle::new(ThrottlePolicy::new(0, 60, 300, 1, 900)),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "alice", "password");
+    register(&opaque_authentication, &mut client_rng, "bob", "password");
+
+    let (client_login, login_start) = start_login(&mut opaque_authentication, &mut client_rng, "bob", "password");
+    let foreign_finalization = finish_client_login(client_login, &mut client_rng, "password", &login_start);
+
+    let (_, wrong_password_login_start) = start_login(&mut opaque_authentication, &mut client_rng, "alice", "wrong password");
+    let (client_login, login_start) = start_login(&mut opaque_authentication, &mut client_rng, "alice", "password");
+    let finalization = finish_client_login(client_login, &mut client_rng, "password", &login_start);
+
+    opaque_authentication
+        .finish_server_login(&generate_username("alice"), &wrong_password_login_start.login_id, foreign_finalization, None)
+        .unwrap_err();
+
+    // A-ct
+
+    let result = opaque_authentication.finish_server_login(&generate_username("alice"), &login_start.login_id, finalization, None);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Throttled(retry_after)) => assert_eq!(retry_after, 900),
+        _ => panic!("Test result should be Throttled."),
+    }
+}
+
+#[test]
+fn should_not_accept_replayed_nonce() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+
+    let signed_request = create_signed_request(&session_key, "GET", "/vault", "1000", b"");
+
+    opaque_authentication.verify_nonce(&signed_request).unwrap();
+
+    mock_clock.advance(request_policy.max_ttl);
+
+    // A-ct
+
+    let result = opaque_authentication.verify_nonce(&signed_request);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::ReplayedRequest(nonce)) => assert_eq!(nonce, signed_request.nonce),
+        _ => panic!("Test result should be ReplayedRequest."),
+    }
+}
+
+#[test]
+fn should_accept_fresh_nonces_in_same_session() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+
+    // A-ct
+
+    let results: Vec<_> = (0..10)
+        .map(|_| {
+            let signed_request = create_signed_request(&session_key, "GET", "/vault", "1000", b"");
+
+            opaque_authentication.verify_nonce(&signed_request)
+        })
+        .collect();
+
+    // A-ssert
+
+    assert!(results.iter().all(|result| result.is_ok()));
+}
+
+#[test]
+fn should_register_and_login_with_p256_cipher_suite() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let test_path = &mock_file_storage.path.clone();
+
+    let mut client_rng = OsRng;
+
+    let server_keyring = ServerKeyring::new(ServerKey::generate(CipherSuiteId::P256Sha256));
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), server_keyring, request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register_p256(&opaque_authentication, &mut client_rng, "username", "password");
+
+    // A-ct
+
+    let session_key = login_p256(&mut opaque_authentication, &mut client_rng, "username", "password");
+
+    // A-ssert
+
+    let content = MockFileStorage::new(test_path.clone()).retrieve("username").unwrap();
+    let password_file = PasswordFile::deserialize(&content).unwrap();
+
+    assert_eq!(password_file.cipher_suite, CipherSuiteId::P256Sha256);
+    assert!(!session_key.is_empty());
+}
+
+#[test]
+fn should_keep_previous_cipher_suite_for_existing_users() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let test_path = &mock_file_storage.path.clone();
+
+    let mut client_rng = OsRng;
+
+    let mut server_keyring = generate_server_keyring();
+
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), server_keyring.clone(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "alice", "password");
+
+    server_keyring.rotate_to(CipherSuiteId::P256Sha256).unwrap();
+
+    let mut opaque_authentication = OpaqueAuthentication::new(MockFileStorage::new(test_path.clone()), MemorySessionStore::new(), server_keyring, request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    // A-ct
+
+    let (_, alice_login_start) = start_login(&mut opaque_authentication, &mut client_rng, "alice", "password");
+    let alice_session_key = login(&mut opaque_authentication, &mut client_rng, "alice", "password");
+
+    register_p256(&opaque_authentication, &mut client_rng, "bob", "password");
+    let bob_session_key = login_p256(&mut opaque_authentication, &mut client_rng, "bob", "password");
+
+    // A-ssert
+
+    let content = MockFileStorage::new(test_path.clone()).retrieve("bob").unwrap();
+    let password_file = PasswordFile::deserialize(&content).unwrap();
+
+    assert_eq!(alice_login_start.parameters.cipher_suite, "ristretto255-sha512");
+    assert!(!alice_session_key.is_empty());
+    assert_eq!(password_file.key_version, 2);
+    assert_eq!(password_file.cipher_suite, CipherSuiteId::P256Sha256);
+    assert!(!bob_session_key.is_empty());
+    assert_eq!(opaque_authentication.opaque_parameters().cipher_suite, "p256-sha256");
+}
+
+#[test]
+fn should_record_and_give_back_ksf_parameters() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let test_path = &mock_file_storage.path.clone();
+
+    let mut client_rng = OsRng;
+
+    let ksf_parameters = KsfParameters::new(65536, 3, 1).unwrap();
+
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap()
+        .with_ksf_parameters(ksf_parameters);
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let mut opaque_authentication = OpaqueAuthentication::new(MockFileStorage::new(test_path.clone()), MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    // A-ct
+
+    let (_, known_user_login_start) = start_login(&mut opaque_authentication, &mut client_rng, "username", "password");
+    let (_, unknown_user_login_start) = start_login(&mut opaque_authentication, &mut client_rng, "unknown", "password");
+
+    // A-ssert
+
+    assert_eq!(known_user_login_start.parameters.ksf_parameters, "m=65536,t=3,p=1");
+    assert_eq!(unknown_user_login_start.parameters.ksf_parameters, "m=65536,t=3,p=1");
+}
+
+#[test]
+fn should_keep_fake_parameters_of_unknown_user_across_configuration_change() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let test_path = &mock_file_storage.path.clone();
+
+    let mut client_rng = OsRng;
+
+    let mut server_keyring = generate_server_keyring();
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), server_keyring.clone(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap()
+        .with_ksf_parameters(KsfParameters::new(65536, 3, 1).unwrap());
+
+    register(&opaque_authentication, &mut client_rng, "alice", "password");
+
+    let (_, login_start_before_change) = start_login(&mut opaque_authentication, &mut client_rng, "unknown", "password");
+
+    server_keyring.rotate_to(CipherSuiteId::P256Sha256).unwrap();
+
+    let mut opaque_authentication = OpaqueAuthentication::new(MockFileStorage::new(test_path.clone()), MemorySessionStore::new(), server_keyring, request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap()
+        .with_ksf_parameters(KsfParameters::new(131072, 4, 2).unwrap());
+
+    // A-ct
+
+    let (_, login_start_after_change) = start_login(&mut opaque_authentication, &mut client_rng, "unknown", "password");
+
+    // A-ssert
+
+    assert_eq!(login_start_after_change.parameters, login_start_before_change.parameters);
+    assert_eq!(login_start_after_change.parameters.cipher_suite, "ristretto255-sha512");
+    assert_eq!(login_start_after_change.parameters.ksf_parameters, "m=65536,t=3,p=1");
+}
+
+#[test]
+fn should_login_with_same_server_identity_and_context() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap()
+        .with_server_identity(generate_server_identity());
+
+    register_with_server_identity(&opaque_authentication, &mut client_rng, "username", "password", "vault.example.org");
+
+    // A-ct
+
+    let result = login_with_server_identity(&mut opaque_authentication, &mut client_rng, "username", "password", "vault.example.org", "vault-v1");
+
+    // A-ssert
+
+    assert!(result);
+
+    let opaque_parameters = opaque_authentication.opaque_parameters();
+
+    assert_eq!(opaque_parameters.server_identity.as_deref(), Some("vault.example.org"));
+    assert_eq!(opaque_parameters.context.as_deref(), Some("vault-v1"));
+}
+
+#[test]
+fn should_not_login_against_other_server_identity() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let test_path = &mock_file_storage.path.clone();
+
+    let mut client_rng = OsRng;
+
+    let server_keyring = generate_server_keyring();
+
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), server_keyring.clone(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap()
+        .with_server_identity(generate_server_identity());
+
+    register_with_server_identity(&opaque_authentication, &mut client_rng, "username", "password", "vault.example.org");
+
+    let mut other_opaque_authentication = OpaqueAuthentication::new(MockFileStorage::new(test_path.clone()), MemorySessionStore::new(), server_keyring, request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap()
+        .with_server_identity(ServerIdentity::new(Some(String::from("other.example.org")), Some(String::from("vault-v1"))));
+
+    // A-ct
+
+    let result = login_with_server_identity(&mut other_opaque_authentication, &mut client_rng, "username", "password", "vault.example.org", "vault-v1");
+
+    // A-ssert
+
+    assert!(!result);
+}
+
+#[test]
+fn should_not_login_with_other_context() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap()
+        .with_server_identity(generate_server_identity());
+
+    register_with_server_identity(&opaque_authentication, &mut client_rng, "username", "password", "vault.example.org");
+
+    // A-ct
+
+    let result = login_with_server_identity(&mut opaque_authentication, &mut client_rng, "username", "password", "vault.example.org", "vault-v2");
+
+    // A-ssert
+
+    assert!(!result);
+}
+
+#[test]
+fn should_keep_sessions_across_restart() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let test_path = &mock_file_storage.path.clone();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        load_file_session_store(test_path),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+
+    drop(opaque_authentication);
+
+    mock_clock.advance(10);
+
+    // A-ct
+
+    let mut restarted_opaque_authentication = OpaqueAuthentication::with_clock(
+        MockFileStorage::new(test_path.clone()),
+        load_file_session_store(test_path),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    let signed_request = create_signed_request(&session_key, "GET", "/vault", "1010", b"");
+
+    // A-ssert
+
+    assert!(restarted_opaque_authentication.verify_bearer_token(&signed_request.bearer_token));
+    assert!(restarted_opaque_authentication.verify_signature(&signed_request).unwrap());
+    assert!(restarted_opaque_authentication.verify_nonce(&signed_request).is_ok());
+    assert_eq!(
+        restarted_opaque_authentication.get_username_from_session(&signed_request.bearer_token).unwrap(),
+        generate_username("username")
+    );
+}
+
+#[test]
+fn should_expire_sessions_loaded_after_restart() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let test_path = &mock_file_storage.path.clone();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        load_file_session_store(test_path),
+        generate_server_keyring(),
+        request_policy,
+        SessionPolicy::new(3_600, 60, 30, 300),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+
+    drop(opaque_authentication);
+
+    mock_clock.advance(61);
+
+    let mut restarted_opaque_authentication = OpaqueAuthentication::with_clock(
+        MockFileStorage::new(test_path.clone()),
+        load_file_session_store(test_path),
+        generate_server_keyring(),
+        request_policy,
+        SessionPolicy::new(3_600, 60, 30, 300),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    // A-ct
+
+    let result = restarted_opaque_authentication.verify_bearer_token(&create_session(&session_key));
+    let purged_sessions = restarted_opaque_authentication.purge_expired_sessions().unwrap();
+
+    // A-ssert
+
+    assert!(!result);
+    assert_eq!(purged_sessions, 1);
+    assert_eq!(load_file_session_store(test_path).list().unwrap().len(), 0);
+}
+
+#[test]
+fn should_not_accept_request_signed_before_restart() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let test_path = &mock_file_storage.path.clone();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        load_file_session_store(test_path),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+
+    let signed_request = create_signed_request(&session_key, "GET", "/vault", "1001", b"");
+
+    mock_clock.advance(2);
+
+    opaque_authentication.verify_nonce(&signed_request).unwrap();
+
+    drop(opaque_authentication);
+
+    let mut restarted_opaque_authentication = OpaqueAuthentication::with_clock(
+        MockFileStorage::new(test_path.clone()),
+        load_file_session_store(test_path),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    // A-ct
+
+    let result = restarted_opaque_authentication.verify_nonce(&signed_request);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::ReplayedRequest(_)) => {}
+        _ => panic!("Test result should be ReplayedRequest."),
+    }
+}
+
+#[test]
+fn should_refresh_session() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+
+    mock_clock.advance(10);
+
+    // A-ct
+
+    let result = opaque_authentication.refresh_session(&create_session(&session_key));
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    let session_refresh = result.unwrap();
+    let refreshed_session_key = create_refreshed_session_key(&session_key, &session_refresh.salt);
+    let signed_request = create_signed_request(&refreshed_session_key, "GET", "/vault", "1010", b"");
+
+    assert_eq!(session_refresh.previous_token_expires_at, 1_040);
+    assert_ne!(refreshed_session_key, session_key);
+    assert!(opaque_authentication.verify_bearer_token(&signed_request.bearer_token));
+    assert!(opaque_authentication.verify_signature(&signed_request).unwrap());
+    assert_eq!(
+        opaque_authentication.get_username_from_session(&signed_request.bearer_token).unwrap(),
+        generate_username("username")
+    );
+}
+
+#[test]
+fn should_retire_previous_token_after_grace_period() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+    let previous_session_token = create_session(&session_key);
+
+    let session_refresh = opaque_authentication.refresh_session(&previous_session_token).unwrap();
+    let refreshed_session_key = create_refreshed_session_key(&session_key, &session_refresh.salt);
+
+    mock_clock.advance(29);
+
+    let verified_within_grace_period = opaque_authentication.verify_bearer_token(&previous_session_token);
+
+    mock_clock.advance(1);
+
+    // A-ct
+
+    let result = opaque_authentication.verify_bearer_token(&previous_session_token);
+    let purged_sessions = opaque_authentication.purge_expired_sessions().unwrap();
+
+    // A-ssert
+
+    assert!(verified_within_grace_period);
+    assert!(!result);
+    assert_eq!(purged_sessions, 1);
+    assert!(opaque_authentication.verify_bearer_token(&create_session(&refreshed_session_key)));
+}
+
+#[test]
+fn should_refresh_session_with_its_cipher_suite_hash() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let server_keyring = ServerKeyring::new(ServerKey::generate(CipherSuiteId::P256Sha256));
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), server_keyring, generate_request_policy(), generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register_p256(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login_p256(&mut opaque_authentication, &mut client_rng, "username", "password");
+
+    // A-ct
+
+    let result = opaque_authentication.refresh_session(&create_p256_session(&session_key));
+
+    // A-ssert
+
+    let session_refresh = result.unwrap();
+    let refreshed_session_key = create_p256_refreshed_session_key(&session_key, &session_refresh.salt);
+
+    assert_eq!(session_key.len(), 32);
+    assert_eq!(refreshed_session_key.len(), 32);
+    assert!(opaque_authentication.verify_bearer_token(&create_p256_session(&refreshed_session_key)));
+    assert!(!opaque_authentication.verify_bearer_token(&create_session(&create_refreshed_session_key(&session_key, &session_refresh.salt))));
+}
+
+#[test]
+fn should_not_overflow_refresh_grace_period() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        generate_request_policy(),
+        SessionPolicy::new(3_600, 900, u64::MAX, 300),
+        generate_login_policy(),
+        generate_login_throttle(),
+        MockClock::new(1_000),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+
+    // A-ct
+
+    let result = opaque_authentication.refresh_session(&create_session(&session_key));
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().previous_token_expires_at, u64::MAX);
+}
+
+#[test]
+fn should_not_refresh_session_with_previous_token() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+
+    opaque_authentication.refresh_session(&create_session(&session_key)).unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.refresh_session(&create_session(&session_key));
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::SessionRetired(_)) => {}
+        _ => panic!("Test result should be SessionRetired."),
+    }
+}
+
+#[test]
+fn should_keep_absolute_ttl_of_refreshed_session() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        request_policy,
+        SessionPolicy::new(60, 60, 30, 300),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+
+    mock_clock.advance(50);
+
+    let session_refresh = opaque_authentication.refresh_session(&create_session(&session_key)).unwrap();
+    let refreshed_session_token = create_session(&create_refreshed_session_key(&session_key, &session_refresh.salt));
+
+    mock_clock.advance(11);
+
+    // A-ct
+
+    let result = opaque_authentication.verify_bearer_token(&refreshed_session_token);
+
+    // A-ssert
+
+    assert!(!result);
+}
+
+#[test]
+fn should_revoke_refreshed_session_with_both_tokens() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+    let other_session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+    let previous_session_token = create_session(&session_key);
+
+    let session_refresh = opaque_authentication.refresh_session(&previous_session_token).unwrap();
+    let refreshed_session_token = create_session(&create_refreshed_session_key(&session_key, &session_refresh.salt));
+
+    let listed_sessions = opaque_authentication.list_user_sessions(&refreshed_session_token).unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.revoke_session(&refreshed_session_token);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(listed_sessions.len(), 2);
+    assert_eq!(listed_sessions.iter().filter(|session| session.current).count(), 1);
+    assert!(!opaque_authentication.verify_bearer_token(&refreshed_session_token));
+    assert!(!opaque_authentication.verify_bearer_token(&previous_session_token));
+    assert!(opaque_authentication.verify_bearer_token(&create_session(&other_session_key)));
+}
+
+#[test]
+fn should_keep_refreshed_session_across_restart() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let test_path = &mock_file_storage.path.clone();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(1_000);
+
+    let mut opaque_authentication = OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        load_file_session_store(test_path),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+    let previous_session_token = create_session(&session_key);
+
+    let session_refresh = opaque_authentication.refresh_session(&previous_session_token).unwrap();
+    let refreshed_session_token = create_session(&create_refreshed_session_key(&session_key, &session_refresh.salt));
+
+    drop(opaque_authentication);
+
+    mock_clock.advance(30);
+
+    // A-ct
+
+    let restarted_opaque_authentication = OpaqueAuthentication::with_clock(
+        MockFileStorage::new(test_path.clone()),
+        load_file_session_store(test_path),
+        generate_server_keyring(),
+        request_policy,
+        generate_session_policy(),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap();
+
+    // A-ssert
+
+    assert!(restarted_opaque_authentication.verify_bearer_token(&refreshed_session_token));
+    assert!(!restarted_opaque_authentication.verify_bearer_token(&previous_session_token));
+}
+
+#[test]
+fn should_require_second_factor_after_login() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(TOTP_TIME);
+
+    let mut opaque_authentication = generate_two_factor_authentication(mock_file_storage, &mock_clock);
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    enable_two_factor(&mut opaque_authentication, &mut client_rng, &mock_clock, "username");
+
+    let (client_login_start_result, login_start) =
+        start_login(&mut opaque_authentication, &mut client_rng, "username", "password");
+
+    let client_login_finish_result = client_login_start_result
+        .state
+        .finish(
+            &mut client_rng,
+            b"password",
+            CredentialResponse::<StandardCipherSuite>::deserialize(&login_start.message).unwrap(),
+            ClientLoginFinishParameters::default(),
+        )
+        .unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.finish_server_login(
+        &generate_username("username"),
+        &login_start.login_id,
+        client_login_finish_result.message.serialize().to_vec(),
+        None,
+    );
+
+    // A-ssert
+
+    assert!(result.unwrap().second_factor_required);
+
+    let session_token = create_session(&client_login_finish_result.session_key);
+
+    assert!(opaque_authentication.verify_bearer_token(&session_token));
+
+    match opaque_authentication.ensure_second_factor_verified(&session_token) {
+        Err(AuthenticationError::SecondFactorRequired(_)) => {}
+        _ => panic!("Test result should be SecondFactorRequired."),
+    }
+}
+
+#[test]
+fn should_verify_second_factor_with_code() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(TOTP_TIME);
+
+    let mut opaque_authentication = generate_two_factor_authentication(mock_file_storage, &mock_clock);
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let (secret, _) = enable_two_factor(&mut opaque_authentication, &mut client_rng, &mock_clock, "username");
+
+    let session_token = create_session(&login(&mut opaque_authentication, &mut client_rng, "username", "password"));
+
+    mock_clock.advance(30);
+
+    // A-ct
+
+    let result = opaque_authentication.verify_second_factor(&session_token, &generate_totp_code(&secret, TOTP_TIME + 30));
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert!(opaque_authentication.ensure_second_factor_verified(&session_token).is_ok());
+}
+
+#[test]
+fn should_verify_second_factor_with_recovery_code_once() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(TOTP_TIME);
+
+    let mut opaque_authentication = generate_two_factor_authentication(mock_file_storage, &mock_clock);
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let (_, recovery_codes) = enable_two_factor(&mut opaque_authentication, &mut client_rng, &mock_clock, "username");
+
+    let session_token = create_session(&login(&mut opaque_authentication, &mut client_rng, "username", "password"));
+    let other_session_token = create_session(&login(&mut opaque_authentication, &mut client_rng, "username", "password"));
+
+    // A-ct
+
+    let result = opaque_authentication.verify_second_factor(&session_token, &recovery_codes[0]);
+    let reused_result = opaque_authentication.verify_second_factor(&other_session_token, &recovery_codes[0]);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert!(opaque_authentication.ensure_second_factor_verified(&session_token).is_ok());
+
+    match reused_result {
+        Err(AuthenticationError::InvalidSecondFactorCode(_)) => {}
+        _ => panic!("Test result should be InvalidSecondFactorCode."),
+    }
+}
+
+#[test]
+fn should_throttle_wrong_second_factor_codes() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(TOTP_TIME);
+
+    let mut opaque_authentication = generate_two_factor_authentication(mock_file_storage, &mock_clock);
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let (secret, _) = enable_two_factor(&mut opaque_authentication, &mut client_rng, &mock_clock, "username");
+
+    let session_token = create_session(&login(&mut opaque_authentication, &mut client_rng, "username", "password"));
+
+    mock_clock.advance(30);
+
+    let valid_code = generate_totp_code(&secret, TOTP_TIME + 30);
+    let wrong_code = format!("{:06}", (valid_code.parse::<u32>().unwrap() + 1) % 1_000_000);
+
+    for _ in 0..4 {
+        assert!(opaque_authentication.verify_second_factor(&session_token, &wrong_code).is_err());
+    }
+
+    // A-ct
+
+    let result = opaque_authentication.verify_second_factor(&session_token, &valid_code);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Throttled(_)) => {}
+        _ => panic!("Test result should be Throttled."),
+    }
+}
+
+#[test]
+fn should_disable_two_factor_with_valid_code_only() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let test_path = &mock_file_storage.path.clone();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(TOTP_TIME);
+
+    let mut opaque_authentication = generate_two_factor_authentication(mock_file_storage, &mock_clock);
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let (secret, _) = enable_two_factor(&mut opaque_authentication, &mut client_rng, &mock_clock, "username");
+
+    mock_clock.advance(30);
+
+    let valid_code = generate_totp_code(&secret, TOTP_TIME + 30);
+    let wrong_code = format!("{:06}", (valid_code.parse::<u32>().unwrap() + 1) % 1_000_000);
+
+    // A-ct
+
+    let wrong_code_result = opaque_authentication.disable_two_factor(&generate_username("username"), &wrong_code);
+    let result = opaque_authentication.disable_two_factor(&generate_username("username"), &valid_code);
+
+    // A-ssert
+
+    assert!(wrong_code_result.is_err());
+    assert!(result.is_ok());
+
+    let session_token = create_session(&login(&mut opaque_authentication, &mut client_rng, "username", "password"));
+
+    assert!(opaque_authentication.ensure_second_factor_verified(&session_token).is_ok());
+    assert!(!Path::new(test_path).join("two_factor").join("username").exists());
+}
+
+#[test]
+fn should_not_enable_two_factor_with_wrong_code() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(TOTP_TIME);
+
+    let mut opaque_authentication = generate_two_factor_authentication(mock_file_storage, &mock_clock);
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let two_factor_enrollment = opaque_authentication.start_two_factor_enrollment(&generate_username("username")).unwrap();
+
+    let valid_code = generate_totp_code(&two_factor_enrollment.secret, TOTP_TIME);
+    let wrong_code = format!("{:06}", (valid_code.parse::<u32>().unwrap() + 1) % 1_000_000);
+
+    // A-ct
+
+    let result = opaque_authentication.confirm_two_factor_enrollment(&generate_username("username"), &wrong_code);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::InvalidSecondFactorCode(_)) => {}
+        _ => panic!("Test result should be InvalidSecondFactorCode."),
+    }
+
+    let session_token = create_session(&login(&mut opaque_authentication, &mut client_rng, "username", "password"));
+
+    assert!(opaque_authentication.ensure_second_factor_verified(&session_token).is_ok());
+    assert!(two_factor_enrollment.otpauth_uri.starts_with("otpauth://totp/Vault:username?"));
+}
+
+#[test]
+fn should_not_enroll_second_factor_without_two_factor_storage() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    // A-ct
+
+    let result = opaque_authentication.start_two_factor_enrollment(&generate_username("username"));
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::SecondFactorState(_)) => {}
+        _ => panic!("Test result should be SecondFactorState."),
+    }
+}
+
+#[test]
+fn should_log_in_with_recovery_credential() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), generate_request_policy(), generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    set_up_recovery(&opaque_authentication, &mut client_rng, "username", "recovery secret", b"wrapped key");
+
+    // A-ct
+
+    let session_key = recovery_login(&mut opaque_authentication, &mut client_rng, "username", "recovery secret");
+
+    // A-ssert
+
+    let session_token = create_session(&session_key);
+
+    assert!(opaque_authentication.verify_bearer_token(&session_token));
+    assert!(opaque_authentication.is_recovery_session(&session_token).unwrap());
+    assert_eq!(opaque_authentication.recovery_key(&generate_username("username")).unwrap(), b"wrapped key".to_vec());
+
+    let login_session_token = create_session(&login(&mut opaque_authentication, &mut client_rng, "username", "password"));
+
+    assert!(!opaque_authentication.is_recovery_session(&login_session_token).unwrap());
+}
+
+#[test]
+fn should_not_recover_with_master_password() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), generate_request_policy(), generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    set_up_recovery(&opaque_authentication, &mut client_rng, "username", "recovery secret", b"wrapped key");
+
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(&mut client_rng, b"password").unwrap();
+
+    let login_start = opaque_authentication
+        .start_recovery_login(&generate_username("username"), None, client_login_start_result.message.serialize().to_vec())
+        .unwrap();
+
+    // A-ct
+
+    let result = client_login_start_result.state.finish(
+        &mut client_rng,
+        b"password",
+        CredentialResponse::<StandardCipherSuite>::deserialize(&login_start.message).unwrap(),
+        ClientLoginFinishParameters::default(),
+    );
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_answer_recovery_login_without_recovery_credential() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), generate_request_policy(), generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(&mut client_rng, b"recovery secret").unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.start_recovery_login(
+        &generate_username("username"),
+        None,
+        client_login_start_result.message.serialize().to_vec(),
+    );
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    match opaque_authentication.recovery_key(&generate_username("username")) {
+        Err(AuthenticationError::RecoveryNotSetUp(_)) => {}
+        _ => panic!("Test result should be RecoveryNotSetUp."),
+    }
+}
+
+#[test]
+fn should_not_finish_recovery_login_as_login() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), generate_request_policy(), generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    set_up_recovery(&opaque_authentication, &mut client_rng, "username", "recovery secret", b"wrapped key");
+
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(&mut client_rng, b"recovery secret").unwrap();
+
+    let login_start = opaque_authentication
+        .start_recovery_login(&generate_username("username"), None, client_login_start_result.message.serialize().to_vec())
+        .unwrap();
+
+    let client_login_finish_message =
+        finish_client_login(client_login_start_result, &mut client_rng, "recovery secret", &login_start);
+
+    // A-ct
+
+    let result = opaque_authentication.finish_server_login(&generate_username("username"), &login_start.login_id, client_login_finish_message, None);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::Login(_)) => {}
+        _ => panic!("Test result should be Login."),
+    }
+}
+
+#[test]
+fn should_not_register_recovery_file_name() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), generate_request_policy(), generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, b"password").unwrap();
+
+    let server_registration_start_result = opaque_authentication
+        .start_server_registration(
+            &generate_username("username.recovery"),
+            client_registration_start_result.message.serialize().to_vec(),
+        )
+        .unwrap();
+
+    let client_finish_registration_result = client_registration_start_result
+        .state
+        .finish(
+            &mut client_rng,
+            b"password",
+            RegistrationResponse::deserialize(&server_registration_start_result).unwrap(),
+            ClientRegistrationFinishParameters::default(),
+        )
+        .unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.finish_server_registration(
+        &generate_username("username.recovery"),
+        client_finish_registration_result.message.serialize().to_vec(),
+    );
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::AccountAlreadyExists(_)) => {}
+        _ => panic!("Test result should be AccountAlreadyExists."),
+    }
+}
+
+#[test]
+fn should_not_set_up_recovery_for_unknown_account() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), generate_request_policy(), generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, b"recovery secret").unwrap();
+
+    // A-ct
+
+    let result = opaque_authentication.start_recovery_registration(
+        &generate_username("username"),
+        client_registration_start_result.message.serialize().to_vec(),
+    );
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::PasswordFileRetrieve(_)) => {}
+        _ => panic!("Test result should be PasswordFileRetrieve."),
+    }
+}
+
+#[test]
+fn should_verify_uppercase_hex_signature() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+
+    let mut signed_request = create_signed_request(&session_key, "GET", "/vault", "42", b"");
+    signed_request.signature = signed_request.signature.to_uppercase();
+
+    // A-ct
+
+    let result = opaque_authentication.verify_signature(&signed_request);
+
+    // A-ssert
+
+    assert!(result.unwrap());
+}
+
+#[test]
+fn should_not_verify_malformed_signature() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+
+    let signed_request = create_signed_request(&session_key, "GET", "/vault", "42", b"");
+    let signature = signed_request.signature.clone();
+
+    let malformed_signatures = [
+        String::new(),
+        format!("zz{}", &signature[2..]),
+        signature[1..].to_string(),
+        format!("{} ", signature),
+        signature[..64].to_string(),
+        format!("{}00", signature),
+    ];
+
+    // A-ct
+
+    let results: Vec<bool> = malformed_signatures
+        .into_iter()
+        .map(|malformed_signature| {
+            let mut malformed_request = signed_request.clone();
+            malformed_request.signature = malformed_signature;
+
+            opaque_authentication.verify_signature(&malformed_request).unwrap()
+        })
+        .collect();
+
+    // A-ssert
+
+    assert!(results.iter().all(|result| !result));
+}
+
+#[test]
+fn should_not_verify_token_hash_as_bearer_token() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+    let client_session_token = create_session(&session_key);
+
+    // A-ct
+
+    let result = opaque_authentication.verify_bearer_token(&token_hash(&client_session_token));
+
+    // A-ssert
+
+    assert!(opaque_authentication.verify_bearer_token(&client_session_token));
+    assert!(!result);
+}
+
+#[test]
+fn should_not_verify_signature_of_tampered_request() {
+    // A-rrange
+
+    let request_policy = generate_request_policy();
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), request_policy, generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "username", "password");
+
+    let signed_request = create_signed_request(&session_key, "POST", "/vault", "42", b"vault");
+
+    let mut other_body_request = signed_request.clone();
+    other_body_request.content_digest = content_digest(b"attacker vault");
+
+    let mut other_path_request = signed_request.clone();
+    other_path_request.path = String::from("/account/password/finish");
+
+    let mut other_query_request = signed_request.clone();
+    other_query_request.query = String::from("force=true");
+
+    let mut other_nonce_request = signed_request.clone();
+    other_nonce_request.nonce = generate_nonce();
+
+    // A-ct
+
+    let results = [
+        opaque_authentication.verify_signature(&other_body_request),
+        opaque_authentication.verify_signature(&other_path_request),
+        opaque_authentication.verify_signature(&other_query_request),
+        opaque_authentication.verify_signature(&other_nonce_request),
+    ];
+
+    // A-ssert
+
+    for result in results {
+        assert!(!result.unwrap());
+    }
+}
+
+#[test]
+fn should_verify_content_digest() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), generate_server_keyring(), generate_request_policy(), generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    // A-ct
+
+    let matching_result = opaque_authentication.verify_content_digest(&content_digest(b"vault"), b"vault");
+    let other_body_result = opaque_authentication.verify_content_digest(&content_digest(b"vault"), b"attacker vault");
+    let malformed_result = opaque_authentication.verify_content_digest("sha-256=:abc:", b"vault");
+
+    // A-ssert
+
+    assert!(matching_result);
+    assert!(!other_body_result);
+    assert!(!malformed_result);
+}
+
+#[test]
+fn should_delete_credentials() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(TOTP_TIME);
+
+    let mut opaque_authentication = generate_two_factor_authentication(mock_file_storage, &mock_clock);
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+    set_up_recovery(&opaque_authentication, &mut client_rng, "username", "recovery secret", &[1, 2, 3]);
+    enable_two_factor(&mut opaque_authentication, &mut client_rng, &mock_clock, "username");
+
+    // A-ct
+
+    let result = opaque_authentication.delete_credentials(&generate_username("username"));
+    let second_result = opaque_authentication.delete_credentials(&generate_username("username"));
+
+    // A-ssert
+
+    assert_eq!(
+        result.unwrap(),
+        vec![
+            String::from("password_file"),
+            String::from("recovery_credential"),
+            String::from("two_factor"),
+        ]
+    );
+    assert!(second_result.unwrap().is_empty());
+
+    let client_login_start_result = ClientLogin::<StandardCipherSuite>::start(&mut client_rng, b"password").unwrap();
+
+    let login_start = opaque_authentication
+        .start_server_login(&generate_username("username"), None, client_login_start_result.message.serialize().to_vec())
+        .unwrap();
+
+    // The account is gone, the login answers like for any unknown username.
+    assert!(
+        client_login_start_result
+            .state
+            .finish(
+                &mut client_rng,
+                b"password",
+                CredentialResponse::<StandardCipherSuite>::deserialize(&login_start.message).unwrap(),
+                ClientLoginFinishParameters::default(),
+            )
+            .is_err()
+    );
+}
+
+#[test]
+fn should_log_in_to_canonicalised_legacy_account() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let server_keyring = generate_server_keyring();
+
+    register_legacy(&server_keyring, &mock_file_storage, &mut client_rng, "Alice", "password");
+
+    canonicalise_password_files(&mock_file_storage).unwrap();
+
+    let mut opaque_authentication = OpaqueAuthentication::new(mock_file_storage, MemorySessionStore::new(), server_keyring, generate_request_policy(), generate_session_policy(), generate_login_policy(), generate_login_throttle()).unwrap();
+
+    // A-ct
+
+    let session_key = login(&mut opaque_authentication, &mut client_rng, "Alice", "password");
+
+    // A-ssert
+
+    assert!(!session_key.is_empty());
+}
+
+#[test]
+fn should_require_fresh_login() {
+    // A-rrange
+
+    let (_directory, mock_file_storage) = generate_mock_file_storage();
+
+    let mut client_rng = OsRng;
+
+    let mock_clock = MockClock::new(TOTP_TIME);
+
+    let mut opaque_authentication = generate_two_factor_authentication(mock_file_storage, &mock_clock);
+
+    register(&opaque_authentication, &mut client_rng, "username", "password");
+
+    let session_token = create_session(&login(&mut opaque_authentication, &mut client_rng, "username", "password"));
+
+    // A-ct
+
+    let fresh_result = opaque_authentication.ensure_fresh_login(&session_token);
+
+    mock_clock.advance(301);
+
+    let stale_result = opaque_authentication.ensure_fresh_login(&session_token);
+
+    // A-ssert
+
+    assert!(fresh_result.is_ok());
+
+    match stale_result {
+        Err(AuthenticationError::FreshLoginRequired(_)) => {}
+        _ => panic!("Test result should be FreshLoginRequired."),
+    }
+}
+
+pub struct MockFileStorage {
+    pub path: String,
+}
+
+impl FileStorage for MockFileStorage {
+    fn new(path: String) -> Self {
+        Self { path }
+    }
+
+    fn retrieve(&self, file_name: &str) -> Result<Vec<u8>> {
+        let file_path = Path::new(&self.path).join(file_name);
+
+        let Ok(file) = File::open(&file_path) else {
+            return Err(FileStorageError::FileNotFound(file_name.to_string()));
+        };
+
+        let mut reader = BufReader::new(file);
+        let mut buffer = Vec::new();
+
+        reader.read_to_end(&mut buffer).unwrap();
+
+        Ok(buffer)
+    }
+
+    fn save(&self, file_name: &str, vault: Vec<u8>) -> Result<()> {
+        if !Path::new(&self.path).exists() {
+            fs::create_dir_all(&self.path).unwrap();
+        }
+
+        let file_path = Path::new(&self.path).join(file_name);
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&file_path)
+            .unwrap();
+
+        file.write_all(&vault).unwrap();
+
+        Ok(())
+    }
+
+    fn delete(&self, file_name: &str) -> Result<()> {
+        fs::remove_file(Path::new(&self.path).join(file_name))
+            .map_err(|_| FileStorageError::FileNotFound(file_name.to_string()))
+    }
+
+    fn exists(&self, file_name: &str) -> Result<bool> {
+        Ok(Path::new(&self.path).join(file_name).is_file())
+    }
+
+    fn list(&self, prefix: &str, start_after: Option<&str>, limit: usize) -> Result<FileListing> {
+        if !Path::new(&self.path).exists() {
+            return Ok(FileListing::page(vec![], prefix, start_after, limit));
+        }
+
+        let file_names: Vec<String> = fs::read_dir(&self.path)
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .filter(|entry| entry.file_type().unwrap().is_file())
+            .map(|entry| entry.file_name().into_string().unwrap())
+            .collect();
+
+        Ok(FileListing::page(file_names, prefix, start_after, limit))
+    }
+
+    fn metadata(&self, file_name: &str) -> Result<FileMetadata> {
+        let Ok(metadata) = fs::metadata(Path::new(&self.path).join(file_name)) else {
+            return Err(FileStorageError::FileNotFound(file_name.to_string()));
+        };
+
+        Ok(FileMetadata {
+            size: metadata.len(),
+            modified_at: 0,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct MockClock {
+    now: Arc<AtomicU64>,
+}
+
+impl MockClock {
+    fn new(now: u64) -> Self {
+        Self {
+            now: Arc::new(AtomicU64::new(now)),
+        }
+    }
+
+    fn advance(&self, seconds: u64) {
+        self.now.fetch_add(seconds, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> core_domain::authentication::authentication_error::Result<u64> {
+        Ok(self.now.load(Ordering::SeqCst))
+    }
+}
+
+pub struct FailingClock;
+
+impl Clock for FailingClock {
+    fn now(&self) -> core_domain::authentication::authentication_error::Result<u64> {
+        Err(AuthenticationError::Internal("Clock is before the UNIX epoch.".to_string()))
+    }
+}
+
+fn create_session(session_key: &[u8]) -> String {
+    let hkdf = Hkdf::<Sha512>::from_prk(session_key).unwrap();
+
+    let mut token = vec![0u8; 64];
+
+    hkdf.expand(b"opaque-session-token", &mut token).unwrap();
+
+    hex::encode(token)
+}
+
+fn create_refreshed_session_key(session_key: &[u8], salt: &[u8]) -> Vec<u8> {
+    let hkdf = Hkdf::<Sha512>::new(Some(salt), session_key);
+
+    let mut refreshed_session_key = vec![0u8; 64];
+
+    hkdf.expand(b"opaque-session-refresh", &mut refreshed_session_key).unwrap();
+
+    refreshed_session_key
+}
+
+fn create_p256_session(session_key: &[u8]) -> String {
+    let hkdf = Hkdf::<Sha256>::from_prk(session_key).unwrap();
+
+    let mut token = vec![0u8; 64];
+
+    hkdf.expand(b"opaque-session-token", &mut token).unwrap();
+
+    hex::encode(token)
+}
+
+fn create_p256_refreshed_session_key(session_key: &[u8], salt: &[u8]) -> Vec<u8> {
+    let hkdf = Hkdf::<Sha256>::new(Some(salt), session_key);
+
+    let mut refreshed_session_key = vec![0u8; 32];
+
+    hkdf.expand(b"opaque-session-refresh", &mut refreshed_session_key).unwrap();
+
+    refreshed_session_key
+}
+
+fn create_signed_request(
+    session_key: &[u8],
+    method: &str,
+    path: &str,
+    timestamp: &str,
+    body: &[u8],
+) -> SignedRequest {
+    let mut signed_request = SignedRequest {
+        bearer_token: create_session(session_key),
+        method: method.to_string(),
+        authority: String::from("localhost"),
+        path: path.to_string(),
+        query: String::new(),
+        content_digest: content_digest(body),
+        timestamp: timestamp.to_string(),
+        nonce: generate_nonce(),
+        signature: String::new(),
+    };
+
+    signed_request.signature = create_client_signature(&signature_base(&signed_request), session_key);
+
+    signed_request
+}
+
+fn generate_nonce() -> String {
+    let mut nonce = [0u8; 16];
+
+    OsRng.fill_bytes(&mut nonce);
+
+    hex::encode(nonce)
+}
+
+fn create_client_signature(raw_signature: &str, session_key: &[u8]) -> String {
+    let mut mac = HmacSha512::new_from_slice(&session_key).unwrap();
+
+    mac.update(raw_signature.as_bytes());
+
+    hex::encode(mac.finalize().into_bytes().to_vec())
+}
+
+fn generate_mock_file_storage() -> (TempDir, MockFileStorage) {
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    (directory, MockFileStorage::new(path))
+}
+
+fn generate_username(username: &str) -> Username {
+    Username::parse(username).unwrap()
+}
+
+fn generate_server_keyring() -> ServerKeyring {
+    let mut rng = OsRng;
+
+    ServerKeyring::new(ServerSetup::<StandardCipherSuite>::new(&mut rng).into())
+}
+
+fn register<SS: SessionStore, C: Clock>(
+    opaque_authentication: &OpaqueAuthentication<MockFileStorage, SS, C>,
+    client_rng: &mut OsRng,
+    username: &str,
+    password: &str,
+) {
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(client_rng, password.as_bytes()).unwrap();
+
+    let server_registration_start_result = opaque_authentication
+        .start_server_registration(
+            &generate_username(username),
+            client_registration_start_result
+                .message
+                .serialize()
+                .to_vec(),
+        )
+        .unwrap();
+
+    let client_finish_registration_result = client_registration_start_result
+        .state
+        .finish(
+            client_rng,
+            password.as_bytes(),
+            RegistrationResponse::deserialize(&server_registration_start_result).unwrap(),
+            ClientRegistrationFinishParameters::default(),
+        )
+        .unwrap();
+
+    opaque_authentication
+        .finish_server_registration(
+            &generate_username(username),
+            client_finish_registration_result
+                .message
+                .serialize()
+                .to_vec(),
+        )
+        .unwrap();
+}
+
+// What older versions saved: a credential registered and stored under a name that is not canonical.
+fn register_legacy(
+    server_keyring: &ServerKeyring,
+    file_storage: &MockFileStorage,
+    client_rng: &mut OsRng,
+    username: &str,
+    password: &str,
+) {
+    let server_key = server_keyring.current();
+
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(client_rng, password.as_bytes()).unwrap();
+
+    let server_registration_start_result = server_key
+        .start_registration(username, &client_registration_start_result.message.serialize())
+        .unwrap();
+
+    let client_finish_registration_result = client_registration_start_result
+        .state
+        .finish(
+            client_rng,
+            password.as_bytes(),
+            RegistrationResponse::deserialize(&server_registration_start_result).unwrap(),
+            ClientRegistrationFinishParameters::default(),
+        )
+        .unwrap();
+
+    let registration = server_key
+        .cipher_suite()
+        .finish_registration(&client_finish_registration_result.message.serialize())
+        .unwrap();
+
+    let password_file = PasswordFile::new(
+        server_keyring.current_version(),
+        server_key.cipher_suite(),
+        KsfParameters::default(),
+        registration,
+    );
+
+    file_storage.save(username, password_file.serialize()).unwrap();
+}
+
+fn login<SS: SessionStore, C: Clock>(
+    opaque_authentication: &mut OpaqueAuthentication<MockFileStorage, SS, C>,
+    client_rng: &mut OsRng,
+    username: &str,
+    password: &str,
+) -> Vec<u8> {
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(client_rng, password.as_bytes()).unwrap();
+
+    let server_login_start_result = opaque_authentication
+        .start_server_login(
+            &generate_username(username),
+            None,
+            client_login_start_result.message.serialize().to_vec(),
+        )
+        .unwrap();
+
+    let client_login_finish_result = client_login_start_result
+        .state
+        .finish(
+            client_rng,
+            password.as_bytes(),
+            CredentialResponse::<StandardCipherSuite>::deserialize(&server_login_start_result.message)
+                .unwrap(),
+            ClientLoginFinishParameters::default(),
+        )
+        .unwrap();
+
+    opaque_authentication
+        .finish_server_login(
+            &generate_username(username),
+            &server_login_start_result.login_id,
+            client_login_finish_result.message.serialize().to_vec(),
+            Some("test client"),
+        )
+        .unwrap();
+
+    client_login_finish_result.session_key.to_vec()
+}
+
+fn set_up_recovery<SS: SessionStore, C: Clock>(
+    opaque_authentication: &OpaqueAuthentication<MockFileStorage, SS, C>,
+    client_rng: &mut OsRng,
+    username: &str,
+    recovery_secret: &str,
+    wrapped_key: &[u8],
+) {
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(client_rng, recovery_secret.as_bytes()).unwrap();
+
+    let server_registration_start_result = opaque_authentication
+        .start_recovery_registration(&generate_username(username), client_registration_start_result.message.serialize().to_vec())
+        .unwrap();
+
+    let client_finish_registration_result = client_registration_start_result
+        .state
+        .finish(
+            client_rng,
+            recovery_secret.as_bytes(),
+            RegistrationResponse::deserialize(&server_registration_start_result).unwrap(),
+            ClientRegistrationFinishParameters::default(),
+        )
+        .unwrap();
+
+    opaque_authentication
+        .finish_recovery_registration(
+            &generate_username(username),
+            client_finish_registration_result.message.serialize().to_vec(),
+            wrapped_key.to_vec(),
+        )
+        .unwrap();
+}
+
+fn recovery_login<SS: SessionStore, C: Clock>(
+    opaque_authentication: &mut OpaqueAuthentication<MockFileStorage, SS, C>,
+    client_rng: &mut OsRng,
+    username: &str,
+    recovery_secret: &str,
+) -> Vec<u8> {
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(client_rng, recovery_secret.as_bytes()).unwrap();
+
+    let login_start = opaque_authentication
+        .start_recovery_login(&generate_username(username), None, client_login_start_result.message.serialize().to_vec())
+        .unwrap();
+
+    let client_login_finish_result = client_login_start_result
+        .state
+        .finish(
+            client_rng,
+            recovery_secret.as_bytes(),
+            CredentialResponse::<StandardCipherSuite>::deserialize(&login_start.message).unwrap(),
+            ClientLoginFinishParameters::default(),
+        )
+        .unwrap();
+
+    opaque_authentication
+        .finish_recovery_login(
+            &generate_username(username),
+            &login_start.login_id,
+            client_login_finish_result.message.serialize().to_vec(),
+            Some("recovery client"),
+        )
+        .unwrap();
+
+    client_login_finish_result.session_key.to_vec()
+}
+
+fn register_p256<SS: SessionStore, C: Clock>(
+    opaque_authentication: &OpaqueAuthentication<MockFileStorage, SS, C>,
+    client_rng: &mut OsRng,
+    username: &str,
+    password: &str,
+) {
+    let client_registration_start_result =
+        ClientRegistration::<P256CipherSuite>::start(client_rng, password.as_bytes()).unwrap();
+
+    let server_registration_start_result = opaque_authentication
+        .start_server_registration(
+            &generate_username(username),
+            client_registration_start_result
+                .message
+                .serialize()
+                .to_vec(),
+        )
+        .unwrap();
+
+    let client_finish_registration_result = client_registration_start_result
+        .state
+        .finish(
+            client_rng,
+            password.as_bytes(),
+            RegistrationResponse::deserialize(&server_registration_start_result).unwrap(),
+            ClientRegistrationFinishParameters::default(),
+        )
+        .unwrap();
+
+    opaque_authentication
+        .finish_server_registration(
+            &generate_username(username),
+            client_finish_registration_result
+                .message
+                .serialize()
+                .to_vec(),
+        )
+        .unwrap();
+}
+
+fn login_p256<SS: SessionStore, C: Clock>(
+    opaque_authentication: &mut OpaqueAuthentication<MockFileStorage, SS, C>,
+    client_rng: &mut OsRng,
+    username: &str,
+    password: &str,
+) -> Vec<u8> {
+    let client_login_start_result =
+        ClientLogin::<P256CipherSuite>::start(client_rng, password.as_bytes()).unwrap();
+
+    let server_login_start_result = opaque_authentication
+        .start_server_login(
+            &generate_username(username),
+            None,
+            client_login_start_result.message.serialize().to_vec(),
+        )
+        .unwrap();
+
+    let client_login_finish_result = client_login_start_result
+        .state
+        .finish(
+            client_rng,
+            password.as_bytes(),
+            CredentialResponse::<P256CipherSuite>::deserialize(&server_login_start_result.message)
+                .unwrap(),
+            ClientLoginFinishParameters::default(),
+        )
+        .unwrap();
+
+    opaque_authentication
+        .finish_server_login(
+            &generate_username(username),
+            &server_login_start_result.login_id,
+            client_login_finish_result.message.serialize().to_vec(),
+            None,
+        )
+        .unwrap();
+
+    client_login_finish_result.session_key.to_vec()
+}
+
+fn register_with_server_identity<SS: SessionStore, C: Clock>(
+    opaque_authentication: &OpaqueAuthentication<MockFileStorage, SS, C>,
+    client_rng: &mut OsRng,
+    username: &str,
+    password: &str,
+    server_identity: &str,
+) {
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(client_rng, password.as_bytes()).unwrap();
+
+    let server_registration_start_result = opaque_authentication
+        .start_server_registration(
+            &generate_username(username),
+            client_registration_start_result
+                .message
+                .serialize()
+                .to_vec(),
+        )
+        .unwrap();
+
+    let identifiers = Identifiers {
+        client: None,
+        server: Some(server_identity.as_bytes()),
+    };
+
+    let client_finish_registration_result = client_registration_start_result
+        .state
+        .finish(
+            client_rng,
+            password.as_bytes(),
+            RegistrationResponse::deserialize(&server_registration_start_result).unwrap(),
+            ClientRegistrationFinishParameters::new(identifiers, None),
+        )
+        .unwrap();
+
+    opaque_authentication
+        .finish_server_registration(
+            &generate_username(username),
+            client_finish_registration_result
+                .message
+                .serialize()
+                .to_vec(),
+        )
+        .unwrap();
+}
+
+// Either side may notice the mismatch: the client when opening its envelope or checking the server MAC,
+// the server when checking the client MAC.
+fn login_with_server_identity<SS: SessionStore, C: Clock>(
+    opaque_authentication: &mut OpaqueAuthentication<MockFileStorage, SS, C>,
+    client_rng: &mut OsRng,
+    username: &str,
+    password: &str,
+    server_identity: &str,
+    context: &str,
+) -> bool {
+    let (client_login_start_result, login_start) = start_login(opaque_authentication, client_rng, username, password);
+
+    let identifiers = Identifiers {
+        client: None,
+        server: Some(server_identity.as_bytes()),
+    };
+
+    let client_login_finish_result = client_login_start_result.state.finish(
+        client_rng,
+        password.as_bytes(),
+        CredentialResponse::<StandardCipherSuite>::deserialize(&login_start.message).unwrap(),
+        ClientLoginFinishParameters::new(Some(context.as_bytes()), identifiers, None),
+    );
+
+    let Ok(client_login_finish_result) = client_login_finish_result else {
+        return false;
+    };
+
+    opaque_authentication
+        .finish_server_login(
+            &generate_username(username),
+            &login_start.login_id,
+            client_login_finish_result.message.serialize().to_vec(),
+            None,
+        )
+        .is_ok()
+}
+
+fn generate_server_identity() -> ServerIdentity {
+    ServerIdentity::new(Some(String::from("vault.example.org")), Some(String::from("vault-v1")))
+}
+
+fn generate_two_factor_authentication(
+    mock_file_storage: MockFileStorage,
+    mock_clock: &MockClock,
+) -> OpaqueAuthentication<MockFileStorage, MemorySessionStore, MockClock> {
+    let two_factor_storage = MockFileStorage::new(format!("{}/two_factor", mock_file_storage.path));
+
+    OpaqueAuthentication::with_clock(
+        mock_file_storage,
+        MemorySessionStore::new(),
+        generate_server_keyring(),
+        generate_request_policy(),
+        generate_session_policy(),
+        generate_login_policy(),
+        generate_login_throttle(),
+        mock_clock.clone(),
+    )
+    .unwrap()
+    .with_two_factor(two_factor_storage, "Vault")
+}
+
+// Enrolls from a fresh session, like a client would, and gives back the base32 secret and the recovery codes.
+fn enable_two_factor(
+    opaque_authentication: &mut OpaqueAuthentication<MockFileStorage, MemorySessionStore, MockClock>,
+    client_rng: &mut OsRng,
+    mock_clock: &MockClock,
+    username: &str,
+) -> (String, Vec<String>) {
+    login(opaque_authentication, client_rng, username, "password");
+
+    let two_factor_enrollment = opaque_authentication.start_two_factor_enrollment(&generate_username(username)).unwrap();
+
+    let recovery_codes = opaque_authentication
+        .confirm_two_factor_enrollment(&generate_username(username), &generate_totp_code(&two_factor_enrollment.secret, mock_clock.now().unwrap()))
+        .unwrap();
+
+    (two_factor_enrollment.secret, recovery_codes)
+}
+
+fn generate_totp_code(secret: &str, time: u64) -> String {
+    let secret = Secret::Encoded(secret.to_string()).to_bytes().unwrap();
+
+    TOTP::new(Algorithm::SHA1, 6, 0, 30, secret, None, String::new())
+        .unwrap()
+        .generate(time)
+}
+
+fn load_file_session_store(test_path: &str) -> FileSessionStore {
+    let session_store_key =
+        load_or_create_session_store_key(&format!("{}/session_store.key", test_path)).unwrap();
+
+    FileSessionStore::load(&format!("{}/sessions", test_path), session_store_key).unwrap()
+}
+
+fn generate_request_policy() -> RequestPolicy {
+    RequestPolicy::new(5, 2)
+}
+
+fn generate_session_policy() -> SessionPolicy {
+    SessionPolicy::new(3_600, 900, 30, 300)
+}
+
+fn generate_login_policy() -> LoginPolicy {
+    LoginPolicy::new(30, 100)
+}
+
+fn generate_login_throttle() -> LoginThrottle {
+    LoginThrottle::new(ThrottlePolicy::new(3, 1, 300, 10, 900))
+}
+
+fn start_login<SS: SessionStore, C: Clock>(
+    opaque_authentication: &mut OpaqueAuthentication<MockFileStorage, SS, C>,
+    client_rng: &mut OsRng,
+    username: &str,
+    password: &str,
+) -> (ClientLoginStartResult<StandardCipherSuite>, LoginStart) {
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(client_rng, password.as_bytes()).unwrap();
+
+    let login_start = opaque_authentication
+        .start_server_login(
+            &generate_username(username),
+            None,
+            client_login_start_result.message.serialize().to_vec(),
+        )
+        .unwrap();
+
+    (client_login_start_result, login_start)
+}
+
+fn finish_client_login(
+    client_login_start_result: ClientLoginStartResult<StandardCipherSuite>,
+    client_rng: &mut OsRng,
+    password: &str,
+    login_start: &LoginStart,
+) -> Vec<u8> {
+    client_login_start_result
+        .state
+        .finish(
+            client_rng,
+            password.as_bytes(),
+            CredentialResponse::<StandardCipherSuite>::deserialize(&login_start.message).unwrap(),
+            ClientLoginFinishParameters::default(),
+        )
+        .unwrap()
+        .message
+        .serialize()
+        .to_vec()
+}
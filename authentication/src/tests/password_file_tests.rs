@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use core_domain::{
+    file_storage::{file_listing::FileListing, file_metadata::FileMetadata, file_storage_error::Result},
+    ports::file_storage::FileStorage,
+};
+
+use crate::{
+    cipher_suite::{CipherSuiteId, KsfParameters},
+    password_file::{PasswordFile, count_password_files_by_key_version},
+    recovery_file::RecoveryFile,
+};
+
+#[test]
+fn should_serialize_and_deserialize_password_file() {
+    // A-rrange
+
+    let ksf_parameters = KsfParameters::new(65536, 3, 4).unwrap();
+    let password_file = PasswordFile::new(3, CipherSuiteId::P256Sha256, ksf_parameters, vec![42, 43]);
+
+    // A-ct
+
+    let result = PasswordFile::deserialize(&password_file.serialize());
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    let result = result.unwrap();
+
+    assert_eq!(result.key_version, 3);
+    assert_eq!(result.cipher_suite, CipherSuiteId::P256Sha256);
+    assert_eq!(result.ksf_parameters, ksf_parameters);
+    assert_eq!(result.registration, vec![42, 43]);
+    assert_eq!(result.credential_identifier, None);
+}
+
+#[test]
+fn should_serialize_and_deserialize_password_file_with_credential_identifier() {
+    // A-rrange
+
+    let password_file = PasswordFile::new(2, CipherSuiteId::Ristretto255Sha512, KsfParameters::default(), vec![42, 43])
+        .with_credential_identifier(String::from("Alice"));
+
+    // A-ct
+
+    let result = PasswordFile::deserialize(&password_file.serialize());
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    let result = result.unwrap();
+
+    assert_eq!(result.key_version, 2);
+    assert_eq!(result.credential_identifier, Some(String::from("Alice")));
+    assert_eq!(result.registration, vec![42, 43]);
+}
+
+#[test]
+fn should_deserialize_legacy_password_file_as_first_key_version() {
+    // A-rrange
+
+    let legacy_password_file = vec![42; 64];
+
+    // A-ct
+
+    let result = PasswordFile::deserialize(&legacy_password_file);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    let result = result.unwrap();
+
+    assert_eq!(result.key_version, 1);
+    assert_eq!(result.cipher_suite, CipherSuiteId::Ristretto255Sha512);
+    assert_eq!(result.ksf_parameters, KsfParameters::default());
+    assert_eq!(result.registration, legacy_password_file);
+}
+
+#[test]
+fn should_not_deserialize_password_file_with_unknown_cipher_suite() {
+    // A-rrange
+
+    let mut password_file =
+        PasswordFile::new(1, CipherSuiteId::P256Sha256, KsfParameters::default(), vec![42]).serialize();
+    password_file[5] = 42;
+
+    // A-ct
+
+    let result = PasswordFile::deserialize(&password_file);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_count_password_files_by_key_version() {
+    // A-rrange
+
+    let mock_file_storage = MockFileStorage::new(String::new());
+
+    // A-ct
+
+    let result = count_password_files_by_key_version(&mock_file_storage);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), BTreeMap::from([(1, 2), (2, 1), (3, 1)]));
+}
+
+struct MockFileStorage;
+
+impl FileStorage for MockFileStorage {
+    fn new(_: String) -> Self {
+        Self
+    }
+
+    fn retrieve(&self, file_name: &str) -> Result<Vec<u8>> {
+        match file_name {
+            "alice" => Ok(PasswordFile::new(2, CipherSuiteId::P256Sha256, KsfParameters::default(), vec![42]).serialize()),
+            "alice.recovery" => Ok(RecoveryFile::new(
+                PasswordFile::new(3, CipherSuiteId::Ristretto255Sha512, KsfParameters::default(), vec![42]),
+                vec![43],
+            )
+            .serialize()),
+            "bob" => Ok(PasswordFile::new(1, CipherSuiteId::Ristretto255Sha512, KsfParameters::default(), vec![42]).serialize()),
+            _ => Ok(vec![42; 64]),
+        }
+    }
+
+    fn save(&self, _: &str, _: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+
+    fn delete(&self, _: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn exists(&self, _: &str) -> Result<bool> {
+        Ok(true)
+    }
+
+    // Pages of one name, the count has to walk through all of them.
+    fn list(&self, prefix: &str, start_after: Option<&str>, _: usize) -> Result<FileListing> {
+        let file_names = vec![
+            String::from("alice"),
+            String::from("alice.recovery"),
+            String::from("bob"),
+            String::from("carol"),
+        ];
+
+        Ok(FileListing::page(file_names, prefix, start_after, 1))
+    }
+
+    fn metadata(&self, _: &str) -> Result<FileMetadata> {
+        Ok(FileMetadata {
+            size: 64,
+            modified_at: 42,
+        })
+    }
+}
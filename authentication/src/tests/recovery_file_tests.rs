@@ -0,0 +1,77 @@
+use crate::{
+    cipher_suite::{CipherSuiteId, KsfParameters},
+    password_file::PasswordFile,
+    recovery_file::{RecoveryFile, is_recovery_file_name, recovery_file_name},
+};
+
+#[test]
+fn should_serialize_and_deserialize_recovery_file() {
+    // A-rrange
+
+    let password_file = PasswordFile::new(2, CipherSuiteId::P256Sha256, KsfParameters::default(), vec![42, 43]);
+    let recovery_file = RecoveryFile::new(password_file, vec![7; 48]);
+
+    // A-ct
+
+    let result = RecoveryFile::deserialize(&recovery_file.serialize());
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    let result = result.unwrap();
+
+    assert_eq!(result.password_file.key_version, 2);
+    assert_eq!(result.password_file.cipher_suite, CipherSuiteId::P256Sha256);
+    assert_eq!(result.password_file.registration, vec![42, 43]);
+    assert_eq!(result.wrapped_key, vec![7; 48]);
+}
+
+#[test]
+fn should_not_deserialize_truncated_recovery_file() {
+    // A-rrange
+
+    let password_file = PasswordFile::new(2, CipherSuiteId::P256Sha256, KsfParameters::default(), vec![42, 43]);
+    let mut content = RecoveryFile::new(password_file, vec![]).serialize();
+
+    content.truncate(content.len() - 1);
+
+    // A-ct
+
+    let result = RecoveryFile::deserialize(&content);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_not_deserialize_password_file_as_recovery_file() {
+    // A-rrange
+
+    let password_file = PasswordFile::new(2, CipherSuiteId::P256Sha256, KsfParameters::default(), vec![42, 43]);
+
+    // A-ct
+
+    let result = RecoveryFile::deserialize(&password_file.serialize());
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_recognize_recovery_file_name() {
+    // A-rrange
+
+    let file_name = recovery_file_name("alice");
+
+    // A-ct
+
+    let result = is_recovery_file_name(&file_name);
+
+    // A-ssert
+
+    assert!(result);
+    assert!(!is_recovery_file_name("alice"));
+}
@@ -0,0 +1,148 @@
+use core_domain::authentication::signed_request::SignedRequest;
+
+use crate::request_signature::{RequestPolicy, content_digest, signature_base};
+
+#[test]
+fn should_compute_content_digest() {
+    // A-rrange
+
+    let body = br#"{"hello": "world"}"#;
+
+    // A-ct
+
+    let result = content_digest(body);
+
+    // A-ssert
+
+    assert_eq!(
+        result,
+        "sha-512=:WZDPaVn/7XgHaAy8pmojAkGWoRx2UFChF41A2svX+TaPm+AbwAgBWnrIiYllu7BNNyealdVLvRwEmTHWXvJwew==:"
+    );
+}
+
+#[test]
+fn should_compute_content_digest_of_empty_body() {
+    // A-rrange
+
+    let body = b"";
+
+    // A-ct
+
+    let result = content_digest(body);
+
+    // A-ssert
+
+    assert_eq!(
+        result,
+        "sha-512=:z4PhNX7vuL3xVChQ1m2AB9Yg5AULVxXcg/SpIdNs6c5H0NE8XYXysP+DGNKHfuwvY7kxvUdBeoGlODJ6+SfaPg==:"
+    );
+}
+
+#[test]
+fn should_build_signature_base() {
+    // A-rrange
+
+    let signed_request = SignedRequest {
+        bearer_token: String::from("token"),
+        method: String::from("post"),
+        authority: String::from("Vault.Example.org"),
+        path: String::from("/vault"),
+        query: String::from("version=2"),
+        content_digest: content_digest(b"vault"),
+        timestamp: String::from("1700000000"),
+        nonce: String::from("b6f0c1d2e3a4958677a8b9c0d1e2f3a4"),
+        signature: String::from("signature"),
+    };
+
+    // A-ct
+
+    let result = signature_base(&signed_request);
+
+    // A-ssert
+
+    assert_eq!(
+        result,
+        format!(
+            "\"@method\": POST\n\
+             \"@authority\": vault.example.org\n\
+             \"@path\": /vault\n\
+             \"@query\": ?version=2\n\
+             \"content-digest\": {}\n\
+             \"x-timestamp\": 1700000000\n\
+             \"x-nonce\": b6f0c1d2e3a4958677a8b9c0d1e2f3a4\n\
+             \"@signature-params\": (\"@method\" \"@authority\" \"@path\" \"@query\" \"content-digest\" \"x-timestamp\" \"x-nonce\")",
+            content_digest(b"vault")
+        )
+    );
+}
+
+#[test]
+fn should_change_signature_base_with_body() {
+    // A-rrange
+
+    let mut signed_request = SignedRequest {
+        bearer_token: String::from("token"),
+        method: String::from("POST"),
+        authority: String::from("localhost"),
+        path: String::from("/vault"),
+        query: String::new(),
+        content_digest: content_digest(b"vault"),
+        timestamp: String::from("1700000000"),
+        nonce: String::from("b6f0c1d2e3a4958677a8b9c0d1e2f3a4"),
+        signature: String::from("signature"),
+    };
+
+    let original_signature_base = signature_base(&signed_request);
+
+    // A-ct
+
+    signed_request.content_digest = content_digest(b"attacker vault");
+
+    // A-ssert
+
+    assert_ne!(signature_base(&signed_request), original_signature_base);
+}
+
+#[test]
+fn should_change_signature_base_with_nonce() {
+    // A-rrange
+
+    let mut signed_request = SignedRequest {
+        bearer_token: String::from("token"),
+        method: String::from("GET"),
+        authority: String::from("localhost"),
+        path: String::from("/vault"),
+        query: String::new(),
+        content_digest: content_digest(b""),
+        timestamp: String::from("1700000000"),
+        nonce: String::from("b6f0c1d2e3a4958677a8b9c0d1e2f3a4"),
+        signature: String::from("signature"),
+    };
+
+    let original_signature_base = signature_base(&signed_request);
+
+    // A-ct
+
+    signed_request.nonce = String::from("0f1e2d3c4b5a69788796a5b4c3d2e1f0");
+
+    // A-ssert
+
+    assert_ne!(signature_base(&signed_request), original_signature_base);
+}
+
+#[test]
+fn should_not_overflow_with_large_request_policy() {
+    // A-rrange
+
+    let request_policy = RequestPolicy::new(u64::MAX, u64::MAX);
+
+    // A-ct
+
+    let accepted = request_policy.accepts(0, 1_700_000_000);
+    let expires_at = request_policy.expires_at(1_700_000_000);
+
+    // A-ssert
+
+    assert!(accepted);
+    assert_eq!(expires_at, u64::MAX);
+}
@@ -0,0 +1,34 @@
+use crate::server_identity::ServerIdentity;
+
+#[test]
+fn should_treat_empty_values_as_unset() {
+    // A-rrange
+
+    let identity = Some(String::new());
+    let context = Some(String::new());
+
+    // A-ct
+
+    let server_identity = ServerIdentity::new(identity, context);
+
+    // A-ssert
+
+    assert_eq!(server_identity, ServerIdentity::default());
+}
+
+#[test]
+fn should_give_login_parameters() {
+    // A-rrange
+
+    let server_identity = ServerIdentity::new(Some(String::from("vault.example.org")), Some(String::from("vault-v1")));
+
+    // A-ct
+
+    let login_parameters = server_identity.login_parameters();
+
+    // A-ssert
+
+    assert_eq!(login_parameters.identifiers.server, Some(b"vault.example.org".as_slice()));
+    assert_eq!(login_parameters.identifiers.client, None);
+    assert_eq!(login_parameters.context, Some(b"vault-v1".as_slice()));
+}
@@ -0,0 +1,373 @@
+use std::{collections::BTreeSet, fs};
+
+use core_domain::authentication::authentication_error::AuthenticationError;
+use tempfile::TempDir;
+
+use crate::{
+    cipher_suite::{CipherSuiteId, ServerKey},
+    server_key::{ServerKeyring, load_existing_server_keyring, load_or_create_server_keyring},
+};
+
+#[test]
+fn should_create_server_key_file() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("keys").join("server_setup.key");
+
+    // A-ct
+
+    let result = load_or_create_server_keyring(path.to_str().unwrap(), CipherSuiteId::Ristretto255Sha512);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert!(path.exists());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+#[test]
+fn should_load_same_server_setup() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("server_setup.key");
+    let path = path.to_str().unwrap();
+
+    let created_server_keyring = load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512).unwrap();
+
+    // A-ct
+
+    let result = load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap().current().serialize(), created_server_keyring.current().serialize());
+}
+
+#[test]
+fn should_not_load_corrupt_server_key_file() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("server_setup.key");
+    let path = path.to_str().unwrap();
+
+    load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512).unwrap();
+
+    let mut content = fs::read(path).unwrap();
+    content.truncate(content.len() / 2);
+    fs::write(path, content).unwrap();
+
+    // A-ct
+
+    let result = load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::ServerKey(_)) => {}
+        _ => panic!("Test result should be ServerKey."),
+    }
+}
+
+#[test]
+fn should_not_load_server_key_file_with_wrong_magic() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("server_setup.key");
+    let path = path.to_str().unwrap();
+
+    load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512).unwrap();
+
+    let mut content = fs::read(path).unwrap();
+    content[0] = b'X';
+    fs::write(path, content).unwrap();
+
+    // A-ct
+
+    let result = load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::ServerKey(_)) => {}
+        _ => panic!("Test result should be ServerKey."),
+    }
+}
+
+#[test]
+fn should_not_load_server_key_file_with_wrong_cipher_suite() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("server_setup.key");
+    let path = path.to_str().unwrap();
+
+    load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512).unwrap();
+
+    // The cipher suite of the first key comes after the magic, the format version, three u32 and the key version.
+    let mut content = fs::read(path).unwrap();
+    content[21] = 42;
+    fs::write(path, content).unwrap();
+
+    // A-ct
+
+    let result = load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::ServerKey(message)) => assert!(message.contains("cipher suite")),
+        _ => panic!("Test result should be ServerKey."),
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn should_not_load_server_key_file_readable_by_others() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("server_setup.key");
+    let path = path.to_str().unwrap();
+
+    load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512).unwrap();
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o644)).unwrap();
+
+    // A-ct
+
+    let result = load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512);
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::ServerKey(_)) => {}
+        _ => panic!("Test result should be ServerKey."),
+    }
+}
+
+#[test]
+fn should_rotate_and_reload_server_keyring() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("server_setup.key");
+    let path = path.to_str().unwrap();
+
+    let mut server_keyring = load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512).unwrap();
+    let first_server_setup = server_keyring.current().serialize();
+
+    // A-ct
+
+    let new_version = server_keyring.rotate().unwrap();
+    server_keyring.save(path).unwrap();
+
+    // A-ssert
+
+    let reloaded_server_keyring = load_existing_server_keyring(path).unwrap();
+
+    assert_eq!(new_version, 2);
+    assert_eq!(reloaded_server_keyring.current_version(), 2);
+    assert_eq!(reloaded_server_keyring.in_use_version(), 1);
+    assert_eq!(reloaded_server_keyring.versions(), vec![1, 2]);
+    assert_eq!(reloaded_server_keyring.get(1).unwrap().serialize(), first_server_setup);
+    assert_ne!(reloaded_server_keyring.current().serialize(), first_server_setup);
+}
+
+#[test]
+fn should_not_rotate_twice_before_server_restart() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("server_setup.key");
+    let path = path.to_str().unwrap();
+
+    let mut server_keyring = load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512).unwrap();
+    server_keyring.rotate().unwrap();
+    server_keyring.save(path).unwrap();
+
+    let mut server_keyring = load_existing_server_keyring(path).unwrap();
+
+    // A-ct
+
+    let result = server_keyring.rotate();
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::ServerKey(_)) => {}
+        _ => panic!("Test result should be ServerKey."),
+    }
+
+    load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512).unwrap();
+
+    let mut restarted_server_keyring = load_existing_server_keyring(path).unwrap();
+
+    assert_eq!(restarted_server_keyring.in_use_version(), 2);
+    assert_eq!(restarted_server_keyring.rotate().unwrap(), 3);
+}
+
+#[test]
+fn should_only_remove_unused_old_versions() {
+    // A-rrange
+
+    let mut server_keyring = ServerKeyring::new(ServerKey::generate(CipherSuiteId::Ristretto255Sha512));
+
+    for _ in 0..3 {
+        server_keyring.mark_current_in_use();
+        server_keyring.rotate().unwrap();
+    }
+
+    let used_versions = BTreeSet::from([2]);
+
+    // A-ct
+
+    let removed_versions = server_keyring.retain_used_versions(&used_versions);
+
+    // A-ssert
+
+    assert_eq!(removed_versions, vec![1]);
+    assert_eq!(server_keyring.versions(), vec![2, 3, 4]);
+}
+
+#[test]
+fn should_keep_server_key_in_use_after_rotation() {
+    // A-rrange
+
+    let mut server_keyring = ServerKeyring::new(ServerKey::generate(CipherSuiteId::Ristretto255Sha512));
+
+    server_keyring.rotate().unwrap();
+
+    // A-ct
+
+    let removed_versions = server_keyring.retain_used_versions(&BTreeSet::new());
+
+    // A-ssert
+
+    assert!(removed_versions.is_empty());
+    assert_eq!(server_keyring.versions(), vec![1, 2]);
+}
+
+#[test]
+fn should_reload_keyring_with_mixed_cipher_suites() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("server_setup.key");
+    let path = path.to_str().unwrap();
+
+    let mut server_keyring = load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512).unwrap();
+    server_keyring.rotate_to(CipherSuiteId::P256Sha256).unwrap();
+    server_keyring.save(path).unwrap();
+
+    // A-ct
+
+    let result = load_or_create_server_keyring(path, CipherSuiteId::P256Sha256);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    let reloaded_server_keyring = result.unwrap();
+
+    assert_eq!(reloaded_server_keyring.versions(), vec![1, 2]);
+    assert_eq!(reloaded_server_keyring.get(1).unwrap().cipher_suite(), CipherSuiteId::Ristretto255Sha512);
+    assert_eq!(reloaded_server_keyring.current().cipher_suite(), CipherSuiteId::P256Sha256);
+    assert_eq!(reloaded_server_keyring.current().serialize(), server_keyring.current().serialize());
+}
+
+#[test]
+fn should_rotate_to_configured_cipher_suite() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("server_setup.key");
+    let path = path.to_str().unwrap();
+
+    load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512).unwrap();
+
+    // A-ct
+
+    let server_keyring = load_or_create_server_keyring(path, CipherSuiteId::P256Sha256).unwrap();
+
+    // A-ssert
+
+    let reloaded_server_keyring = load_or_create_server_keyring(path, CipherSuiteId::P256Sha256).unwrap();
+
+    assert_eq!(server_keyring.current_version(), 2);
+    assert_eq!(server_keyring.current().cipher_suite(), CipherSuiteId::P256Sha256);
+    assert_eq!(reloaded_server_keyring.versions(), vec![1, 2]);
+}
+
+#[test]
+fn should_not_create_p256_key_from_ristretto255_setup() {
+    // A-rrange
+
+    let server_key = ServerKey::generate(CipherSuiteId::Ristretto255Sha512);
+
+    // A-ct
+
+    let result = ServerKey::deserialize(CipherSuiteId::P256Sha256, &server_key.serialize());
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_load_existing_server_keyring_without_rotating() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("server_setup.key");
+    let path = path.to_str().unwrap();
+
+    let created_server_keyring = load_or_create_server_keyring(path, CipherSuiteId::Ristretto255Sha512).unwrap();
+    let created_content = fs::read(path).unwrap();
+
+    // A-ct
+
+    let result = load_existing_server_keyring(path);
+
+    // A-ssert
+
+    let server_keyring = result.unwrap();
+
+    assert_eq!(server_keyring.versions(), created_server_keyring.versions());
+    assert_eq!(server_keyring.current().cipher_suite(), CipherSuiteId::Ristretto255Sha512);
+    assert_eq!(fs::read(path).unwrap(), created_content);
+}
+
+#[test]
+fn should_not_create_missing_server_key_file_when_loading_existing_one() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("server_setup.key");
+
+    // A-ct
+
+    let result = load_existing_server_keyring(path.to_str().unwrap());
+
+    // A-ssert
+
+    match result {
+        Err(AuthenticationError::ServerKey(_)) => {}
+        _ => panic!("Test result should be ServerKey."),
+    }
+
+    assert!(!path.exists());
+}
@@ -0,0 +1,157 @@
+use crate::two_factor::TwoFactorRecord;
+
+const NOW: u64 = 1_700_000_000;
+
+#[test]
+fn should_verify_current_code() {
+    // A-rrange
+
+    let mut two_factor_record = TwoFactorRecord::generate();
+    let code = generate_code(&two_factor_record, NOW);
+
+    // A-ct
+
+    let result = two_factor_record.verify_code(&code, NOW);
+
+    // A-ssert
+
+    assert!(result);
+    assert_eq!(two_factor_record.last_used_step, NOW / 30);
+}
+
+#[test]
+fn should_verify_code_within_one_step() {
+    // A-rrange
+
+    let mut two_factor_record = TwoFactorRecord::generate();
+    let previous_code = generate_code(&two_factor_record, NOW - 30);
+    let next_code = generate_code(&two_factor_record, NOW + 30);
+
+    // A-ct
+
+    let previous_result = two_factor_record.clone().verify_code(&previous_code, NOW);
+    let next_result = two_factor_record.verify_code(&next_code, NOW);
+
+    // A-ssert
+
+    assert!(previous_result);
+    assert!(next_result);
+}
+
+#[test]
+fn should_not_verify_code_outside_of_allowed_skew() {
+    // A-rrange
+
+    let mut two_factor_record = TwoFactorRecord::generate();
+    let old_code = generate_code(&two_factor_record, NOW - 90);
+
+    // A-ct
+
+    let result = two_factor_record.verify_code(&old_code, NOW);
+
+    // A-ssert
+
+    assert!(!result);
+}
+
+#[test]
+fn should_not_verify_code_twice() {
+    // A-rrange
+
+    let mut two_factor_record = TwoFactorRecord::generate();
+    let code = generate_code(&two_factor_record, NOW);
+
+    two_factor_record.verify_code(&code, NOW);
+
+    // A-ct
+
+    let result = two_factor_record.verify_code(&code, NOW + 1);
+    let previous_step_result = two_factor_record.verify_code(&generate_code(&two_factor_record, NOW - 30), NOW);
+
+    // A-ssert
+
+    assert!(!result);
+    assert!(!previous_step_result);
+}
+
+#[test]
+fn should_use_recovery_code_once() {
+    // A-rrange
+
+    let mut two_factor_record = TwoFactorRecord::generate();
+    let recovery_codes = two_factor_record.generate_recovery_codes();
+
+    // A-ct
+
+    let result = two_factor_record.verify(&recovery_codes[3].to_uppercase().replace('-', " "), NOW);
+    let second_result = two_factor_record.verify(&recovery_codes[3], NOW);
+
+    // A-ssert
+
+    assert!(result);
+    assert!(!second_result);
+    assert_eq!(recovery_codes.len(), 10);
+    assert_eq!(two_factor_record.recovery_code_hashes.len(), 9);
+}
+
+#[test]
+fn should_not_keep_recovery_codes_in_clear() {
+    // A-rrange
+
+    let mut two_factor_record = TwoFactorRecord::generate();
+    let recovery_codes = two_factor_record.generate_recovery_codes();
+
+    // A-ct
+
+    let content = two_factor_record.serialize();
+
+    // A-ssert
+
+    for recovery_code in recovery_codes {
+        let recovery_code = recovery_code.replace('-', "");
+
+        assert!(!content.windows(recovery_code.len()).any(|window| window == recovery_code.as_bytes()));
+    }
+}
+
+#[test]
+fn should_serialize_and_deserialize_two_factor_record() {
+    // A-rrange
+
+    let mut two_factor_record = TwoFactorRecord::generate();
+    two_factor_record.enabled = true;
+    two_factor_record.last_used_step = NOW / 30;
+    two_factor_record.generate_recovery_codes();
+
+    // A-ct
+
+    let result = TwoFactorRecord::deserialize(&two_factor_record.serialize());
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), two_factor_record);
+}
+
+#[test]
+fn should_not_deserialize_truncated_two_factor_record() {
+    // A-rrange
+
+    let mut two_factor_record = TwoFactorRecord::generate();
+    two_factor_record.generate_recovery_codes();
+
+    let mut content = two_factor_record.serialize();
+    content.truncate(content.len() - 1);
+
+    // A-ct
+
+    let result = TwoFactorRecord::deserialize(&content);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+fn generate_code(two_factor_record: &TwoFactorRecord, time: u64) -> String {
+    two_factor_record.totp("Vault", "username").generate(time)
+}
@@ -0,0 +1,184 @@
+use core_domain::authentication::authentication_error::{AuthenticationError, Result};
+use opaque_ke::rand::{RngCore, rngs::OsRng};
+use sha2::{Digest, Sha256};
+use totp_rs::{Algorithm, TOTP};
+
+const TWO_FACTOR_FILE_MAGIC: &[u8; 4] = b"FVTF";
+const TWO_FACTOR_FORMAT_VERSION: u8 = 1;
+const SECRET_LENGTH: usize = 20;
+const CODE_DIGITS: usize = 6;
+const CODE_STEP: u64 = 30;
+const ALLOWED_STEP_SKEW: u64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_LENGTH: usize = 10;
+const RECOVERY_CODE_GROUP_LENGTH: usize = 4;
+const RECOVERY_CODE_HASH_LENGTH: usize = 32;
+
+const CORRUPT_TWO_FACTOR_FILE: &str = "Two factor file is corrupt.";
+
+// The usual authenticator app settings: SHA-1, 6 digits, 30 second steps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TwoFactorRecord {
+    pub secret: Vec<u8>,
+    pub enabled: bool,
+    pub last_used_step: u64,
+    pub recovery_code_hashes: Vec<[u8; RECOVERY_CODE_HASH_LENGTH]>,
+}
+
+impl TwoFactorRecord {
+    pub fn generate() -> Self {
+        let mut secret = vec![0u8; SECRET_LENGTH];
+
+        OsRng.fill_bytes(&mut secret);
+
+        Self {
+            secret,
+            enabled: false,
+            last_used_step: 0,
+            recovery_code_hashes: Vec::new(),
+        }
+    }
+
+    pub fn totp(&self, issuer: &str, username: &str) -> TOTP {
+        TOTP::new_unchecked(
+            Algorithm::SHA1,
+            CODE_DIGITS,
+            0,
+            CODE_STEP,
+            self.secret.clone(),
+            Some(issuer.to_string()),
+            username.to_string(),
+        )
+    }
+
+    // A code is accepted once, within one step of the server clock.
+    pub fn verify_code(&mut self, code: &str, now: u64) -> bool {
+        let totp = self.totp("", "");
+        let current_step = now / CODE_STEP;
+
+        let matching_step = (current_step.saturating_sub(ALLOWED_STEP_SKEW)..=current_step + ALLOWED_STEP_SKEW)
+            .filter(|step| *step > self.last_used_step)
+            .find(|step| totp.check(code, step * CODE_STEP));
+
+        let Some(matching_step) = matching_step else {
+            return false;
+        };
+
+        self.last_used_step = matching_step;
+
+        true
+    }
+
+    pub fn use_recovery_code(&mut self, recovery_code: &str) -> bool {
+        let recovery_code_hash = hash_recovery_code(recovery_code);
+
+        let Some(index) = self
+            .recovery_code_hashes
+            .iter()
+            .position(|stored_hash| *stored_hash == recovery_code_hash)
+        else {
+            return false;
+        };
+
+        self.recovery_code_hashes.remove(index);
+
+        true
+    }
+
+    pub fn verify(&mut self, code: &str, now: u64) -> bool {
+        self.verify_code(code, now) || self.use_recovery_code(code)
+    }
+
+    // Only the hashes are kept, the codes are shown to the user once.
+    pub fn generate_recovery_codes(&mut self) -> Vec<String> {
+        let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT).map(|_| generate_recovery_code()).collect();
+
+        self.recovery_code_hashes = recovery_codes
+            .iter()
+            .map(|recovery_code| hash_recovery_code(recovery_code))
+            .collect();
+
+        recovery_codes
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut content = Vec::new();
+
+        content.extend_from_slice(TWO_FACTOR_FILE_MAGIC);
+        content.push(TWO_FACTOR_FORMAT_VERSION);
+        content.push(self.enabled as u8);
+        content.extend_from_slice(&self.last_used_step.to_be_bytes());
+        content.push(self.secret.len() as u8);
+        content.extend_from_slice(&self.secret);
+
+        for recovery_code_hash in &self.recovery_code_hashes {
+            content.extend_from_slice(recovery_code_hash);
+        }
+
+        content
+    }
+
+    pub fn deserialize(content: &[u8]) -> Result<Self> {
+        let body = content
+            .strip_prefix(TWO_FACTOR_FILE_MAGIC)
+            .and_then(|body| body.strip_prefix(&[TWO_FACTOR_FORMAT_VERSION]))
+            .ok_or_else(corrupt_two_factor_file)?;
+
+        let Some((&enabled, body)) = body.split_first() else {
+            return Err(corrupt_two_factor_file());
+        };
+
+        let (last_used_step, body) = body
+            .split_first_chunk::<8>()
+            .ok_or_else(corrupt_two_factor_file)?;
+
+        let Some((&secret_length, body)) = body.split_first() else {
+            return Err(corrupt_two_factor_file());
+        };
+
+        if body.len() < secret_length as usize {
+            return Err(corrupt_two_factor_file());
+        }
+
+        let (secret, recovery_code_hashes) = body.split_at(secret_length as usize);
+
+        let (recovery_code_hashes, []) = recovery_code_hashes.as_chunks::<RECOVERY_CODE_HASH_LENGTH>() else {
+            return Err(corrupt_two_factor_file());
+        };
+
+        Ok(Self {
+            secret: secret.to_vec(),
+            enabled: enabled == 1,
+            last_used_step: u64::from_be_bytes(*last_used_step),
+            recovery_code_hashes: recovery_code_hashes.to_vec(),
+        })
+    }
+}
+
+// Recovery codes are random, a fast hash is enough. Dashes, spaces and case are ignored.
+fn hash_recovery_code(recovery_code: &str) -> [u8; RECOVERY_CODE_HASH_LENGTH] {
+    let normalized_recovery_code: String = recovery_code
+        .chars()
+        .filter(|character| character.is_ascii_alphanumeric())
+        .map(|character| character.to_ascii_lowercase())
+        .collect();
+
+    Sha256::digest(normalized_recovery_code.as_bytes()).into()
+}
+
+fn generate_recovery_code() -> String {
+    let mut recovery_code = [0u8; RECOVERY_CODE_LENGTH];
+
+    OsRng.fill_bytes(&mut recovery_code);
+
+    hex::encode(recovery_code)
+        .as_bytes()
+        .chunks(RECOVERY_CODE_GROUP_LENGTH)
+        .map(|group| String::from_utf8_lossy(group).to_string())
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+fn corrupt_two_factor_file() -> AuthenticationError {
+    AuthenticationError::Deserialization(CORRUPT_TWO_FACTOR_FILE.to_string())
+}
@@ -1 +1,8 @@
-pub mod authentication_error;
\ No newline at end of file
+pub mod authentication_error;
+pub mod login_finish;
+pub mod login_start;
+pub mod opaque_parameters;
+pub mod session_refresh;
+pub mod session_summary;
+pub mod signed_request;
+pub mod two_factor_enrollment;
\ No newline at end of file
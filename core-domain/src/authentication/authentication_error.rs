@@ -6,6 +6,20 @@ pub enum AuthenticationError {
     PasswordFileSave(String),
     PasswordFileRetrieve(String),
     CreatingSession(String),
+    ServerKey(String),
+    SessionNotFound(String),
+    TooManyPendingLogins(String),
+    AccountAlreadyExists(String),
+    Throttled(u64),
+    ReplayedRequest(String),
+    ClockSkew(u64),
+    SessionStore(String),
+    SessionRetired(String),
+    SecondFactorRequired(String),
+    InvalidSecondFactorCode(String),
+    SecondFactorState(String),
+    RecoveryNotSetUp(String),
+    FreshLoginRequired(String),
     Internal(String)
 }
 
@@ -19,6 +33,20 @@ impl std::fmt::Display for AuthenticationError {
             AuthenticationError::PasswordFileSave(message) => write!(formatter, "Error while saving password file: {}", message),
             AuthenticationError::PasswordFileRetrieve(message) => write!(formatter, "Error while retrieving password file: {}", message),
             AuthenticationError::CreatingSession(message) => write!(formatter, "Error while creating session: {}", message),
+            AuthenticationError::ServerKey(message) => write!(formatter, "Error with the server key file: {}", message),
+            AuthenticationError::SessionNotFound(message) => write!(formatter, "Session not found: {}", message),
+            AuthenticationError::TooManyPendingLogins(message) => write!(formatter, "Too many pending logins: {}", message),
+            AuthenticationError::AccountAlreadyExists(username) => write!(formatter, "Account {} already exists", username),
+            AuthenticationError::Throttled(retry_after) => write!(formatter, "Too many failed login attempts, retry in {} seconds", retry_after),
+            AuthenticationError::ReplayedRequest(nonce) => write!(formatter, "Request nonce {} was already used", nonce),
+            AuthenticationError::ClockSkew(server_time) => write!(formatter, "Request timestamp is outside the allowed clock skew, server time is {}", server_time),
+            AuthenticationError::SessionStore(message) => write!(formatter, "Error with the session store: {}", message),
+            AuthenticationError::SessionRetired(message) => write!(formatter, "Session token was refreshed: {}", message),
+            AuthenticationError::SecondFactorRequired(message) => write!(formatter, "Second factor required: {}", message),
+            AuthenticationError::InvalidSecondFactorCode(message) => write!(formatter, "Invalid second factor code: {}", message),
+            AuthenticationError::SecondFactorState(message) => write!(formatter, "Two factor authentication: {}", message),
+            AuthenticationError::RecoveryNotSetUp(message) => write!(formatter, "Account recovery is not set up: {}", message),
+            AuthenticationError::FreshLoginRequired(message) => write!(formatter, "Fresh login required: {}", message),
             AuthenticationError::Internal(message) => write!(formatter, "Internal error: {}", message)
         }
     }
@@ -0,0 +1,4 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoginFinish {
+    pub second_factor_required: bool,
+}
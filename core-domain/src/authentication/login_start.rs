@@ -0,0 +1,8 @@
+use crate::authentication::opaque_parameters::OpaqueParameters;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoginStart {
+    pub login_id: String,
+    pub message: Vec<u8>,
+    pub parameters: OpaqueParameters,
+}
@@ -0,0 +1,7 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpaqueParameters {
+    pub cipher_suite: String,
+    pub ksf_parameters: String,
+    pub server_identity: Option<String>,
+    pub context: Option<String>,
+}
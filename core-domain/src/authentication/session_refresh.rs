@@ -0,0 +1,5 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionRefresh {
+    pub salt: Vec<u8>,
+    pub previous_token_expires_at: u64,
+}
@@ -0,0 +1,8 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    pub session_id: String,
+    pub created_at: u64,
+    pub last_used_at: u64,
+    pub client_label: Option<String>,
+    pub current: bool,
+}
@@ -0,0 +1,12 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedRequest {
+    pub bearer_token: String,
+    pub method: String,
+    pub authority: String,
+    pub path: String,
+    pub query: String,
+    pub content_digest: String,
+    pub timestamp: String,
+    pub nonce: String,
+    pub signature: String,
+}
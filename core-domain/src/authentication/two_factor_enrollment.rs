@@ -0,0 +1,5 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct TwoFactorEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
@@ -0,0 +1 @@
+pub mod backup_removal_list_error;
\ No newline at end of file
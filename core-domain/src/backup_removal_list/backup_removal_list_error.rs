@@ -0,0 +1,19 @@
+#[derive(Debug)]
+pub enum BackupRemovalListError {
+    WritingToFile(String),
+    Internal(String)
+}
+
+impl std::fmt::Display for BackupRemovalListError {
+
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            BackupRemovalListError::WritingToFile(message) => write!(formatter, "Error writing to the file: {}", message),
+            BackupRemovalListError::Internal(message) => write!(formatter, "Internal error: {}", message)
+        }
+    }
+}
+
+impl std::error::Error for BackupRemovalListError {}
+
+pub type Result<T> = std::result::Result<T, BackupRemovalListError>;
\ No newline at end of file
@@ -1,2 +1,6 @@
+pub mod account_deletion;
+pub mod password_change;
+pub mod recovery_setup;
 pub mod server_domain;
-pub mod server_domain_errors;
\ No newline at end of file
+pub mod server_domain_errors;
+pub mod username;
\ No newline at end of file
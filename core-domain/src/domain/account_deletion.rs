@@ -0,0 +1,8 @@
+// The username is not kept, the account and its personal data are gone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountDeletion {
+    pub deleted: Vec<String>,
+    pub revoked_sessions: usize,
+    // Why the backups could not be flagged for removal, the account is deleted either way.
+    pub backup_removal_error: Option<String>,
+}
@@ -0,0 +1,34 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordChange {
+    pub registration_message: Vec<u8>,
+    pub vault: Vec<u8>,
+}
+
+// On the wire a password change is the length of the registration upload as a big endian u32,
+// the registration upload, then the vault re-encrypted with the new export key.
+impl PasswordChange {
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut content = (self.registration_message.len() as u32).to_be_bytes().to_vec();
+
+        content.extend_from_slice(&self.registration_message);
+        content.extend_from_slice(&self.vault);
+
+        content
+    }
+
+    pub fn deserialize(content: &[u8]) -> Option<Self> {
+        let (length, rest) = content.split_first_chunk::<4>()?;
+        let length = u32::from_be_bytes(*length) as usize;
+
+        if rest.len() < length {
+            return None;
+        }
+
+        let (registration_message, vault) = rest.split_at(length);
+
+        Some(Self {
+            registration_message: registration_message.to_vec(),
+            vault: vault.to_vec(),
+        })
+    }
+}
@@ -0,0 +1,34 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoverySetup {
+    pub registration_message: Vec<u8>,
+    pub wrapped_key: Vec<u8>,
+}
+
+// Same layout as a password change: the length of the registration upload as a big endian u32,
+// the registration upload, then the vault key wrapped by the client with the recovery secret.
+impl RecoverySetup {
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut content = (self.registration_message.len() as u32).to_be_bytes().to_vec();
+
+        content.extend_from_slice(&self.registration_message);
+        content.extend_from_slice(&self.wrapped_key);
+
+        content
+    }
+
+    pub fn deserialize(content: &[u8]) -> Option<Self> {
+        let (length, rest) = content.split_first_chunk::<4>()?;
+        let length = u32::from_be_bytes(*length) as usize;
+
+        if rest.len() < length {
+            return None;
+        }
+
+        let (registration_message, wrapped_key) = rest.split_at(length);
+
+        Some(Self {
+            registration_message: registration_message.to_vec(),
+            wrapped_key: wrapped_key.to_vec(),
+        })
+    }
+}
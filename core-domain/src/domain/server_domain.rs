@@ -1,70 +1,169 @@
 use crate::{
-    authentication::authentication_error::AuthenticationError,
-    domain::server_domain_errors::{Result, ServerDomainError},
-    ports::{authentication::Authentication, vault_store::VaultStore},
+    authentication::{
+        authentication_error::AuthenticationError, login_finish::LoginFinish, login_start::LoginStart,
+        opaque_parameters::OpaqueParameters, session_refresh::SessionRefresh,
+        session_summary::SessionSummary,
+        signed_request::SignedRequest, two_factor_enrollment::TwoFactorEnrollment,
+    },
+    domain::{
+        account_deletion::AccountDeletion,
+        password_change::PasswordChange,
+        recovery_setup::RecoverySetup,
+        server_domain_errors::{Result, ServerDomainError},
+        username::Username,
+    },
+    ports::{authentication::Authentication, backup_removal_list::BackupRemovalList, vault_store::VaultStore},
     vault_store::vault_store_error::VaultStoreError,
 };
 
 const INVALID_BEARER_TOKEN: &'static str = "Invalid bearer token.";
 const INVALID_SIGNATURE: &'static str = "Invalid request signature.";
-const INVALID_REQUEST: &'static str = "Invalid request, it outlived its duration.";
+const INVALID_CONTENT_DIGEST: &str = "Content-Digest does not match the request body.";
+const PASSWORD_CHANGE_ROLLBACK_FAILED: &str = "restoring the previous credentials failed";
+const RECOVERY_SESSION_ONLY_RESETS: &str = "A recovery session can only reset the master password.";
+const NOT_A_RECOVERY_SESSION: &str = "Log in with the recovery credential first.";
+const DELETED_VAULT: &str = "vault";
 
 pub trait Domain<VS: VaultStore, A: Authentication> {
+    fn opaque_parameters(&self) -> OpaqueParameters;
     fn start_server_registration(&self, username: &str, client_message: Vec<u8>)
     -> Result<Vec<u8>>;
     fn finish_server_registration(&self, username: &str, client_message: Vec<u8>) -> Result<()>;
-    fn start_server_login(&mut self, username: &str, client_message: Vec<u8>) -> Result<Vec<u8>>;
-    fn finish_server_login(&mut self, username: &str, client_message: Vec<u8>) -> Result<()>;
-    fn get_vault(
-        &self,
-        bearer_token: &str,
-        verb: &str,
-        uri: &str,
-        timestamp: &str,
-        signature: &str,
+    fn start_server_login(
+        &mut self,
+        username: &str,
+        client_ip: Option<&str>,
+        client_message: Vec<u8>,
+    ) -> Result<LoginStart>;
+    fn finish_server_login(
+        &mut self,
+        username: &str,
+        login_id: &str,
+        client_message: Vec<u8>,
+        client_label: Option<&str>,
+    ) -> Result<LoginFinish>;
+    fn get_vault(&mut self, signed_request: &SignedRequest) -> Result<Vec<u8>>;
+    fn save_vault(&mut self, signed_request: &SignedRequest, vault: Vec<u8>) -> Result<()>;
+    fn start_password_change(
+        &mut self,
+        signed_request: &SignedRequest,
+        client_message: Vec<u8>,
     ) -> Result<Vec<u8>>;
-    fn save_vault(
-        &self,
-        bearer_token: &str,
-        verb: &str,
-        uri: &str,
-        timestamp: &str,
-        signature: &str,
-        vault: Vec<u8>,
-    ) -> Result<()>;
+    fn finish_password_change(
+        &mut self,
+        signed_request: &SignedRequest,
+        password_change: PasswordChange,
+    ) -> Result<usize>;
+    fn purge_expired_sessions(&mut self) -> Result<usize>;
+    fn server_time(&self) -> Result<u64>;
+    fn logout(&mut self, signed_request: &SignedRequest) -> Result<()>;
+    fn logout_all(&mut self, signed_request: &SignedRequest) -> Result<usize>;
+    fn list_sessions(&mut self, signed_request: &SignedRequest) -> Result<Vec<SessionSummary>>;
+    fn end_session(&mut self, signed_request: &SignedRequest, session_id: &str) -> Result<()>;
+    fn refresh_session(&mut self, signed_request: &SignedRequest) -> Result<SessionRefresh>;
+    fn start_two_factor_enrollment(&mut self, signed_request: &SignedRequest) -> Result<TwoFactorEnrollment>;
+    fn confirm_two_factor_enrollment(&mut self, signed_request: &SignedRequest, code: &str) -> Result<Vec<String>>;
+    fn verify_second_factor(&mut self, signed_request: &SignedRequest, code: &str) -> Result<()>;
+    fn disable_two_factor(&mut self, signed_request: &SignedRequest, code: &str) -> Result<()>;
+    fn start_recovery_setup(&mut self, signed_request: &SignedRequest, client_message: Vec<u8>) -> Result<Vec<u8>>;
+    fn finish_recovery_setup(&mut self, signed_request: &SignedRequest, recovery_setup: RecoverySetup) -> Result<()>;
+    fn start_recovery_login(
+        &mut self,
+        username: &str,
+        client_ip: Option<&str>,
+        client_message: Vec<u8>,
+    ) -> Result<LoginStart>;
+    fn finish_recovery_login(
+        &mut self,
+        username: &str,
+        login_id: &str,
+        client_message: Vec<u8>,
+        client_label: Option<&str>,
+    ) -> Result<LoginFinish>;
+    fn get_recovery_key(&mut self, signed_request: &SignedRequest) -> Result<Vec<u8>>;
+    fn start_recovery_reset(&mut self, signed_request: &SignedRequest, client_message: Vec<u8>) -> Result<Vec<u8>>;
+    fn finish_recovery_reset(&mut self, signed_request: &SignedRequest, password_change: PasswordChange) -> Result<usize>;
+    fn delete_account(&mut self, signed_request: &SignedRequest) -> Result<AccountDeletion>;
 }
 
-pub struct ServerDomain<VS: VaultStore, A: Authentication> {
+pub struct ServerDomain<VS: VaultStore, A: Authentication, BR: BackupRemovalList> {
     vault_store: VS,
     authentication: A,
+    backup_removal_list: BR,
 }
 
-impl<VS: VaultStore, A: Authentication> ServerDomain<VS, A> {
-    pub fn new(vault_store: VS, authentication: A) -> Self {
+impl<VS: VaultStore, A: Authentication, BR: BackupRemovalList> ServerDomain<VS, A, BR> {
+    pub fn new(vault_store: VS, authentication: A, backup_removal_list: BR) -> Self {
         Self {
             vault_store,
             authentication,
+            backup_removal_list,
         }
     }
 
     fn verify_request_and_get_username(
-        &self,
-        bearer_token: &str,
-        verb: &str,
-        uri: &str,
-        timestamp: &str,
-        signature: &str,
-    ) -> Result<String> {
+        &mut self,
+        signed_request: &SignedRequest,
+        body: &[u8],
+    ) -> Result<Username> {
+        let (username, recovery) = self.verify_request_and_get_session(signed_request, body)?;
+
+        if recovery {
+            return Err(ServerDomainError::Forbidden(RECOVERY_SESSION_ONLY_RESETS.to_string()));
+        }
+
+        Ok(username)
+    }
+
+    fn verify_recovery_request_and_get_username(
+        &mut self,
+        signed_request: &SignedRequest,
+        body: &[u8],
+    ) -> Result<Username> {
+        let (username, recovery) = self.verify_request_and_get_session(signed_request, body)?;
+
+        if !recovery {
+            return Err(ServerDomainError::Forbidden(NOT_A_RECOVERY_SESSION.to_string()));
+        }
+
+        Ok(username)
+    }
+
+    // Gives the username and whether the session comes from a recovery login.
+    fn verify_request_and_get_session(
+        &mut self,
+        signed_request: &SignedRequest,
+        body: &[u8],
+    ) -> Result<(Username, bool)> {
+        let username = self.verify_request_and_get_pending_username(signed_request, body)?;
+
+        self.authentication
+            .ensure_second_factor_verified(&signed_request.bearer_token)
+            .map_err(authentication_error_to_server_domain_error)?;
+
+        let recovery = self
+            .authentication
+            .is_recovery_session(&signed_request.bearer_token)
+            .map_err(authentication_error_to_server_domain_error)?;
+
+        Ok((username, recovery))
+    }
+
+    // Also accepts a session still waiting for its second factor, only for the step-up and the logout.
+    fn verify_request_and_get_pending_username(
+        &mut self,
+        signed_request: &SignedRequest,
+        body: &[u8],
+    ) -> Result<Username> {
+        let bearer_token = &signed_request.bearer_token;
+
         if !self.authentication.verify_bearer_token(bearer_token) {
             return Err(ServerDomainError::Forbidden(
                 INVALID_BEARER_TOKEN.to_string(),
             ));
         }
 
-        match self
-            .authentication
-            .verify_signature(bearer_token, verb, uri, timestamp, signature)
-        {
+        match self.authentication.verify_signature(signed_request) {
             Ok(value) if !value => {
                 return Err(ServerDomainError::Forbidden(INVALID_SIGNATURE.to_string()));
             }
@@ -72,86 +171,382 @@ impl<VS: VaultStore, A: Authentication> ServerDomain<VS, A> {
             _ => {}
         }
 
-        match self.authentication.verify_request_timestamp(timestamp) {
-            Ok(value) if !value => {
-                return Err(ServerDomainError::Forbidden(INVALID_REQUEST.to_string()));
-            }
-            Err(error) => return Err(ServerDomainError::Internal(error.to_string())),
-            _ => {}
+        self.authentication
+            .verify_request_timestamp(&signed_request.timestamp)
+            .map_err(authentication_error_to_server_domain_error)?;
+
+        // The signature covers the Content-Digest header, this binds the body to the signature.
+        if !self
+            .authentication
+            .verify_content_digest(&signed_request.content_digest, body)
+        {
+            return Err(ServerDomainError::Forbidden(INVALID_CONTENT_DIGEST.to_string()));
         }
 
+        // Only checked once the request is known to be genuine, a forged request must not burn a nonce.
+        self.authentication
+            .verify_nonce(signed_request)
+            .map_err(authentication_error_to_server_domain_error)?;
+
+        self.authentication
+            .touch_session(bearer_token)
+            .map_err(authentication_error_to_server_domain_error)?;
+
         self.authentication
             .get_username_from_session(bearer_token)
             .map_err(authentication_error_to_server_domain_error)
     }
+
+    // Swaps the password file and the vault together, the previous ones are put back if a write fails.
+    fn change_password(&self, username: &Username, password_change: PasswordChange) -> Result<()> {
+        let previous_vault = self
+            .vault_store
+            .retrieve(username)
+            .map_err(vault_store_error_to_server_domain_error)?;
+
+        let previous_password_file = self
+            .authentication
+            .finish_password_change(username, password_change.registration_message)
+            .map_err(authentication_error_to_server_domain_error)?;
+
+        if let Err(error) = self.vault_store.save(username, password_change.vault) {
+            return Err(self.rollback_password_change(
+                username,
+                previous_password_file,
+                previous_vault,
+                vault_store_error_to_server_domain_error(error),
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Puts back the credentials and the vault as they were before a failed password change.
+    fn rollback_password_change(
+        &self,
+        username: &Username,
+        previous_password_file: Vec<u8>,
+        previous_vault: Vec<u8>,
+        error: ServerDomainError,
+    ) -> ServerDomainError {
+        let vault_rollback = self
+            .vault_store
+            .save(username, previous_vault)
+            .map_err(vault_store_error_to_server_domain_error);
+
+        let password_file_rollback = self
+            .authentication
+            .restore_password_file(username, previous_password_file)
+            .map_err(authentication_error_to_server_domain_error);
+
+        match vault_rollback.and(password_file_rollback) {
+            Ok(_) => error,
+            Err(rollback_error) => ServerDomainError::Internal(format!(
+                "{} ({}: {})",
+                error, PASSWORD_CHANGE_ROLLBACK_FAILED, rollback_error
+            )),
+        }
+    }
 }
 
-impl<VS: VaultStore, A: Authentication> Domain<VS, A> for ServerDomain<VS, A> {
+impl<VS: VaultStore, A: Authentication, BR: BackupRemovalList> Domain<VS, A> for ServerDomain<VS, A, BR> {
+    fn opaque_parameters(&self) -> OpaqueParameters {
+        self.authentication.opaque_parameters()
+    }
+
     fn start_server_registration(
         &self,
         username: &str,
         client_message: Vec<u8>,
     ) -> Result<Vec<u8>> {
+        let username = parse_username(username)?;
+
         self.authentication
-            .start_server_registration(username, client_message)
+            .start_server_registration(&username, client_message)
             .map_err(authentication_error_to_server_domain_error)
     }
 
     fn finish_server_registration(&self, username: &str, client_message: Vec<u8>) -> Result<()> {
+        let username = parse_username(username)?;
+
         self.authentication
-            .finish_server_registration(username, client_message)
+            .finish_server_registration(&username, client_message)
             .map_err(authentication_error_to_server_domain_error)?;
 
-        self.vault_store.save(username, vec![])
+        self.vault_store.save(&username, vec![])
             .map_err(vault_store_error_to_server_domain_error)?;
 
         Ok(())
     }
 
-    fn start_server_login(&mut self, username: &str, client_message: Vec<u8>) -> Result<Vec<u8>> {
+    fn start_server_login(
+        &mut self,
+        username: &str,
+        client_ip: Option<&str>,
+        client_message: Vec<u8>,
+    ) -> Result<LoginStart> {
+        let username = parse_username(username)?;
+
         self.authentication
-            .start_server_login(username, client_message)
+            .start_server_login(&username, client_ip, client_message)
             .map_err(authentication_error_to_server_domain_error)
     }
 
-    fn finish_server_login(&mut self, username: &str, client_message: Vec<u8>) -> Result<()> {
+    fn finish_server_login(
+        &mut self,
+        username: &str,
+        login_id: &str,
+        client_message: Vec<u8>,
+        client_label: Option<&str>,
+    ) -> Result<LoginFinish> {
+        let username = parse_username(username)?;
+
         self.authentication
-            .finish_server_login(username, client_message)
+            .finish_server_login(&username, login_id, client_message, client_label)
             .map_err(authentication_error_to_server_domain_error)
     }
 
-    fn get_vault(
-        &self,
-        bearer_token: &str,
-        verb: &str,
-        uri: &str,
-        timestamp: &str,
-        signature: &str,
-    ) -> Result<Vec<u8>> {
-        let username =
-            self.verify_request_and_get_username(bearer_token, verb, uri, timestamp, signature)?;
+    // A recovery session reads the vault too, the client re-encrypts it during the reset.
+    fn get_vault(&mut self, signed_request: &SignedRequest) -> Result<Vec<u8>> {
+        let (username, _) =
+            self.verify_request_and_get_session(signed_request, &[])?;
 
         self.vault_store
             .retrieve(&username)
             .map_err(vault_store_error_to_server_domain_error)
     }
 
-    fn save_vault(
-        &self,
-        bearer_token: &str,
-        verb: &str,
-        uri: &str,
-        timestamp: &str,
-        signature: &str,
-        vault: Vec<u8>,
-    ) -> Result<()> {
+    fn save_vault(&mut self, signed_request: &SignedRequest, vault: Vec<u8>) -> Result<()> {
         let username =
-            self.verify_request_and_get_username(bearer_token, verb, uri, timestamp, signature)?;
+            self.verify_request_and_get_username(signed_request, &vault)?;
 
         self.vault_store
             .save(&username, vault)
             .map_err(vault_store_error_to_server_domain_error)
     }
+
+    fn start_password_change(
+        &mut self,
+        signed_request: &SignedRequest,
+        client_message: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        let username =
+            self.verify_request_and_get_username(signed_request, &client_message)?;
+
+        self.authentication
+            .start_password_change(&username, client_message)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn finish_password_change(
+        &mut self,
+        signed_request: &SignedRequest,
+        password_change: PasswordChange,
+    ) -> Result<usize> {
+        let username =
+            self.verify_request_and_get_username(signed_request, &password_change.serialize())?;
+
+        self.change_password(&username, password_change)?;
+
+        self.authentication
+            .revoke_other_sessions(&signed_request.bearer_token)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn purge_expired_sessions(&mut self) -> Result<usize> {
+        self.authentication
+            .purge_expired_sessions()
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn server_time(&self) -> Result<u64> {
+        self.authentication
+            .current_timestamp()
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn logout(&mut self, signed_request: &SignedRequest) -> Result<()> {
+        self.verify_request_and_get_pending_username(signed_request, &[])?;
+
+        self.authentication
+            .revoke_session(&signed_request.bearer_token)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn logout_all(&mut self, signed_request: &SignedRequest) -> Result<usize> {
+        let username =
+            self.verify_request_and_get_username(signed_request, &[])?;
+
+        self.authentication
+            .revoke_user_sessions(&username)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn list_sessions(&mut self, signed_request: &SignedRequest) -> Result<Vec<SessionSummary>> {
+        self.verify_request_and_get_username(signed_request, &[])?;
+
+        self.authentication
+            .list_user_sessions(&signed_request.bearer_token)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn end_session(&mut self, signed_request: &SignedRequest, session_id: &str) -> Result<()> {
+        let username =
+            self.verify_request_and_get_username(signed_request, &[])?;
+
+        self.authentication
+            .revoke_user_session(&username, session_id)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn refresh_session(&mut self, signed_request: &SignedRequest) -> Result<SessionRefresh> {
+        self.verify_request_and_get_username(signed_request, &[])?;
+
+        self.authentication
+            .refresh_session(&signed_request.bearer_token)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn start_two_factor_enrollment(&mut self, signed_request: &SignedRequest) -> Result<TwoFactorEnrollment> {
+        let username = self.verify_request_and_get_username(signed_request, &[])?;
+
+        self.authentication
+            .start_two_factor_enrollment(&username)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn confirm_two_factor_enrollment(&mut self, signed_request: &SignedRequest, code: &str) -> Result<Vec<String>> {
+        let username = self.verify_request_and_get_username(signed_request, code.as_bytes())?;
+
+        self.authentication
+            .confirm_two_factor_enrollment(&username, code)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn verify_second_factor(&mut self, signed_request: &SignedRequest, code: &str) -> Result<()> {
+        self.verify_request_and_get_pending_username(signed_request, code.as_bytes())?;
+
+        self.authentication
+            .verify_second_factor(&signed_request.bearer_token, code)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn disable_two_factor(&mut self, signed_request: &SignedRequest, code: &str) -> Result<()> {
+        let username = self.verify_request_and_get_username(signed_request, code.as_bytes())?;
+
+        self.authentication
+            .disable_two_factor(&username, code)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn start_recovery_setup(&mut self, signed_request: &SignedRequest, client_message: Vec<u8>) -> Result<Vec<u8>> {
+        let username = self.verify_request_and_get_username(signed_request, &client_message)?;
+
+        self.authentication
+            .start_recovery_registration(&username, client_message)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn finish_recovery_setup(&mut self, signed_request: &SignedRequest, recovery_setup: RecoverySetup) -> Result<()> {
+        let username = self.verify_request_and_get_username(signed_request, &recovery_setup.serialize())?;
+
+        self.authentication
+            .finish_recovery_registration(&username, recovery_setup.registration_message, recovery_setup.wrapped_key)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn start_recovery_login(
+        &mut self,
+        username: &str,
+        client_ip: Option<&str>,
+        client_message: Vec<u8>,
+    ) -> Result<LoginStart> {
+        let username = parse_username(username)?;
+
+        self.authentication
+            .start_recovery_login(&username, client_ip, client_message)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn finish_recovery_login(
+        &mut self,
+        username: &str,
+        login_id: &str,
+        client_message: Vec<u8>,
+        client_label: Option<&str>,
+    ) -> Result<LoginFinish> {
+        let username = parse_username(username)?;
+
+        self.authentication
+            .finish_recovery_login(&username, login_id, client_message, client_label)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn get_recovery_key(&mut self, signed_request: &SignedRequest) -> Result<Vec<u8>> {
+        let username = self.verify_recovery_request_and_get_username(signed_request, &[])?;
+
+        self.authentication
+            .recovery_key(&username)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    fn start_recovery_reset(&mut self, signed_request: &SignedRequest, client_message: Vec<u8>) -> Result<Vec<u8>> {
+        let username = self.verify_recovery_request_and_get_username(signed_request, &client_message)?;
+
+        self.authentication
+            .start_password_change(&username, client_message)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    // Whoever held the old master password is logged out, the recovery session included.
+    fn finish_recovery_reset(&mut self, signed_request: &SignedRequest, password_change: PasswordChange) -> Result<usize> {
+        let username = self.verify_recovery_request_and_get_username(signed_request, &password_change.serialize())?;
+
+        self.change_password(&username, password_change)?;
+
+        self.authentication
+            .revoke_user_sessions(&username)
+            .map_err(authentication_error_to_server_domain_error)
+    }
+
+    // The credentials go first, a failure past them leaves an account nobody can log in to rather than one without
+    // its vault. The sessions go last so a fresh session can retry what failed. Flagging the backups comes once the
+    // account is gone, so its failure is only reported.
+    fn delete_account(&mut self, signed_request: &SignedRequest) -> Result<AccountDeletion> {
+        let username = self.verify_request_and_get_username(signed_request, &[])?;
+
+        self.authentication
+            .ensure_fresh_login(&signed_request.bearer_token)
+            .map_err(authentication_error_to_server_domain_error)?;
+
+        let mut deleted = self
+            .authentication
+            .delete_credentials(&username)
+            .map_err(authentication_error_to_server_domain_error)?;
+
+        match self.vault_store.delete(&username) {
+            Ok(_) => deleted.push(DELETED_VAULT.to_string()),
+            Err(VaultStoreError::VaultNotFound(_)) => {}
+            Err(error) => return Err(vault_store_error_to_server_domain_error(error)),
+        }
+
+        let revoked_sessions = self
+            .authentication
+            .revoke_user_sessions(&username)
+            .map_err(authentication_error_to_server_domain_error)?;
+
+        let backup_removal_error = self.backup_removal_list.flag(&username).err().map(|error| error.to_string());
+
+        Ok(AccountDeletion {
+            deleted,
+            revoked_sessions,
+            backup_removal_error,
+        })
+    }
+}
+
+fn parse_username(username: &str) -> Result<Username> {
+    Username::parse(username).map_err(|error| ServerDomainError::InvalidUsername(error.to_string()))
 }
 
 fn authentication_error_to_server_domain_error(
@@ -165,6 +560,20 @@ fn authentication_error_to_server_domain_error(
         AuthenticationError::PasswordFileRetrieve(error) => ServerDomainError::Forbidden(error),
         AuthenticationError::Internal(error) => ServerDomainError::Internal(error),
         AuthenticationError::PasswordFileSave(error) => ServerDomainError::Internal(error),
+        AuthenticationError::ServerKey(error) => ServerDomainError::Internal(error),
+        AuthenticationError::SessionNotFound(error) => ServerDomainError::NotFound(error),
+        AuthenticationError::TooManyPendingLogins(error) => ServerDomainError::TooManyRequests(error),
+        AuthenticationError::AccountAlreadyExists(error) => ServerDomainError::Conflict(error),
+        AuthenticationError::Throttled(retry_after) => ServerDomainError::Throttled(retry_after),
+        AuthenticationError::ReplayedRequest(error) => ServerDomainError::ReplayedRequest(error),
+        AuthenticationError::ClockSkew(server_time) => ServerDomainError::ClockSkew(server_time),
+        AuthenticationError::SessionStore(error) => ServerDomainError::Internal(error),
+        AuthenticationError::SessionRetired(error) => ServerDomainError::Forbidden(error),
+        AuthenticationError::SecondFactorRequired(error) => ServerDomainError::SecondFactorRequired(error),
+        AuthenticationError::InvalidSecondFactorCode(error) => ServerDomainError::Forbidden(error),
+        AuthenticationError::SecondFactorState(error) => ServerDomainError::Conflict(error),
+        AuthenticationError::RecoveryNotSetUp(error) => ServerDomainError::NotFound(error),
+        AuthenticationError::FreshLoginRequired(error) => ServerDomainError::FreshLoginRequired(error),
     }
 }
 
@@ -1,6 +1,15 @@
 #[derive(Debug)]
 pub enum ServerDomainError {
+    InvalidUsername(String),
     Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    TooManyRequests(String),
+    Throttled(u64),
+    ReplayedRequest(String),
+    ClockSkew(u64),
+    SecondFactorRequired(String),
+    FreshLoginRequired(String),
     Internal(String)
 }
 
@@ -8,7 +17,16 @@ impl std::fmt::Display for ServerDomainError {
 
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
         match &self {
+            ServerDomainError::InvalidUsername(message) => write!(formatter, "Invalid username: {}", message),
             ServerDomainError::Forbidden(message) => write!(formatter, "Error during registration phase: {}", message),
+            ServerDomainError::NotFound(message) => write!(formatter, "Resource not found: {}", message),
+            ServerDomainError::Conflict(message) => write!(formatter, "Conflict: {}", message),
+            ServerDomainError::TooManyRequests(message) => write!(formatter, "Too many requests: {}", message),
+            ServerDomainError::Throttled(retry_after) => write!(formatter, "Too many failed attempts, retry in {} seconds", retry_after),
+            ServerDomainError::ReplayedRequest(message) => write!(formatter, "Replayed request: {}", message),
+            ServerDomainError::ClockSkew(server_time) => write!(formatter, "Request timestamp is outside the allowed clock skew, server time is {}", server_time),
+            ServerDomainError::SecondFactorRequired(message) => write!(formatter, "Second factor required: {}", message),
+            ServerDomainError::FreshLoginRequired(message) => write!(formatter, "Fresh login required: {}", message),
             ServerDomainError::Internal(message) => write!(formatter, "Error during login phase: {}", message),
         }
     }
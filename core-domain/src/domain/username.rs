@@ -0,0 +1,75 @@
+use caseless::default_case_fold_str;
+use unicode_normalization::UnicodeNormalization;
+
+pub const MAX_USERNAME_LENGTH: usize = 64;
+
+const ALLOWED_PUNCTUATION: [char; 4] = ['.', '_', '-', '@'];
+
+#[derive(Debug, PartialEq)]
+pub enum UsernameError {
+    Empty,
+    TooLong(usize),
+    InvalidCharacter(char),
+    InvalidFirstCharacter(char),
+}
+
+impl std::fmt::Display for UsernameError {
+
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            UsernameError::Empty => write!(formatter, "Username is empty"),
+            UsernameError::TooLong(length) => write!(formatter, "Username is {} characters long, the maximum is {}", length, MAX_USERNAME_LENGTH),
+            UsernameError::InvalidCharacter(character) => write!(formatter, "Username contains the invalid character {:?}", character),
+            UsernameError::InvalidFirstCharacter(character) => write!(formatter, "Username cannot start with {:?}", character),
+        }
+    }
+}
+
+impl std::error::Error for UsernameError {}
+
+// The canonical form of a username, the only one the ports ever see. Two names that differ in Unicode form
+// or letter case give the same Username, so they are the same account.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Username(String);
+
+impl Username {
+    // NFKC, then case folding, then NFKC again since folding can leave a string that is not normalised.
+    pub fn parse(raw_username: &str) -> Result<Self, UsernameError> {
+        let username: String = default_case_fold_str(&raw_username.nfkc().collect::<String>()).nfkc().collect();
+
+        let Some(first_character) = username.chars().next() else {
+            return Err(UsernameError::Empty);
+        };
+
+        let length = username.chars().count();
+
+        if length > MAX_USERNAME_LENGTH {
+            return Err(UsernameError::TooLong(length));
+        }
+
+        if let Some(character) = username
+            .chars()
+            .find(|character| !character.is_alphanumeric() && !ALLOWED_PUNCTUATION.contains(character))
+        {
+            return Err(UsernameError::InvalidCharacter(character));
+        }
+
+        // Keeps names like "." or ".." and hidden files out of the storage directories.
+        if !first_character.is_alphanumeric() {
+            return Err(UsernameError::InvalidFirstCharacter(first_character));
+        }
+
+        Ok(Self(username))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for Username {
+
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "{}", self.0)
+    }
+}
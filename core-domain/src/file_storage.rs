@@ -1 +1,3 @@
+pub mod file_listing;
+pub mod file_metadata;
 pub mod file_storage_error;
\ No newline at end of file
@@ -0,0 +1,28 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileListing {
+    pub file_names: Vec<String>,
+    pub next_start_after: Option<String>,
+}
+
+impl FileListing {
+    // Pages are in name order. A full page gives its last name back, the next page starts after it.
+    pub fn page(mut file_names: Vec<String>, prefix: &str, start_after: Option<&str>, limit: usize) -> Self {
+        file_names.retain(|file_name| {
+            file_name.starts_with(prefix) && start_after.is_none_or(|start_after| file_name.as_str() > start_after)
+        });
+        file_names.sort();
+
+        let next_start_after = if file_names.len() > limit && limit > 0 {
+            Some(file_names[limit - 1].clone())
+        } else {
+            None
+        };
+
+        file_names.truncate(limit);
+
+        Self {
+            file_names,
+            next_start_after,
+        }
+    }
+}
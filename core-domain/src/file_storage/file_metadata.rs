@@ -0,0 +1,5 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileMetadata {
+    pub size: u64,
+    pub modified_at: u64,
+}
@@ -1,9 +1,12 @@
 #[derive(Debug)]
 pub enum FileStorageError {
     FileNotFound(String),
+    AlreadyExists(String),
     PermissionDenied(String),
     ReadingFile(String),
     WritingToFile(String),
+    InvalidFileName(String),
+    InvalidPageLimit(String),
     Internal(String)
 }
 
@@ -12,9 +15,12 @@ impl std::fmt::Display for FileStorageError {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
         match &self {
             FileStorageError::FileNotFound(path) => write!(formatter, "Vault {} not found", path),
+            FileStorageError::AlreadyExists(path) => write!(formatter, "File {} already exists", path),
             FileStorageError::PermissionDenied(path) => write!(formatter, "You don't have access to this path: {}", path),
             FileStorageError::ReadingFile(message) => write!(formatter, "Error during file reading: {}", message),
             FileStorageError::WritingToFile(message) => write!(formatter, "Error writing to the file: {}", message),
+            FileStorageError::InvalidFileName(message) => write!(formatter, "Invalid file name: {}", message),
+            FileStorageError::InvalidPageLimit(message) => write!(formatter, "Invalid page limit: {}", message),
             FileStorageError::Internal(message) => write!(formatter, "Internal error: {}", message)
         }
     }
@@ -3,6 +3,8 @@ pub mod authentication;
 pub mod ports;
 pub mod domain;
 pub mod file_storage;
+pub mod session_store;
+pub mod backup_removal_list;
 pub mod utils;
 
 #[cfg(test)]
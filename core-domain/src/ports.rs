@@ -1,3 +1,5 @@
 pub mod authentication;
 pub mod vault_store;
-pub mod file_storage;
\ No newline at end of file
+pub mod file_storage;
+pub mod session_store;
+pub mod backup_removal_list;
\ No newline at end of file
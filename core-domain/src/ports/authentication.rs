@@ -1,13 +1,42 @@
-use crate::authentication::authentication_error::Result;
+use crate::authentication::{authentication_error::Result, login_finish::LoginFinish, login_start::LoginStart, opaque_parameters::OpaqueParameters, session_refresh::SessionRefresh, session_summary::SessionSummary, signed_request::SignedRequest, two_factor_enrollment::TwoFactorEnrollment};
+use crate::domain::username::Username;
 
 pub trait Authentication {
 
-    fn start_server_registration(&self, username: &str, client_registration_message: Vec<u8>) -> Result<Vec<u8>>;
-    fn finish_server_registration(&self, username: &str, client_registration_message: Vec<u8>) -> Result<()>;
-    fn start_server_login(&mut self, username: &str, client_login_message: Vec<u8>) -> Result<Vec<u8>>;
-    fn finish_server_login(&mut self, username: &str, client_login_message: Vec<u8>) -> Result<()>;
+    fn opaque_parameters(&self) -> OpaqueParameters;
+    fn start_server_registration(&self, username: &Username, client_registration_message: Vec<u8>) -> Result<Vec<u8>>;
+    fn finish_server_registration(&self, username: &Username, client_registration_message: Vec<u8>) -> Result<()>;
+    fn start_password_change(&self, username: &Username, client_registration_message: Vec<u8>) -> Result<Vec<u8>>;
+    fn finish_password_change(&self, username: &Username, client_registration_message: Vec<u8>) -> Result<Vec<u8>>;
+    fn restore_password_file(&self, username: &Username, password_file: Vec<u8>) -> Result<()>;
+    fn start_server_login(&mut self, username: &Username, client_ip: Option<&str>, client_login_message: Vec<u8>) -> Result<LoginStart>;
+    fn finish_server_login(&mut self, username: &Username, login_id: &str, client_login_message: Vec<u8>, client_label: Option<&str>) -> Result<LoginFinish>;
     fn verify_bearer_token(&self, bearer_token: &str) -> bool;
-    fn verify_signature(&self, bearer_token: &str, verb: &str, uri: &str, timestamp: &str, signature: &str) -> Result<bool>;
-    fn verify_request_timestamp(&self, request_creation_timestamp: &str) -> Result<bool>;
-    fn get_username_from_session(&self, bearer_token: &str) -> Result<String>;
+    fn verify_signature(&self, signed_request: &SignedRequest) -> Result<bool>;
+    fn verify_content_digest(&self, content_digest: &str, body: &[u8]) -> bool;
+    fn verify_request_timestamp(&self, request_creation_timestamp: &str) -> Result<()>;
+    fn current_timestamp(&self) -> Result<u64>;
+    fn verify_nonce(&mut self, signed_request: &SignedRequest) -> Result<()>;
+    fn get_username_from_session(&self, bearer_token: &str) -> Result<Username>;
+    fn touch_session(&mut self, bearer_token: &str) -> Result<()>;
+    fn purge_expired_sessions(&mut self) -> Result<usize>;
+    fn revoke_session(&mut self, bearer_token: &str) -> Result<()>;
+    fn revoke_other_sessions(&mut self, bearer_token: &str) -> Result<usize>;
+    fn revoke_user_sessions(&mut self, username: &Username) -> Result<usize>;
+    fn list_user_sessions(&self, bearer_token: &str) -> Result<Vec<SessionSummary>>;
+    fn revoke_user_session(&mut self, username: &Username, session_id: &str) -> Result<()>;
+    fn refresh_session(&mut self, bearer_token: &str) -> Result<SessionRefresh>;
+    fn ensure_second_factor_verified(&self, bearer_token: &str) -> Result<()>;
+    fn start_two_factor_enrollment(&mut self, username: &Username) -> Result<TwoFactorEnrollment>;
+    fn confirm_two_factor_enrollment(&mut self, username: &Username, code: &str) -> Result<Vec<String>>;
+    fn verify_second_factor(&mut self, bearer_token: &str, code: &str) -> Result<()>;
+    fn disable_two_factor(&mut self, username: &Username, code: &str) -> Result<()>;
+    fn start_recovery_registration(&self, username: &Username, client_registration_message: Vec<u8>) -> Result<Vec<u8>>;
+    fn finish_recovery_registration(&self, username: &Username, client_registration_message: Vec<u8>, wrapped_key: Vec<u8>) -> Result<()>;
+    fn start_recovery_login(&mut self, username: &Username, client_ip: Option<&str>, client_login_message: Vec<u8>) -> Result<LoginStart>;
+    fn finish_recovery_login(&mut self, username: &Username, login_id: &str, client_login_message: Vec<u8>, client_label: Option<&str>) -> Result<LoginFinish>;
+    fn is_recovery_session(&self, bearer_token: &str) -> Result<bool>;
+    fn recovery_key(&self, username: &Username) -> Result<Vec<u8>>;
+    fn ensure_fresh_login(&self, bearer_token: &str) -> Result<()>;
+    fn delete_credentials(&mut self, username: &Username) -> Result<Vec<String>>;
 }
\ No newline at end of file
@@ -0,0 +1,7 @@
+use crate::{backup_removal_list::backup_removal_list_error::Result, domain::username::Username};
+
+// Deleted accounts are flagged here so their files get removed from the backups the server cannot reach. A list
+// keeps no username, only what the operator needs to find the files.
+pub trait BackupRemovalList {
+    fn flag(&self, username: &Username) -> Result<()>;
+}
\ No newline at end of file
@@ -1,7 +1,12 @@
-use crate::file_storage::file_storage_error::Result;
+use crate::file_storage::{file_listing::FileListing, file_metadata::FileMetadata, file_storage_error::Result};
 
 pub trait FileStorage {
     fn new(path: String) -> Self; 
     fn retrieve(&self, file_name: &str) -> Result<Vec<u8>>;
     fn save(&self, file_name: &str, content: Vec<u8>) -> Result<()>;
+    fn delete(&self, file_name: &str) -> Result<()>;
+    fn exists(&self, file_name: &str) -> Result<bool>;
+    // A page holds at least one name, a limit of 0 is refused.
+    fn list(&self, prefix: &str, start_after: Option<&str>, limit: usize) -> Result<FileListing>;
+    fn metadata(&self, file_name: &str) -> Result<FileMetadata>;
 }
\ No newline at end of file
@@ -0,0 +1,10 @@
+use crate::session_store::{session::Session, session_store_error::Result};
+
+// Sessions are keyed by the hash of their bearer token, a store never sees the token itself.
+pub trait SessionStore {
+    fn get(&self, token_hash: &str) -> Result<Option<Session>>;
+    fn save(&mut self, token_hash: &str, session: Session) -> Result<()>;
+    fn remove(&mut self, token_hash: &str) -> Result<Option<Session>>;
+    fn list(&self) -> Result<Vec<(String, Session)>>;
+    fn retain(&mut self, keep: &dyn Fn(&str, &Session) -> bool) -> Result<Vec<String>>;
+}
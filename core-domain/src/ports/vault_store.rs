@@ -1,6 +1,8 @@
 use crate::vault_store::vault_store_error::Result;
+use crate::domain::username::Username;
 
 pub trait VaultStore {
-    fn retrieve(&self, username: &str) -> Result<Vec<u8>>;
-    fn save(&self, username: &str, vault: Vec<u8>) -> Result<()>;
+    fn retrieve(&self, username: &Username) -> Result<Vec<u8>>;
+    fn save(&self, username: &Username, vault: Vec<u8>) -> Result<()>;
+    fn delete(&self, username: &Username) -> Result<()>;
 }
\ No newline at end of file
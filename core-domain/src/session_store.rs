@@ -0,0 +1,2 @@
+pub mod session;
+pub mod session_store_error;
\ No newline at end of file
@@ -0,0 +1,37 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub session_key: Vec<u8>,
+    pub session_id: String,
+    pub username: String,
+    pub client_label: Option<String>,
+    pub created_at: u64,
+    pub last_used_at: u64,
+    pub retires_at: Option<u64>,
+    pub cipher_suite: String,
+    pub second_factor_pending: bool,
+    pub recovery: bool,
+}
+
+impl Session {
+    pub fn new(
+        session_key: Vec<u8>,
+        session_id: String,
+        username: String,
+        client_label: Option<String>,
+        cipher_suite: String,
+        created_at: u64,
+    ) -> Self {
+        Self {
+            session_key,
+            session_id,
+            username,
+            client_label,
+            created_at,
+            last_used_at: created_at,
+            retires_at: None,
+            cipher_suite,
+            second_factor_pending: false,
+            recovery: false,
+        }
+    }
+}
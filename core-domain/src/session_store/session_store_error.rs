@@ -0,0 +1,23 @@
+#[derive(Debug)]
+pub enum SessionStoreError {
+    ReadingFile(String),
+    WritingToFile(String),
+    Corrupt(String),
+    Internal(String)
+}
+
+impl std::fmt::Display for SessionStoreError {
+
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self {
+            SessionStoreError::ReadingFile(message) => write!(formatter, "Error reading the session store: {}", message),
+            SessionStoreError::WritingToFile(message) => write!(formatter, "Error writing the session store: {}", message),
+            SessionStoreError::Corrupt(message) => write!(formatter, "Session store is corrupt: {}", message),
+            SessionStoreError::Internal(message) => write!(formatter, "Internal error: {}", message)
+        }
+    }
+}
+
+impl std::error::Error for SessionStoreError {}
+
+pub type Result<T> = std::result::Result<T, SessionStoreError>;
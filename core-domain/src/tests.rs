@@ -1 +1,2 @@
-mod server_domain_tests;
\ No newline at end of file
+mod server_domain_tests;
+mod username_tests;
\ No newline at end of file
@@ -1,171 +1,1080 @@
+use std::{cell::RefCell, rc::Rc};
+
 use crate::{
-    domain::server_domain::{Domain, ServerDomain},
-    ports::{authentication::Authentication, vault_store::VaultStore},
+    authentication::{
+        authentication_error::AuthenticationError, login_finish::LoginFinish, login_start::LoginStart,
+        opaque_parameters::OpaqueParameters, session_refresh::SessionRefresh,
+        session_summary::SessionSummary,
+        signed_request::SignedRequest, two_factor_enrollment::TwoFactorEnrollment,
+    },
+    domain::server_domain_errors::ServerDomainError,
+    domain::{
+        account_deletion::AccountDeletion,
+        password_change::PasswordChange,
+        recovery_setup::RecoverySetup,
+        server_domain::{Domain, ServerDomain},
+        username::Username,
+    },
+    backup_removal_list::backup_removal_list_error::BackupRemovalListError,
+    ports::{authentication::Authentication, backup_removal_list::BackupRemovalList, vault_store::VaultStore},
+    vault_store::vault_store_error::VaultStoreError,
 };
 
+const EXISTING_USERNAME: &str = "existing";
+const PREVIOUS_PASSWORD_FILE: [u8; 1] = [7];
+const INVALID_CONTENT_DIGEST: &str = "sha-512=:invalid:";
+const REPLAYED_NONCE: &str = "replayed-nonce";
+const SKEWED_TIMESTAMP: &str = "4242";
+const SERVER_TIME: u64 = 42;
+const CIPHER_SUITE: &str = "p256-sha256";
+const KSF_PARAMETERS: &str = "m=19456,t=2,p=1";
+const SERVER_IDENTITY: &str = "vault.example.org";
+const RETIRED_BEARER_TOKEN: &str = "retired bearer ...";
+const SECOND_FACTOR_PENDING_BEARER_TOKEN: &str = "second factor pending bearer ...";
+const VALID_TOTP_CODE: &str = "123456";
+const RECOVERY_BEARER_TOKEN: &str = "recovery bearer ...";
+const WRAPPED_KEY: [u8; 3] = [1, 2, 3];
+const STALE_BEARER_TOKEN: &str = "stale bearer ...";
+
+#[test]
+fn should_start_server_registration() {
+    // A-rrange
+
+    let username = "username";
+    let client_message = vec![42];
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.start_server_registration(username, client_message);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), vec![42]);
+}
+
+#[test]
+fn should_finish_server_registration() {
+
+    // A-rrange
+
+    let username = "username";
+    let client_message = vec![42];
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.finish_server_registration(username, client_message);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn should_not_finish_server_registration_over_existing_account() {
+
+    // A-rrange
+
+    let client_message = vec![42];
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.finish_server_registration(EXISTING_USERNAME, client_message);
+
+    // A-ssert
+
+    match result {
+        Err(ServerDomainError::Conflict(_)) => {}
+        _ => panic!("Test result should be Conflict."),
+    }
+}
+
+#[test]
+fn should_not_start_server_registration_with_invalid_username() {
+
+    // A-rrange
+
+    let username = "../username";
+    let client_message = vec![42];
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.start_server_registration(username, client_message);
+
+    // A-ssert
+
+    match result {
+        Err(ServerDomainError::InvalidUsername(_)) => {}
+        _ => panic!("Test result should be InvalidUsername."),
+    }
+}
+
+#[test]
+fn should_start_server_login() {
+
+    // A-rrange
+
+    let username = "username";
+    let client_message = vec![42];
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.start_server_login(username, Some("127.0.0.1"), client_message);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    let login_start = result.unwrap();
+
+    assert_eq!(login_start.login_id, "login-id");
+    assert_eq!(login_start.message, vec![42]);
+    assert_eq!(login_start.parameters, generate_opaque_parameters());
+}
+
+#[test]
+fn should_finish_server_login() {
+
+    // A-rrange
+
+    let username = "username";
+    let client_message = vec![42];
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.finish_server_login(username, "login-id", client_message, Some("laptop"));
+
+    // A-ssert
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn should_get_vault() {
+
+    // A-rrange
+
+    let signed_request = generate_signed_request("GET", "/");
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.get_vault(&signed_request);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), vec![42]);
+}
+
+#[test]
+fn should_save_vault() {
+
+    // A-rrange
+
+    let signed_request = generate_signed_request("GET", "/");
+    let vault = vec![42];
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.save_vault(&signed_request, vault);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn should_not_save_vault_with_invalid_content_digest() {
+
+    // A-rrange
+
+    let mut signed_request = generate_signed_request("POST", "/vault");
+    signed_request.content_digest = INVALID_CONTENT_DIGEST.to_string();
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.save_vault(&signed_request, vec![42]);
+
+    // A-ssert
+
+    match result {
+        Err(ServerDomainError::Forbidden(_)) => {}
+        _ => panic!("Test result should be Forbidden."),
+    }
+}
+
+#[test]
+fn should_not_get_vault_with_replayed_nonce() {
+
+    // A-rrange
+
+    let mut signed_request = generate_signed_request("GET", "/vault");
+    signed_request.nonce = REPLAYED_NONCE.to_string();
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.get_vault(&signed_request);
+
+    // A-ssert
+
+    match result {
+        Err(ServerDomainError::ReplayedRequest(_)) => {}
+        _ => panic!("Test result should be ReplayedRequest."),
+    }
+}
+
+#[test]
+fn should_not_get_vault_with_skewed_timestamp() {
+
+    // A-rrange
+
+    let mut signed_request = generate_signed_request("GET", "/vault");
+    signed_request.timestamp = SKEWED_TIMESTAMP.to_string();
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.get_vault(&signed_request);
+
+    // A-ssert
+
+    match result {
+        Err(ServerDomainError::ClockSkew(server_time)) => assert_eq!(server_time, SERVER_TIME),
+        _ => panic!("Test result should be ClockSkew."),
+    }
+}
+
+#[test]
+fn should_give_server_time() {
+
+    // A-rrange
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.server_time();
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), SERVER_TIME);
+}
+
+#[test]
+fn should_give_opaque_parameters() {
+
+    // A-rrange
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.opaque_parameters();
+
+    // A-ssert
+
+    assert_eq!(result.cipher_suite, CIPHER_SUITE);
+    assert_eq!(result.ksf_parameters, KSF_PARAMETERS);
+    assert_eq!(result.server_identity.as_deref(), Some(SERVER_IDENTITY));
+}
+
+#[test]
+fn should_start_password_change() {
+
+    // A-rrange
+
+    let signed_request = generate_signed_request("POST", "/account/password/start");
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.start_password_change(&signed_request, vec![42]);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), vec![42]);
+}
+
+#[test]
+fn should_finish_password_change() {
+
+    // A-rrange
+
+    let signed_request = generate_signed_request("POST", "/account/password/finish");
+    let password_change = PasswordChange {
+        registration_message: vec![42],
+        vault: vec![43],
+    };
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.finish_password_change(&signed_request, password_change);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), 3);
+}
+
+#[test]
+fn should_rollback_password_change_when_vault_save_fails() {
+
+    // A-rrange
+
+    let signed_request = generate_signed_request("POST", "/account/password/finish");
+    let password_change = PasswordChange {
+        registration_message: vec![42],
+        vault: vec![43],
+    };
+
+    let saved_vaults = Rc::new(RefCell::new(Vec::new()));
+
+    let failing_vault_store = FailingVaultStore {
+        saved_vaults: saved_vaults.clone(),
+    };
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(failing_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.finish_password_change(&signed_request, password_change);
+
+    // A-ssert
+
+    match result {
+        Err(ServerDomainError::Internal(message)) => assert!(!message.contains("restoring")),
+        _ => panic!("Test result should be Internal."),
+    }
+
+    assert_eq!(*saved_vaults.borrow(), vec![vec![42]]);
+}
+
+#[test]
+fn should_purge_expired_sessions() {
+
+    // A-rrange
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.purge_expired_sessions();
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), 1);
+}
+
+#[test]
+fn should_logout() {
+
+    // A-rrange
+
+    let signed_request = generate_signed_request("POST", "/session/logout");
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.logout(&signed_request);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn should_logout_all() {
+
+    // A-rrange
+
+    let signed_request = generate_signed_request("POST", "/session/logout-all");
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.logout_all(&signed_request);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), 2);
+}
+
+#[test]
+fn should_list_sessions() {
+
+    // A-rrange
+
+    let signed_request = generate_signed_request("GET", "/sessions");
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.list_sessions(&signed_request);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap()[0].session_id, "42");
+}
+
+#[test]
+fn should_end_session() {
+
+    // A-rrange
+
+    let signed_request = generate_signed_request("DELETE", "/sessions/42");
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.end_session(&signed_request, "42");
+
+    // A-ssert
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn should_not_end_unknown_session() {
+
+    // A-rrange
+
+    let signed_request = generate_signed_request("DELETE", "/sessions/43");
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.end_session(&signed_request, "43");
+
+    // A-ssert
+
+    match result {
+        Err(ServerDomainError::NotFound(_)) => {}
+        _ => panic!("Test result should be NotFound."),
+    }
+}
+
+#[test]
+fn should_refresh_session() {
+
+    // A-rrange
+
+    let signed_request = generate_signed_request("POST", "/session/refresh");
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.refresh_session(&signed_request);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(
+        result.unwrap(),
+        SessionRefresh {
+            salt: vec![42; 32],
+            previous_token_expires_at: SERVER_TIME + 30,
+        }
+    );
+}
+
+#[test]
+fn should_not_refresh_retired_session() {
+
+    // A-rrange
+
+    let mut signed_request = generate_signed_request("POST", "/session/refresh");
+    signed_request.bearer_token = RETIRED_BEARER_TOKEN.to_string();
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.refresh_session(&signed_request);
+
+    // A-ssert
+
+    match result {
+        Err(ServerDomainError::Forbidden(_)) => {}
+        _ => panic!("Test result should be Forbidden."),
+    }
+}
+
+#[test]
+fn should_not_get_vault_before_second_factor() {
+
+    // A-rrange
+
+    let mut signed_request = generate_signed_request("GET", "/vault");
+    signed_request.bearer_token = SECOND_FACTOR_PENDING_BEARER_TOKEN.to_string();
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.get_vault(&signed_request);
+
+    // A-ssert
+
+    match result {
+        Err(ServerDomainError::SecondFactorRequired(_)) => {}
+        _ => panic!("Test result should be SecondFactorRequired."),
+    }
+}
+
+#[test]
+fn should_verify_second_factor_of_pending_session() {
+
+    // A-rrange
+
+    let mut signed_request = generate_signed_request("POST", "/account/2fa/verify");
+    signed_request.bearer_token = SECOND_FACTOR_PENDING_BEARER_TOKEN.to_string();
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.verify_second_factor(&signed_request, VALID_TOTP_CODE);
+    let logout_result = server_domain.logout(&signed_request);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert!(logout_result.is_ok());
+}
+
 #[test]
-fn should_start_server_registration() {
+fn should_not_enroll_second_factor_before_second_factor() {
+
     // A-rrange
 
-    let username = "username";
-    let client_message = vec![42];
+    let mut signed_request = generate_signed_request("POST", "/account/2fa/enroll");
+    signed_request.bearer_token = SECOND_FACTOR_PENDING_BEARER_TOKEN.to_string();
 
     let mock_vault_store = MockVaultStore;
     let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
 
-    let server_domain = ServerDomain::new(mock_vault_store, mock_authentication);
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
 
     // A-ct
 
-    let result = server_domain.start_server_registration(username, client_message);
+    let result = server_domain.start_two_factor_enrollment(&signed_request);
 
     // A-ssert
 
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap(), vec![42]);
+    match result {
+        Err(ServerDomainError::SecondFactorRequired(_)) => {}
+        _ => panic!("Test result should be SecondFactorRequired."),
+    }
 }
 
 #[test]
-fn should_finish_server_registration() {
+fn should_confirm_two_factor_enrollment() {
 
     // A-rrange
 
-    let username = "username";
-    let client_message = vec![42];
+    let signed_request = generate_signed_request("POST", "/account/2fa/confirm");
 
     let mock_vault_store = MockVaultStore;
     let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
 
-    let server_domain = ServerDomain::new(mock_vault_store, mock_authentication);
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
 
     // A-ct
 
-    let result = server_domain.finish_server_registration(username, client_message);
+    let result = server_domain.confirm_two_factor_enrollment(&signed_request, VALID_TOTP_CODE);
 
     // A-ssert
 
     assert!(result.is_ok());
+    assert_eq!(result.unwrap(), vec![String::from("recovery-code")]);
 }
 
 #[test]
-fn should_start_server_login() {
+fn should_not_disable_two_factor_with_invalid_code() {
 
     // A-rrange
 
-    let username = "username";
-    let client_message = vec![42];
+    let signed_request = generate_signed_request("POST", "/account/2fa/disable");
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.disable_two_factor(&signed_request, "000000");
+
+    // A-ssert
+
+    match result {
+        Err(ServerDomainError::Forbidden(_)) => {}
+        _ => panic!("Test result should be Forbidden."),
+    }
+}
+
+#[test]
+fn should_finish_recovery_setup() {
+
+    // A-rrange
+
+    let signed_request = generate_signed_request("POST", "/account/recovery/finish");
 
     let mock_vault_store = MockVaultStore;
     let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
 
-    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication);
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    let recovery_setup = RecoverySetup {
+        registration_message: vec![42],
+        wrapped_key: WRAPPED_KEY.to_vec(),
+    };
 
     // A-ct
 
-    let result = server_domain.start_server_login(username, client_message);
+    let result = server_domain.finish_recovery_setup(&signed_request, recovery_setup);
 
     // A-ssert
 
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), vec![42]);
 }
 
 #[test]
-fn should_finish_server_login() {
+fn should_only_reset_password_from_recovery_session() {
 
     // A-rrange
 
-    let username = "username";
-    let client_message = vec![42];
+    let mut signed_request = generate_signed_request("POST", "/vault");
+    signed_request.bearer_token = RECOVERY_BEARER_TOKEN.to_string();
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.save_vault(&signed_request, vec![42]);
+    let get_vault_result = server_domain.get_vault(&signed_request);
+    let recovery_key_result = server_domain.get_recovery_key(&signed_request);
+
+    // A-ssert
+
+    match result {
+        Err(ServerDomainError::Forbidden(_)) => {}
+        _ => panic!("Test result should be Forbidden."),
+    }
+
+    assert!(get_vault_result.is_ok());
+    assert_eq!(recovery_key_result.unwrap(), WRAPPED_KEY.to_vec());
+}
+
+#[test]
+fn should_not_get_recovery_key_from_login_session() {
+
+    // A-rrange
+
+    let signed_request = generate_signed_request("GET", "/account/recovery/key");
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.get_recovery_key(&signed_request);
+
+    // A-ssert
+
+    match result {
+        Err(ServerDomainError::Forbidden(_)) => {}
+        _ => panic!("Test result should be Forbidden."),
+    }
+}
+
+#[test]
+fn should_finish_recovery_reset() {
+
+    // A-rrange
+
+    let mut signed_request = generate_signed_request("POST", "/account/recovery/reset/finish");
+    signed_request.bearer_token = RECOVERY_BEARER_TOKEN.to_string();
 
     let mock_vault_store = MockVaultStore;
     let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
 
-    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication);
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    let password_change = PasswordChange {
+        registration_message: vec![42],
+        vault: vec![43],
+    };
 
     // A-ct
 
-    let result = server_domain.finish_server_login(username, client_message);
+    let result = server_domain.finish_recovery_reset(&signed_request, password_change);
 
     // A-ssert
 
     assert!(result.is_ok());
+    assert_eq!(result.unwrap(), 2);
 }
 
 #[test]
-fn should_get_vault() {
+fn should_delete_account() {
 
     // A-rrange
 
-    let bearer_token = "bearer ...";
-    let verb = "GET";
-    let uri = "http://localhost";
-    let timestamp = "42";
-    let signature = "signature";
+    let signed_request = generate_signed_request("DELETE", "/account");
 
     let mock_vault_store = MockVaultStore;
     let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
 
-    let server_domain = ServerDomain::new(mock_vault_store, mock_authentication);
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
 
     // A-ct
 
-    let result = server_domain.get_vault(bearer_token, verb, uri, timestamp, signature);
+    let result = server_domain.delete_account(&signed_request);
 
     // A-ssert
 
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), vec![42]);
+    assert_eq!(
+        result.unwrap(),
+        AccountDeletion {
+            deleted: vec![String::from("password_file"), String::from("vault")],
+            revoked_sessions: 2,
+            backup_removal_error: None,
+        }
+    );
 }
 
 #[test]
-fn should_save_vault() {
+fn should_delete_account_when_flagging_backups_fails() {
 
     // A-rrange
 
-    let bearer_token = "bearer ...";
-    let verb = "GET";
-    let uri = "http://localhost";
-    let timestamp = "42";
-    let signature = "signature";
-    let vault = vec![42];
+    let signed_request = generate_signed_request("DELETE", "/account");
 
     let mock_vault_store = MockVaultStore;
     let mock_authentication = MockAuthentication;
+    let failing_backup_removal_list = FailingBackupRemovalList;
 
-    let server_domain = ServerDomain::new(mock_vault_store, mock_authentication);
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, failing_backup_removal_list);
 
     // A-ct
 
-    let result = server_domain.save_vault(bearer_token, verb, uri, timestamp, signature, vault);
+    let result = server_domain.delete_account(&signed_request);
 
     // A-ssert
 
     assert!(result.is_ok());
+
+    let account_deletion = result.unwrap();
+
+    assert_eq!(account_deletion.deleted, vec![String::from("password_file"), String::from("vault")]);
+    assert!(account_deletion.backup_removal_error.unwrap().contains("disk full"));
+}
+
+#[test]
+fn should_not_delete_account_without_fresh_login() {
+
+    // A-rrange
+
+    let mut signed_request = generate_signed_request("DELETE", "/account");
+    signed_request.bearer_token = STALE_BEARER_TOKEN.to_string();
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.delete_account(&signed_request);
+
+    // A-ssert
+
+    match result {
+        Err(ServerDomainError::FreshLoginRequired(_)) => {}
+        _ => panic!("Test result should be FreshLoginRequired."),
+    }
+}
+
+#[test]
+fn should_not_delete_account_from_recovery_session() {
+
+    // A-rrange
+
+    let mut signed_request = generate_signed_request("DELETE", "/account");
+    signed_request.bearer_token = RECOVERY_BEARER_TOKEN.to_string();
+
+    let mock_vault_store = MockVaultStore;
+    let mock_authentication = MockAuthentication;
+    let mock_backup_removal_list = MockBackupRemovalList;
+
+    let mut server_domain = ServerDomain::new(mock_vault_store, mock_authentication, mock_backup_removal_list);
+
+    // A-ct
+
+    let result = server_domain.delete_account(&signed_request);
+
+    // A-ssert
+
+    match result {
+        Err(ServerDomainError::Forbidden(_)) => {}
+        _ => panic!("Test result should be Forbidden."),
+    }
+}
+
+fn generate_opaque_parameters() -> OpaqueParameters {
+    OpaqueParameters {
+        cipher_suite: CIPHER_SUITE.to_string(),
+        ksf_parameters: KSF_PARAMETERS.to_string(),
+        server_identity: Some(SERVER_IDENTITY.to_string()),
+        context: None,
+    }
+}
+
+fn generate_signed_request(method: &str, path: &str) -> SignedRequest {
+    SignedRequest {
+        bearer_token: String::from("bearer ..."),
+        method: method.to_string(),
+        authority: String::from("localhost"),
+        path: path.to_string(),
+        query: String::new(),
+        content_digest: String::from("sha-512=:digest:"),
+        timestamp: String::from("42"),
+        nonce: String::from("nonce"),
+        signature: String::from("signature"),
+    }
 }
 
 struct MockVaultStore;
 
 impl VaultStore for MockVaultStore {
-    fn retrieve(&self, _: &str) -> crate::vault_store::vault_store_error::Result<Vec<u8>> {
+    fn retrieve(&self, _: &Username) -> crate::vault_store::vault_store_error::Result<Vec<u8>> {
         Ok(vec![42])
     }
 
     fn save(
         &self,
-        _: &str,
+        username: &Username,
         _: Vec<u8>,
     ) -> crate::vault_store::vault_store_error::Result<()> {
+        if username.as_str() == EXISTING_USERNAME {
+            panic!("The vault of an existing account should never be overwritten.");
+        }
+
+        Ok(())
+    }
+
+    fn delete(&self, _: &Username) -> crate::vault_store::vault_store_error::Result<()> {
+        Ok(())
+    }
+}
+
+// Fails to save the new vault, then records the vaults saved by the rollback.
+struct FailingVaultStore {
+    saved_vaults: Rc<RefCell<Vec<Vec<u8>>>>,
+}
+
+impl VaultStore for FailingVaultStore {
+    fn retrieve(&self, _: &Username) -> crate::vault_store::vault_store_error::Result<Vec<u8>> {
+        Ok(vec![42])
+    }
+
+    fn save(
+        &self,
+        _: &Username,
+        vault: Vec<u8>,
+    ) -> crate::vault_store::vault_store_error::Result<()> {
+        if vault == vec![43] {
+            return Err(VaultStoreError::WritingToFile(String::from("disk full")));
+        }
+
+        self.saved_vaults.borrow_mut().push(vault);
+
+        Ok(())
+    }
+
+    fn delete(&self, _: &Username) -> crate::vault_store::vault_store_error::Result<()> {
+        Ok(())
+    }
+}
+
+struct MockBackupRemovalList;
+
+impl BackupRemovalList for MockBackupRemovalList {
+    fn flag(&self, _: &Username) -> crate::backup_removal_list::backup_removal_list_error::Result<()> {
         Ok(())
     }
 }
 
+struct FailingBackupRemovalList;
+
+impl BackupRemovalList for FailingBackupRemovalList {
+    fn flag(&self, _: &Username) -> crate::backup_removal_list::backup_removal_list_error::Result<()> {
+        Err(BackupRemovalListError::WritingToFile(String::from("disk full")))
+    }
+}
+
 struct MockAuthentication;
 
 impl Authentication for MockAuthentication {
+    fn opaque_parameters(&self) -> OpaqueParameters {
+        generate_opaque_parameters()
+    }
+
     fn start_server_registration(
         &self,
-        _: &str,
+        _: &Username,
         _: Vec<u8>,
     ) -> crate::authentication::authentication_error::Result<Vec<u8>> {
         Ok(vec![42])
@@ -173,26 +1082,67 @@ impl Authentication for MockAuthentication {
 
     fn finish_server_registration(
         &self,
-        _: &str,
+        username: &Username,
         _: Vec<u8>,
     ) -> crate::authentication::authentication_error::Result<()> {
+        if username.as_str() == EXISTING_USERNAME {
+            return Err(AuthenticationError::AccountAlreadyExists(username.to_string()));
+        }
+
         Ok(())
     }
 
-    fn start_server_login(
-        &mut self,
-        _: &str,
+    fn start_password_change(
+        &self,
+        _: &Username,
         _: Vec<u8>,
     ) -> crate::authentication::authentication_error::Result<Vec<u8>> {
         Ok(vec![42])
     }
 
+    fn finish_password_change(
+        &self,
+        _: &Username,
+        _: Vec<u8>,
+    ) -> crate::authentication::authentication_error::Result<Vec<u8>> {
+        Ok(PREVIOUS_PASSWORD_FILE.to_vec())
+    }
+
+    fn restore_password_file(
+        &self,
+        username: &Username,
+        password_file: Vec<u8>,
+    ) -> crate::authentication::authentication_error::Result<()> {
+        if password_file != PREVIOUS_PASSWORD_FILE {
+            return Err(AuthenticationError::PasswordFileSave(username.to_string()));
+        }
+
+        Ok(())
+    }
+
+    fn start_server_login(
+        &mut self,
+        _: &Username,
+        _: Option<&str>,
+        _: Vec<u8>,
+    ) -> crate::authentication::authentication_error::Result<LoginStart> {
+        Ok(LoginStart {
+            login_id: String::from("login-id"),
+            message: vec![42],
+            parameters: generate_opaque_parameters(),
+        })
+    }
+
     fn finish_server_login(
         &mut self,
+        _: &Username,
         _: &str,
         _: Vec<u8>,
-    ) -> crate::authentication::authentication_error::Result<()> {
-        Ok(())
+        _: Option<&str>,
+    ) -> crate::authentication::authentication_error::Result<LoginFinish> {
+        Ok(LoginFinish {
+            second_factor_required: false,
+        })
     }
 
     fn verify_bearer_token(&self, _: &str) -> bool {
@@ -201,26 +1151,177 @@ impl Authentication for MockAuthentication {
 
     fn verify_signature(
         &self,
-        _: &str,
-        _: &str,
-        _: &str,
-        _: &str,
-        _: &str,
+        _: &SignedRequest,
     ) -> crate::authentication::authentication_error::Result<bool> {
         Ok(true)
     }
 
+    fn verify_content_digest(&self, content_digest: &str, _: &[u8]) -> bool {
+        content_digest != INVALID_CONTENT_DIGEST
+    }
+
     fn verify_request_timestamp(
         &self,
-        _: &str,
-    ) -> crate::authentication::authentication_error::Result<bool> {
-        Ok(true)
+        timestamp: &str,
+    ) -> crate::authentication::authentication_error::Result<()> {
+        if timestamp == SKEWED_TIMESTAMP {
+            return Err(AuthenticationError::ClockSkew(SERVER_TIME));
+        }
+
+        Ok(())
+    }
+
+    fn current_timestamp(&self) -> crate::authentication::authentication_error::Result<u64> {
+        Ok(SERVER_TIME)
+    }
+
+    fn verify_nonce(
+        &mut self,
+        signed_request: &SignedRequest,
+    ) -> crate::authentication::authentication_error::Result<()> {
+        if signed_request.nonce == REPLAYED_NONCE {
+            return Err(AuthenticationError::ReplayedRequest(signed_request.nonce.clone()));
+        }
+
+        Ok(())
     }
 
     fn get_username_from_session(
         &self,
         _: &str,
-    ) -> crate::authentication::authentication_error::Result<String> {
-        Ok(String::from("username"))
+    ) -> crate::authentication::authentication_error::Result<Username> {
+        Ok(Username::parse("username").unwrap())
+    }
+
+    fn touch_session(&mut self, _: &str) -> crate::authentication::authentication_error::Result<()> {
+        Ok(())
+    }
+
+    fn purge_expired_sessions(&mut self) -> crate::authentication::authentication_error::Result<usize> {
+        Ok(1)
+    }
+
+    fn revoke_session(&mut self, _: &str) -> crate::authentication::authentication_error::Result<()> {
+        Ok(())
+    }
+
+    fn revoke_other_sessions(&mut self, _: &str) -> crate::authentication::authentication_error::Result<usize> {
+        Ok(3)
+    }
+
+    fn revoke_user_sessions(&mut self, _: &Username) -> crate::authentication::authentication_error::Result<usize> {
+        Ok(2)
+    }
+
+    fn list_user_sessions(&self, _: &str) -> crate::authentication::authentication_error::Result<Vec<SessionSummary>> {
+        Ok(vec![SessionSummary {
+            session_id: String::from("42"),
+            created_at: 42,
+            last_used_at: 42,
+            client_label: None,
+            current: true,
+        }])
+    }
+
+    fn revoke_user_session(&mut self, _: &Username, session_id: &str) -> crate::authentication::authentication_error::Result<()> {
+        match session_id {
+            "42" => Ok(()),
+            _ => Err(AuthenticationError::SessionNotFound(session_id.to_string())),
+        }
+    }
+
+    fn refresh_session(&mut self, bearer_token: &str) -> crate::authentication::authentication_error::Result<SessionRefresh> {
+        if bearer_token == RETIRED_BEARER_TOKEN {
+            return Err(AuthenticationError::SessionRetired(bearer_token.to_string()));
+        }
+
+        Ok(SessionRefresh {
+            salt: vec![42; 32],
+            previous_token_expires_at: SERVER_TIME + 30,
+        })
+    }
+
+    fn ensure_second_factor_verified(&self, bearer_token: &str) -> crate::authentication::authentication_error::Result<()> {
+        if bearer_token == SECOND_FACTOR_PENDING_BEARER_TOKEN {
+            return Err(AuthenticationError::SecondFactorRequired(bearer_token.to_string()));
+        }
+
+        Ok(())
+    }
+
+    fn start_two_factor_enrollment(&mut self, username: &Username) -> crate::authentication::authentication_error::Result<TwoFactorEnrollment> {
+        Ok(TwoFactorEnrollment {
+            secret: String::from("SECRET"),
+            otpauth_uri: format!("otpauth://totp/Vault:{}?secret=SECRET&issuer=Vault", username),
+        })
+    }
+
+    fn confirm_two_factor_enrollment(&mut self, _: &Username, code: &str) -> crate::authentication::authentication_error::Result<Vec<String>> {
+        check_mock_code(code)?;
+
+        Ok(vec![String::from("recovery-code")])
+    }
+
+    fn verify_second_factor(&mut self, _: &str, code: &str) -> crate::authentication::authentication_error::Result<()> {
+        check_mock_code(code)
+    }
+
+    fn disable_two_factor(&mut self, _: &Username, code: &str) -> crate::authentication::authentication_error::Result<()> {
+        check_mock_code(code)
+    }
+
+    fn start_recovery_registration(&self, _: &Username, _: Vec<u8>) -> crate::authentication::authentication_error::Result<Vec<u8>> {
+        Ok(vec![42])
+    }
+
+    fn finish_recovery_registration(&self, _: &Username, _: Vec<u8>, _: Vec<u8>) -> crate::authentication::authentication_error::Result<()> {
+        Ok(())
+    }
+
+    fn start_recovery_login(
+        &mut self,
+        username: &Username,
+        client_ip: Option<&str>,
+        client_login_message: Vec<u8>,
+    ) -> crate::authentication::authentication_error::Result<LoginStart> {
+        self.start_server_login(username, client_ip, client_login_message)
+    }
+
+    fn finish_recovery_login(
+        &mut self,
+        username: &Username,
+        login_id: &str,
+        client_login_message: Vec<u8>,
+        client_label: Option<&str>,
+    ) -> crate::authentication::authentication_error::Result<LoginFinish> {
+        self.finish_server_login(username, login_id, client_login_message, client_label)
+    }
+
+    fn is_recovery_session(&self, bearer_token: &str) -> crate::authentication::authentication_error::Result<bool> {
+        Ok(bearer_token == RECOVERY_BEARER_TOKEN)
+    }
+
+    fn recovery_key(&self, _: &Username) -> crate::authentication::authentication_error::Result<Vec<u8>> {
+        Ok(WRAPPED_KEY.to_vec())
+    }
+
+    fn ensure_fresh_login(&self, bearer_token: &str) -> crate::authentication::authentication_error::Result<()> {
+        if bearer_token == STALE_BEARER_TOKEN {
+            return Err(AuthenticationError::FreshLoginRequired(bearer_token.to_string()));
+        }
+
+        Ok(())
+    }
+
+    fn delete_credentials(&mut self, _: &Username) -> crate::authentication::authentication_error::Result<Vec<String>> {
+        Ok(vec![String::from("password_file")])
     }
 }
+
+fn check_mock_code(code: &str) -> crate::authentication::authentication_error::Result<()> {
+    if code != VALID_TOTP_CODE {
+        return Err(AuthenticationError::InvalidSecondFactorCode(code.to_string()));
+    }
+
+    Ok(())
+}
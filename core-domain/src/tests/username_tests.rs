@@ -0,0 +1,136 @@
+use crate::domain::username::{MAX_USERNAME_LENGTH, Username, UsernameError};
+
+#[test]
+fn should_parse_username() {
+    // A-rrange
+
+    let raw_username = "alice.smith-42@example.org";
+
+    // A-ct
+
+    let result = Username::parse(raw_username);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().as_str(), raw_username);
+}
+
+#[test]
+fn should_fold_case_of_username() {
+    // A-rrange
+
+    let lowercase_username = "straße";
+    let uppercase_username = "STRASSE";
+
+    // A-ct
+
+    let lowercase_result = Username::parse(lowercase_username);
+    let uppercase_result = Username::parse(uppercase_username);
+
+    // A-ssert
+
+    assert_eq!(lowercase_result.unwrap(), uppercase_result.unwrap());
+}
+
+#[test]
+fn should_normalize_unicode_form_of_username() {
+    // A-rrange
+
+    let composed_username = "andr\u{e9}";
+    let decomposed_username = "andre\u{301}";
+    let fullwidth_username = "\u{ff41}\u{ff4e}\u{ff44}\u{ff52}\u{e9}";
+
+    // A-ct
+
+    let composed_result = Username::parse(composed_username);
+    let decomposed_result = Username::parse(decomposed_username);
+    let fullwidth_result = Username::parse(fullwidth_username);
+
+    // A-ssert
+
+    let composed_result = composed_result.unwrap();
+
+    assert_eq!(composed_result.as_str(), "andr\u{e9}");
+    assert_eq!(decomposed_result.unwrap(), composed_result);
+    assert_eq!(fullwidth_result.unwrap(), composed_result);
+}
+
+#[test]
+fn should_not_parse_empty_username() {
+    // A-rrange
+
+    let raw_username = "";
+
+    // A-ct
+
+    let result = Username::parse(raw_username);
+
+    // A-ssert
+
+    assert_eq!(result, Err(UsernameError::Empty));
+}
+
+#[test]
+fn should_not_parse_too_long_username() {
+    // A-rrange
+
+    let longest_username = "a".repeat(MAX_USERNAME_LENGTH);
+    let too_long_username = "a".repeat(MAX_USERNAME_LENGTH + 1);
+
+    // A-ct
+
+    let longest_result = Username::parse(&longest_username);
+    let too_long_result = Username::parse(&too_long_username);
+
+    // A-ssert
+
+    assert!(longest_result.is_ok());
+    assert_eq!(too_long_result, Err(UsernameError::TooLong(MAX_USERNAME_LENGTH + 1)));
+}
+
+#[test]
+fn should_not_parse_username_with_invalid_character() {
+    // A-rrange
+
+    let raw_usernames = ["alice/bob", "alice bob", "alice\\bob", "alice\0", "alice\n"];
+
+    // A-ct
+
+    let results: Vec<_> = raw_usernames.iter().map(|raw_username| Username::parse(raw_username)).collect();
+
+    // A-ssert
+
+    assert_eq!(
+        results,
+        vec![
+            Err(UsernameError::InvalidCharacter('/')),
+            Err(UsernameError::InvalidCharacter(' ')),
+            Err(UsernameError::InvalidCharacter('\\')),
+            Err(UsernameError::InvalidCharacter('\0')),
+            Err(UsernameError::InvalidCharacter('\n')),
+        ]
+    );
+}
+
+#[test]
+fn should_not_parse_username_starting_with_punctuation() {
+    // A-rrange
+
+    let raw_usernames = [".", "..", ".hidden", "-alice"];
+
+    // A-ct
+
+    let results: Vec<_> = raw_usernames.iter().map(|raw_username| Username::parse(raw_username)).collect();
+
+    // A-ssert
+
+    assert_eq!(
+        results,
+        vec![
+            Err(UsernameError::InvalidFirstCharacter('.')),
+            Err(UsernameError::InvalidFirstCharacter('.')),
+            Err(UsernameError::InvalidFirstCharacter('.')),
+            Err(UsernameError::InvalidFirstCharacter('-')),
+        ]
+    );
+}
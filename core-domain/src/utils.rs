@@ -8,9 +8,12 @@ pub fn file_storage_error_to_vault_store_error(
 ) -> VaultStoreError {
     match file_storage_error {
         FileStorageError::FileNotFound(error) => VaultStoreError::VaultNotFound(error),
+        FileStorageError::AlreadyExists(error) => VaultStoreError::WritingToFile(error),
         FileStorageError::PermissionDenied(error) => VaultStoreError::PermissionDenied(error),
         FileStorageError::ReadingFile(error) => VaultStoreError::ReadingFile(error),
         FileStorageError::WritingToFile(error) => VaultStoreError::WritingToFile(error),
+        FileStorageError::InvalidFileName(error) => VaultStoreError::Internal(error),
+        FileStorageError::InvalidPageLimit(error) => VaultStoreError::Internal(error),
         FileStorageError::Internal(error) => VaultStoreError::Internal(error),
     }
 }
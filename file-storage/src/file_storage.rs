@@ -1,27 +1,233 @@
 use std::{
-    fs::{File, OpenOptions},
+    collections::BTreeSet,
+    fs::{self, File, OpenOptions},
     io::{BufReader, Error, ErrorKind, Read, Write},
     path::{Path, PathBuf},
+    ops::Bound,
+    process,
+    sync::{
+        Mutex, MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::UNIX_EPOCH,
 };
 
 use core_domain::{
-    file_storage::file_storage_error::{FileStorageError, Result},
+    file_storage::{
+        file_listing::FileListing,
+        file_metadata::FileMetadata,
+        file_storage_error::{FileStorageError, Result},
+    },
     ports::file_storage::FileStorage,
 };
+use sha2::{Digest, Sha256};
 
 const INVALID_UTF8_PATH: &'static str = "Invalid UTF-8 file path.";
+const EMPTY_FILE_NAME: &str = "the file name is empty";
+const FILE_NAME_TOO_LONG: &str = "the file name is too long";
+const OUTSIDE_STORAGE_DIRECTORY: &str = "the file resolves outside the storage directory";
+const KEY_FILE_EXTENSION: &str = "key";
+const TEMPORARY_FILE_EXTENSION: &str = "tmp";
+const STORED_FILE_NAME_LENGTH: usize = 64;
+const ZERO_PAGE_LIMIT: &str = "a page holds at least one file name";
+const POISONED_FILE_NAMES: &str = "the file names lock is poisoned";
+
+pub const MAX_FILE_NAME_LENGTH: usize = 1024;
+
+static TEMPORARY_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
 
 pub struct StandardFileStorage {
     path: String,
+    // The names of the directory in order, read from the `.key` files by the first `list` and then kept up to date by
+    // the saves and deletes of this storage, so a page does not read every name again. Nothing else is expected to
+    // write to the directory meanwhile.
+    file_names: Mutex<Option<BTreeSet<String>>>,
+}
+
+// Files are saved under the SHA-256 of their name, so no name can lead out of the storage directory or hit the
+// file system limits. The name itself is kept next to the file, in `<hash>.key`, for `list`.
+pub fn stored_file_name(file_name: &str) -> String {
+    hex::encode(Sha256::digest(file_name.as_bytes()))
+}
+
+impl StandardFileStorage {
+    // Older versions saved the files under their name, gives the names of the files still saved that way.
+    pub fn legacy_file_names(&self) -> Result<Vec<String>> {
+        let directory = Path::new(&self.path);
+
+        let entries = match fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(file_error_to_file_storage_error(directory.to_path_buf(), error)),
+        };
+
+        let mut file_names = Vec::new();
+
+        for entry in entries {
+            let entry = entry.map_err(|error| FileStorageError::ReadingFile(error.to_string()))?;
+
+            let is_file = entry
+                .file_type()
+                .map_err(|error| FileStorageError::ReadingFile(error.to_string()))?
+                .is_file();
+
+            if let (true, Some(file_name)) = (is_file, entry.file_name().to_str())
+                && is_legacy_file_name(file_name)
+            {
+                file_names.push(file_name.to_string());
+            }
+        }
+
+        file_names.sort();
+
+        Ok(file_names)
+    }
+
+    // Moves the given legacy files to their hashed name and gives how many moved. The storage does not know which
+    // names are valid, the caller checks them first. Nothing moves when a file is already saved under a hashed name.
+    pub fn migrate_legacy_files(&self, file_names: &[String]) -> Result<usize> {
+        let mut legacy_files = Vec::new();
+
+        for file_name in file_names {
+            let file_path = self.file_path(file_name)?;
+
+            // The rename would replace the file saved since under the same name.
+            if file_path.exists() {
+                return Err(FileStorageError::AlreadyExists(file_name.to_string()));
+            }
+
+            legacy_files.push((Path::new(&self.path).join(file_name), file_path, file_name));
+        }
+
+        for (legacy_file_path, file_path, file_name) in &legacy_files {
+            write_file(&key_file_path(file_path), file_name.as_bytes())?;
+
+            fs::rename(legacy_file_path, file_path)
+                .map_err(|error| file_error_to_file_storage_error(legacy_file_path.clone(), error))?;
+        }
+
+        *self.file_names()? = None;
+
+        Ok(file_names.len())
+    }
+
+    // A crash between the write and the rename of a save leaves its temporary file behind, gives how many were removed.
+    pub fn remove_temporary_files(&self) -> Result<usize> {
+        let directory = Path::new(&self.path);
+
+        let entries = match fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(error) => return Err(file_error_to_file_storage_error(directory.to_path_buf(), error)),
+        };
+
+        let mut removed_files = 0;
+
+        for entry in entries {
+            let entry = entry.map_err(|error| FileStorageError::ReadingFile(error.to_string()))?;
+
+            if !entry.file_name().to_str().is_some_and(is_temporary_file_name) {
+                continue;
+            }
+
+            fs::remove_file(entry.path()).map_err(|error| file_error_to_file_storage_error(entry.path(), error))?;
+
+            removed_files += 1;
+        }
+
+        Ok(removed_files)
+    }
+
+    // Every name of the directory, read from the `.key` files.
+    fn read_file_names(&self) -> Result<BTreeSet<String>> {
+        let directory = Path::new(&self.path);
+
+        let entries = match fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(BTreeSet::new()),
+            Err(error) => return Err(file_error_to_file_storage_error(directory.to_path_buf(), error)),
+        };
+
+        let mut file_names = BTreeSet::new();
+
+        for entry in entries {
+            let entry = entry.map_err(|error| FileStorageError::ReadingFile(error.to_string()))?;
+
+            let is_file = entry
+                .file_type()
+                .map_err(|error| FileStorageError::ReadingFile(error.to_string()))?
+                .is_file();
+
+            if !is_file || !entry.file_name().to_str().is_some_and(is_stored_file_name) {
+                continue;
+            }
+
+            let key_file_path = key_file_path(&entry.path());
+
+            let file_name = match fs::read_to_string(&key_file_path) {
+                Ok(file_name) => file_name,
+                Err(error) => return Err(file_error_to_file_storage_error(key_file_path, error)),
+            };
+
+            file_names.insert(file_name);
+        }
+
+        Ok(file_names)
+    }
+
+    fn file_names(&self) -> Result<MutexGuard<'_, Option<BTreeSet<String>>>> {
+        self.file_names
+            .lock()
+            .map_err(|_| FileStorageError::Internal(POISONED_FILE_NAMES.to_string()))
+    }
+
+    fn file_path(&self, file_name: &str) -> Result<PathBuf> {
+        if file_name.is_empty() {
+            return Err(FileStorageError::InvalidFileName(EMPTY_FILE_NAME.to_string()));
+        }
+
+        if file_name.len() > MAX_FILE_NAME_LENGTH {
+            return Err(FileStorageError::InvalidFileName(FILE_NAME_TOO_LONG.to_string()));
+        }
+
+        let file_path = Path::new(&self.path).join(stored_file_name(file_name));
+
+        self.ensure_inside_directory(&file_path)?;
+        self.ensure_inside_directory(&key_file_path(&file_path))?;
+
+        Ok(file_path)
+    }
+
+    // The hashed names cannot escape, only a symlink planted in the directory can lead out of it.
+    fn ensure_inside_directory(&self, file_path: &Path) -> Result<()> {
+        let Ok(metadata) = fs::symlink_metadata(file_path) else {
+            return Ok(());
+        };
+
+        if !metadata.file_type().is_symlink() {
+            return Ok(());
+        }
+
+        let directory = fs::canonicalize(&self.path)
+            .map_err(|error| file_error_to_file_storage_error(PathBuf::from(&self.path), error))?;
+
+        match fs::canonicalize(file_path) {
+            Ok(target) if target.starts_with(&directory) => Ok(()),
+            _ => Err(FileStorageError::InvalidFileName(OUTSIDE_STORAGE_DIRECTORY.to_string())),
+        }
+    }
 }
 
 impl FileStorage for StandardFileStorage {
     fn new(path: String) -> Self {
-        Self { path }
+        Self {
+            path,
+            file_names: Mutex::new(None),
+        }
     }
 
     fn retrieve(&self, file_name: &str) -> Result<Vec<u8>> {
-        let file_path = Path::new(&self.path).join(file_name);
+        let file_path = self.file_path(file_name)?;
 
         let file = File::open(&file_path)
             .map_err(|error| file_error_to_file_storage_error(file_path, error))?;
@@ -36,20 +242,188 @@ impl FileStorage for StandardFileStorage {
         Ok(buffer)
     }
 
+    // The name goes first, a file is never on disk without it.
     fn save(&self, file_name: &str, vault: Vec<u8>) -> Result<()> {
-        let file_path = Path::new(&self.path).join(file_name);
+        let file_path = self.file_path(file_name)?;
 
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(&file_path)
-            .map_err(|error| file_error_to_file_storage_error(file_path, error))?;
+        write_file(&key_file_path(&file_path), file_name.as_bytes())?;
+        write_file(&file_path, &vault)?;
 
-        file.write_all(&vault)
-            .map_err(|error| FileStorageError::WritingToFile(error.to_string()))?;
+        if let Some(file_names) = self.file_names()?.as_mut() {
+            file_names.insert(file_name.to_string());
+        }
 
         Ok(())
     }
+
+    fn delete(&self, file_name: &str) -> Result<()> {
+        let file_path = self.file_path(file_name)?;
+
+        fs::remove_file(&file_path).map_err(|error| file_error_to_file_storage_error(file_path.clone(), error))?;
+
+        if let Some(file_names) = self.file_names()?.as_mut() {
+            file_names.remove(file_name);
+        }
+
+        let key_file_path = key_file_path(&file_path);
+
+        match fs::remove_file(&key_file_path) {
+            Err(error) if error.kind() != ErrorKind::NotFound => Err(file_error_to_file_storage_error(key_file_path, error)),
+            _ => Ok(()),
+        }
+    }
+
+    fn exists(&self, file_name: &str) -> Result<bool> {
+        let file_path = self.file_path(file_name)?;
+
+        match fs::metadata(&file_path) {
+            Ok(metadata) => Ok(metadata.is_file()),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(file_error_to_file_storage_error(file_path, error)),
+        }
+    }
+
+    // The names with a prefix follow each other in order, a page is a range of the sorted names.
+    fn list(&self, prefix: &str, start_after: Option<&str>, limit: usize) -> Result<FileListing> {
+        if limit == 0 {
+            return Err(FileStorageError::InvalidPageLimit(ZERO_PAGE_LIMIT.to_string()));
+        }
+
+        let mut file_names = self.file_names()?;
+
+        if file_names.is_none() {
+            *file_names = Some(self.read_file_names()?);
+        }
+
+        let file_names = file_names.get_or_insert_default();
+
+        let start = match start_after {
+            Some(start_after) if start_after >= prefix => Bound::Excluded(start_after),
+            _ => Bound::Included(prefix),
+        };
+
+        let mut page: Vec<String> = file_names
+            .range::<str, _>((start, Bound::Unbounded))
+            .take_while(|file_name| file_name.starts_with(prefix))
+            .take(limit.saturating_add(1))
+            .cloned()
+            .collect();
+
+        let next_start_after = if page.len() > limit {
+            page.truncate(limit);
+            page.last().cloned()
+        } else {
+            None
+        };
+
+        Ok(FileListing {
+            file_names: page,
+            next_start_after,
+        })
+    }
+
+    fn metadata(&self, file_name: &str) -> Result<FileMetadata> {
+        let file_path = self.file_path(file_name)?;
+
+        let metadata = fs::metadata(&file_path).map_err(|error| file_error_to_file_storage_error(file_path, error))?;
+
+        let modified_at = metadata
+            .modified()
+            .map_err(|error| FileStorageError::ReadingFile(error.to_string()))?
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        Ok(FileMetadata {
+            size: metadata.len(),
+            modified_at,
+        })
+    }
+}
+
+fn write_file(file_path: &Path, content: &[u8]) -> Result<()> {
+    write_file_atomically(file_path, content, |file, content| file.write_all(content))
+}
+
+// The content goes to a temporary file of the same directory, synced, then renamed over the target. A crash leaves
+// either the previous file or the new one, never a mix of both.
+pub(crate) fn write_file_atomically(
+    file_path: &Path,
+    content: &[u8],
+    write_content: impl Fn(&mut File, &[u8]) -> std::io::Result<()>,
+) -> Result<()> {
+    let temporary_file_path = temporary_file_path(file_path);
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temporary_file_path)
+        .map_err(|error| file_error_to_file_storage_error(temporary_file_path.clone(), error))?;
+
+    let written = write_content(&mut file, content)
+        .and_then(|_| file.sync_all())
+        .and_then(|_| fs::rename(&temporary_file_path, file_path));
+
+    if let Err(error) = written {
+        let _ = fs::remove_file(&temporary_file_path);
+
+        return Err(FileStorageError::WritingToFile(error.to_string()));
+    }
+
+    sync_directory(file_path)
+}
+
+// The rename only survives a power loss once the directory itself is synced.
+#[cfg(unix)]
+fn sync_directory(file_path: &Path) -> Result<()> {
+    let Some(directory) = file_path.parent() else {
+        return Ok(());
+    };
+
+    File::open(directory)
+        .and_then(|directory| directory.sync_all())
+        .map_err(|error| FileStorageError::WritingToFile(error.to_string()))
+}
+
+// Directories cannot be opened on Windows, NTFS journals the rename on its own.
+#[cfg(not(unix))]
+fn sync_directory(_: &Path) -> Result<()> {
+    Ok(())
+}
+
+// Hidden, so the migration and `list` never take it for a stored file.
+fn temporary_file_path(file_path: &Path) -> PathBuf {
+    let file_name = file_path.file_name().and_then(|file_name| file_name.to_str()).unwrap_or_default();
+
+    file_path.with_file_name(format!(
+        ".{}.{}.{}.{}",
+        file_name,
+        process::id(),
+        TEMPORARY_FILE_COUNTER.fetch_add(1, Ordering::Relaxed),
+        TEMPORARY_FILE_EXTENSION
+    ))
+}
+
+fn key_file_path(file_path: &Path) -> PathBuf {
+    file_path.with_extension(KEY_FILE_EXTENSION)
+}
+
+fn is_stored_file_name(file_name: &str) -> bool {
+    file_name.len() == STORED_FILE_NAME_LENGTH
+        && file_name.chars().all(|character| matches!(character, '0'..='9' | 'a'..='f'))
+}
+
+fn is_temporary_file_name(file_name: &str) -> bool {
+    file_name.starts_with('.') && file_name.ends_with(&format!(".{}", TEMPORARY_FILE_EXTENSION))
+}
+
+// Hidden files are left alone, they are not ones this storage saved.
+fn is_legacy_file_name(file_name: &str) -> bool {
+    let is_key_file = file_name
+        .strip_suffix(&format!(".{}", KEY_FILE_EXTENSION))
+        .is_some_and(is_stored_file_name);
+
+    !file_name.starts_with('.') && !is_stored_file_name(file_name) && !is_key_file
 }
 
 fn file_error_to_file_storage_error(file_path: PathBuf, error: Error) -> FileStorageError {
@@ -59,6 +433,7 @@ fn file_error_to_file_storage_error(file_path: PathBuf, error: Error) -> FileSto
 
     return match error.kind() {
         ErrorKind::NotFound => FileStorageError::FileNotFound(file_path.to_string()),
+        ErrorKind::AlreadyExists => FileStorageError::AlreadyExists(file_path.to_string()),
         ErrorKind::PermissionDenied => FileStorageError::PermissionDenied(file_path.to_string()),
         _ => FileStorageError::Internal(error.to_string()),
     };
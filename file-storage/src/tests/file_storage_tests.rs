@@ -1,6 +1,12 @@
-use tempfile::NamedTempFile;
+use std::{
+    fs,
+    io::{Error, ErrorKind, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tempfile::TempDir;
 
-use crate::file_storage::StandardFileStorage;
+use crate::file_storage::{MAX_FILE_NAME_LENGTH, StandardFileStorage, stored_file_name, write_file_atomically};
 
 use core_domain::{
     file_storage::file_storage_error::FileStorageError,
@@ -11,15 +17,16 @@ use core_domain::{
 fn should_retrieve_file() {
     // A-rrange
 
-    let file = NamedTempFile::new().unwrap();
-    let file_name = file.path().file_name().unwrap().to_str().unwrap();
-    let path = file.path().parent().unwrap().to_str().unwrap().to_string();
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
 
     let standard_file_storage = StandardFileStorage::new(path);
 
+    standard_file_storage.save("alice", vec![]).unwrap();
+
     // A-ct
 
-    let result = standard_file_storage.retrieve(file_name);
+    let result = standard_file_storage.retrieve("alice");
 
     // A-ssert
     assert!(result.is_ok());
@@ -50,8 +57,8 @@ fn should_save_file() {
 
     // A-rrange
 
-    let file = NamedTempFile::new().unwrap();
-    let path = file.path().parent().unwrap().to_str().unwrap().to_string();
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
 
     let standard_file_storage = StandardFileStorage::new(path);
 
@@ -61,4 +68,554 @@ fn should_save_file() {
 
     // A-ssert
     assert!(result.is_ok());
+    assert_eq!(fs::read(directory.path().join(stored_file_name("test"))).unwrap(), vec![42]);
+}
+
+#[test]
+fn should_replace_longer_content_when_saving() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    standard_file_storage.save("test", vec![1, 2, 3]).unwrap();
+
+    // A-ct
+
+    let result = standard_file_storage.save("test", vec![]);
+
+    // A-ssert
+    assert!(result.is_ok());
+    assert!(standard_file_storage.retrieve("test").unwrap().is_empty());
+}
+
+#[test]
+fn should_list_files() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    standard_file_storage.save("bob", vec![42]).unwrap();
+    standard_file_storage.save("alice", vec![42]).unwrap();
+
+    // A-ct
+
+    let result = standard_file_storage.list("", None, 100);
+
+    // A-ssert
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap().file_names, vec!["alice".to_string(), "bob".to_string()]);
+}
+
+#[test]
+fn should_list_nothing_when_directory_does_not_exist() {
+    // A-rrange
+
+    let standard_file_storage = StandardFileStorage::new("/wrong_path/".to_string());
+
+    // A-ct
+
+    let result = standard_file_storage.list("", None, 100);
+
+    // A-ssert
+    assert!(result.is_ok());
+    assert!(result.unwrap().file_names.is_empty());
+}
+
+#[test]
+fn should_delete_file() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    standard_file_storage.save("alice", vec![42]).unwrap();
+
+    // A-ct
+
+    let result = standard_file_storage.delete("alice");
+
+    // A-ssert
+    assert!(result.is_ok());
+    assert_eq!(fs::read_dir(directory.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn should_not_delete_file_not_found() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    // A-ct
+
+    let result = standard_file_storage.delete("doest_not_exist");
+
+    // A-ssert
+
+    match result {
+        Err(FileStorageError::FileNotFound(_)) => {}
+        _ => panic!("Test result should be FileNotFound."),
+    }
+}
+
+#[test]
+fn should_keep_hostile_file_names_inside_directory() {
+    // A-rrange
+
+    let parent_directory = TempDir::new().unwrap();
+    let directory = parent_directory.path().join("storage");
+    fs::create_dir(&directory).unwrap();
+
+    let standard_file_storage = StandardFileStorage::new(directory.to_str().unwrap().to_string());
+
+    let hostile_file_names = [
+        "../escaped",
+        "../../etc/cron.d/x",
+        "/tmp/absolute",
+        "..",
+        ".",
+        "sub/directory",
+        "..\\windows",
+        "C:\\Windows\\win.ini",
+        "nul\0byte",
+    ];
+
+    // A-ct
+
+    let results: Vec<_> = hostile_file_names
+        .iter()
+        .map(|file_name| standard_file_storage.save(file_name, file_name.as_bytes().to_vec()))
+        .collect();
+
+    // A-ssert
+
+    assert!(results.iter().all(|result| result.is_ok()));
+
+    for file_name in hostile_file_names {
+        assert_eq!(standard_file_storage.retrieve(file_name).unwrap(), file_name.as_bytes());
+    }
+
+    let mut sorted_file_names: Vec<String> = hostile_file_names.iter().map(|file_name| file_name.to_string()).collect();
+    sorted_file_names.sort();
+
+    assert_eq!(standard_file_storage.list("", None, 100).unwrap().file_names, sorted_file_names);
+    assert_eq!(fs::read_dir(parent_directory.path()).unwrap().count(), 1);
+    assert_eq!(fs::read_dir(&directory).unwrap().count(), 2 * hostile_file_names.len());
+}
+
+#[test]
+fn should_store_long_file_name() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    let long_file_name = "a".repeat(MAX_FILE_NAME_LENGTH);
+
+    // A-ct
+
+    let result = standard_file_storage.save(&long_file_name, vec![42]);
+
+    // A-ssert
+    assert!(result.is_ok());
+    assert_eq!(standard_file_storage.retrieve(&long_file_name).unwrap(), vec![42]);
+    assert_eq!(standard_file_storage.list("", None, 100).unwrap().file_names, vec![long_file_name]);
+}
+
+#[test]
+fn should_not_save_too_long_or_empty_file_name() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    let too_long_file_name = "a".repeat(MAX_FILE_NAME_LENGTH + 1);
+
+    // A-ct
+
+    let too_long_result = standard_file_storage.save(&too_long_file_name, vec![42]);
+    let empty_result = standard_file_storage.save("", vec![42]);
+
+    // A-ssert
+
+    match (too_long_result, empty_result) {
+        (Err(FileStorageError::InvalidFileName(_)), Err(FileStorageError::InvalidFileName(_))) => {}
+        _ => panic!("Test results should be InvalidFileName."),
+    }
+
+    assert_eq!(fs::read_dir(directory.path()).unwrap().count(), 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn should_not_follow_symlink_outside_directory() {
+    // A-rrange
+
+    let outside_directory = TempDir::new().unwrap();
+    let outside_file = outside_directory.path().join("secret");
+    fs::write(&outside_file, b"secret").unwrap();
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    std::os::unix::fs::symlink(&outside_file, directory.path().join(stored_file_name("alice"))).unwrap();
+    std::os::unix::fs::symlink(
+        outside_directory.path().join("missing"),
+        directory.path().join(stored_file_name("bob")),
+    )
+    .unwrap();
+
+    // A-ct
+
+    let retrieve_result = standard_file_storage.retrieve("alice");
+    let save_result = standard_file_storage.save("alice", b"overwritten".to_vec());
+    let dangling_save_result = standard_file_storage.save("bob", b"created".to_vec());
+
+    // A-ssert
+
+    match (retrieve_result, save_result, dangling_save_result) {
+        (
+            Err(FileStorageError::InvalidFileName(_)),
+            Err(FileStorageError::InvalidFileName(_)),
+            Err(FileStorageError::InvalidFileName(_)),
+        ) => {}
+        _ => panic!("Test results should be InvalidFileName."),
+    }
+
+    assert_eq!(fs::read(&outside_file).unwrap(), b"secret");
+    assert!(!outside_directory.path().join("missing").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn should_follow_symlink_inside_directory() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    let inside_file = directory.path().join("shared");
+    fs::write(&inside_file, b"shared").unwrap();
+
+    std::os::unix::fs::symlink(&inside_file, directory.path().join(stored_file_name("alice"))).unwrap();
+
+    // A-ct
+
+    let result = standard_file_storage.retrieve("alice");
+
+    // A-ssert
+    assert_eq!(result.unwrap(), b"shared");
+}
+
+#[test]
+fn should_migrate_legacy_files() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    fs::write(directory.path().join("alice"), b"alice vault").unwrap();
+    fs::write(directory.path().join("alice.recovery"), b"alice recovery").unwrap();
+    fs::write(directory.path().join(".hidden"), b"hidden").unwrap();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    standard_file_storage.save("bob", b"bob vault".to_vec()).unwrap();
+
+    // A-ct
+
+    let legacy_file_names = standard_file_storage.legacy_file_names().unwrap();
+
+    let result = standard_file_storage.migrate_legacy_files(&legacy_file_names);
+
+    // A-ssert
+
+    assert_eq!(legacy_file_names, vec!["alice".to_string(), "alice.recovery".to_string()]);
+    assert_eq!(result.unwrap(), 2);
+    assert!(standard_file_storage.legacy_file_names().unwrap().is_empty());
+    assert_eq!(standard_file_storage.retrieve("alice").unwrap(), b"alice vault");
+    assert_eq!(standard_file_storage.retrieve("alice.recovery").unwrap(), b"alice recovery");
+    assert_eq!(
+        standard_file_storage.list("", None, 100).unwrap().file_names,
+        vec!["alice".to_string(), "alice.recovery".to_string(), "bob".to_string()]
+    );
+    assert!(!directory.path().join("alice").exists());
+    assert!(directory.path().join(".hidden").exists());
+}
+
+#[test]
+fn should_not_migrate_legacy_file_over_saved_one() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    standard_file_storage.save("alice", b"current vault".to_vec()).unwrap();
+
+    fs::write(directory.path().join("alice"), b"legacy vault").unwrap();
+
+    // A-ct
+
+    let result = standard_file_storage.migrate_legacy_files(&["alice".to_string()]);
+
+    // A-ssert
+
+    match result {
+        Err(FileStorageError::AlreadyExists(_)) => {}
+        _ => panic!("Test result should be AlreadyExists."),
+    }
+
+    assert_eq!(standard_file_storage.retrieve("alice").unwrap(), b"current vault");
+    assert!(directory.path().join("alice").exists());
+}
+
+#[test]
+fn should_keep_previous_file_when_write_is_interrupted() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    standard_file_storage.save("alice", b"previous vault".to_vec()).unwrap();
+
+    let file_path = directory.path().join(stored_file_name("alice"));
+
+    // A-ct
+
+    let result = write_file_atomically(&file_path, b"new vault, much longer than the previous one", |file, content| {
+        file.write_all(&content[..content.len() / 2])?;
+
+        Err(Error::new(ErrorKind::Interrupted, "simulated crash"))
+    });
+
+    // A-ssert
+
+    match result {
+        Err(FileStorageError::WritingToFile(_)) => {}
+        _ => panic!("Test result should be WritingToFile."),
+    }
+
+    assert_eq!(standard_file_storage.retrieve("alice").unwrap(), b"previous vault");
+    assert_eq!(fs::read_dir(directory.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn should_ignore_and_remove_temporary_file_of_crashed_save() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    standard_file_storage.save("alice", b"previous vault".to_vec()).unwrap();
+
+    // What a save leaves behind when the process dies before its rename.
+    fs::write(
+        directory.path().join(format!(".{}.4242.0.tmp", stored_file_name("alice"))),
+        b"new va",
+    )
+    .unwrap();
+
+    // A-ct
+
+    let retrieve_result = standard_file_storage.retrieve("alice");
+    let list_result = standard_file_storage.list("", None, 100);
+    let legacy_file_names_result = standard_file_storage.legacy_file_names();
+    let remove_result = standard_file_storage.remove_temporary_files();
+
+    // A-ssert
+
+    assert_eq!(retrieve_result.unwrap(), b"previous vault");
+    assert_eq!(list_result.unwrap().file_names, vec!["alice".to_string()]);
+    assert!(legacy_file_names_result.unwrap().is_empty());
+    assert_eq!(remove_result.unwrap(), 1);
+    assert_eq!(fs::read_dir(directory.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn should_list_files_with_prefix_by_page() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    for file_name in ["alice", "alice.recovery", "albert", "bob", "carol"] {
+        standard_file_storage.save(file_name, vec![42]).unwrap();
+    }
+
+    // A-ct
+
+    let first_page = standard_file_storage.list("al", None, 2).unwrap();
+    let second_page = standard_file_storage
+        .list("al", first_page.next_start_after.as_deref(), 2)
+        .unwrap();
+    let whole_listing = standard_file_storage.list("", None, 5).unwrap();
+
+    // A-ssert
+
+    assert_eq!(first_page.file_names, vec!["albert".to_string(), "alice".to_string()]);
+    assert_eq!(first_page.next_start_after, Some("alice".to_string()));
+    assert_eq!(second_page.file_names, vec!["alice.recovery".to_string()]);
+    assert_eq!(second_page.next_start_after, None);
+    assert_eq!(whole_listing.file_names.len(), 5);
+    assert_eq!(whole_listing.next_start_after, None);
+}
+
+#[test]
+fn should_list_files_with_start_after_outside_prefix() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    for file_name in ["alice", "bob", "bobby", "carol"] {
+        standard_file_storage.save(file_name, vec![42]).unwrap();
+    }
+
+    // A-ct
+
+    let before_prefix = standard_file_storage.list("bob", Some("alice"), 100).unwrap();
+    let after_prefix = standard_file_storage.list("bob", Some("carol"), 100).unwrap();
+
+    // A-ssert
+
+    assert_eq!(before_prefix.file_names, vec!["bob".to_string(), "bobby".to_string()]);
+    assert!(after_prefix.file_names.is_empty());
+}
+
+#[test]
+fn should_list_files_saved_and_deleted_after_first_listing() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    standard_file_storage.save("alice", vec![42]).unwrap();
+    standard_file_storage.save("bob", vec![42]).unwrap();
+
+    let first_listing = standard_file_storage.list("", None, 100).unwrap();
+
+    // A-ct
+
+    standard_file_storage.save("carol", vec![42]).unwrap();
+    standard_file_storage.save("alice", vec![43]).unwrap();
+    standard_file_storage.delete("bob").unwrap();
+
+    let second_listing = standard_file_storage.list("", None, 100).unwrap();
+
+    // A-ssert
+
+    assert_eq!(first_listing.file_names, vec!["alice".to_string(), "bob".to_string()]);
+    assert_eq!(second_listing.file_names, vec!["alice".to_string(), "carol".to_string()]);
+}
+
+#[test]
+fn should_not_list_files_with_zero_limit() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    standard_file_storage.save("alice", vec![42]).unwrap();
+
+    // A-ct
+
+    let result = standard_file_storage.list("", None, 0);
+
+    // A-ssert
+
+    match result {
+        Err(FileStorageError::InvalidPageLimit(_)) => {}
+        _ => panic!("Test result should be InvalidPageLimit."),
+    }
+}
+
+#[test]
+fn should_tell_whether_file_exists() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    standard_file_storage.save("alice", vec![42]).unwrap();
+
+    // A-ct
+
+    let existing_result = standard_file_storage.exists("alice");
+    let missing_result = standard_file_storage.exists("bob");
+    let invalid_result = standard_file_storage.exists("");
+
+    // A-ssert
+
+    assert!(existing_result.unwrap());
+    assert!(!missing_result.unwrap());
+
+    match invalid_result {
+        Err(FileStorageError::InvalidFileName(_)) => {}
+        _ => panic!("Test result should be InvalidFileName."),
+    }
+}
+
+#[test]
+fn should_give_file_metadata() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().to_str().unwrap().to_string();
+
+    let standard_file_storage = StandardFileStorage::new(path);
+
+    let saved_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+    standard_file_storage.save("alice", vec![42; 100]).unwrap();
+
+    // A-ct
+
+    let result = standard_file_storage.metadata("alice");
+    let missing_result = standard_file_storage.metadata("bob");
+
+    // A-ssert
+
+    let file_metadata = result.unwrap();
+
+    assert_eq!(file_metadata.size, 100);
+    assert!(file_metadata.modified_at.abs_diff(saved_at) <= 1);
+
+    match missing_result {
+        Err(FileStorageError::FileNotFound(_)) => {}
+        _ => panic!("Test result should be FileNotFound."),
+    }
 }
@@ -0,0 +1,128 @@
+use std::collections::BTreeSet;
+
+use authentication::{
+    cipher_suite::CipherSuiteId,
+    legacy_accounts::{canonicalise_files, canonicalise_password_files},
+    password_file::count_password_files_by_key_version,
+    server_key::load_existing_server_keyring,
+};
+use core_domain::{domain::username::Username, ports::file_storage::FileStorage};
+use file_storage::file_storage::StandardFileStorage;
+
+use crate::config::AppConfig;
+
+const ROTATE_SERVER_KEY: &str = "rotate-server-key";
+const SERVER_KEY_STATUS: &str = "server-key-status";
+const MIGRATE_LEGACY_FILES: &str = "migrate-legacy-files";
+
+pub fn run_admin_command(command: &str, app_config: &AppConfig) -> Result<String, String> {
+    match command {
+        ROTATE_SERVER_KEY => rotate_server_key(app_config),
+        SERVER_KEY_STATUS => server_key_status(app_config),
+        MIGRATE_LEGACY_FILES => migrate_legacy_files(app_config),
+        _ => Err(format!(
+            "Unknown command {}, available commands: {}, {}, {}.",
+            command, ROTATE_SERVER_KEY, SERVER_KEY_STATUS, MIGRATE_LEGACY_FILES
+        )),
+    }
+}
+
+fn rotate_server_key(app_config: &AppConfig) -> Result<String, String> {
+    let mut server_keyring = load_existing_server_keyring(&app_config.server_key.path)
+        .map_err(|error| error.to_string())?;
+
+    let password_file_storage = StandardFileStorage::new(app_config.password_file.path.clone());
+
+    let used_versions: BTreeSet<u32> = count_password_files_by_key_version(&password_file_storage)
+        .map_err(|error| error.to_string())?
+        .into_keys()
+        .collect();
+
+    let new_version = server_keyring
+        .rotate_to(configured_cipher_suite(app_config)?)
+        .map_err(|error| error.to_string())?;
+    let removed_versions = server_keyring.retain_used_versions(&used_versions);
+
+    server_keyring
+        .save(&app_config.server_key.path)
+        .map_err(|error| error.to_string())?;
+
+    Ok(format!(
+        "Server key version {} is now current, removed unused versions: {:?}. Restart the server to use it.",
+        new_version, removed_versions
+    ))
+}
+
+fn server_key_status(app_config: &AppConfig) -> Result<String, String> {
+    let server_keyring = load_existing_server_keyring(&app_config.server_key.path)
+        .map_err(|error| error.to_string())?;
+
+    let password_file_storage = StandardFileStorage::new(app_config.password_file.path.clone());
+
+    let mut counts = count_password_files_by_key_version(&password_file_storage)
+        .map_err(|error| error.to_string())?;
+
+    let mut report = format!("Current server key version: {}", server_keyring.current_version());
+
+    for version in server_keyring.versions() {
+        let users = counts.remove(&version).unwrap_or(0);
+        let cipher_suite = server_keyring
+            .get(version)
+            .map(|server_key| server_key.cipher_suite().name())
+            .unwrap_or_default();
+
+        report.push_str(&format!("\nVersion {} ({}): {} password file(s)", version, cipher_suite, users));
+    }
+
+    for (version, users) in counts {
+        report.push_str(&format!("\nVersion {} (missing from the key file): {} password file(s)", version, users));
+    }
+
+    Ok(report)
+}
+
+// Older versions saved the files under the username, they move to their hashed name. Names saved before usernames
+// were canonical then move to their canonical one. Run once when upgrading, the server never moves files by itself.
+fn migrate_legacy_files(app_config: &AppConfig) -> Result<String, String> {
+    let mut legacy_files = Vec::new();
+
+    // Only usernames were saved under their name, a file named otherwise is not one of ours. Nothing moves then.
+    for path in [&app_config.vault_store.path, &app_config.password_file.path, &app_config.two_factor.path] {
+        let file_storage = StandardFileStorage::new(path.clone());
+
+        let legacy_file_names = file_storage.legacy_file_names().map_err(|error| error.to_string())?;
+
+        for file_name in &legacy_file_names {
+            Username::parse(file_name)
+                .map_err(|error| format!("{} in {} is not a valid username: {}", file_name, path, error))?;
+        }
+
+        legacy_files.push((path, file_storage, legacy_file_names));
+    }
+
+    let mut report = Vec::new();
+
+    for (path, file_storage, legacy_file_names) in legacy_files {
+        let hashed_files = file_storage
+            .migrate_legacy_files(&legacy_file_names)
+            .map_err(|error| error.to_string())?;
+
+        let canonicalised_files = if *path == app_config.password_file.path {
+            canonicalise_password_files(&file_storage)
+        } else {
+            canonicalise_files(&file_storage)
+        }
+        .map_err(|error| error.to_string())?;
+
+        report.push(format!(
+            "{}: {} file(s) moved to their hashed name, {} to their canonical username",
+            path, hashed_files, canonicalised_files
+        ));
+    }
+
+    Ok(report.join("\n"))
+}
+
+fn configured_cipher_suite(app_config: &AppConfig) -> Result<CipherSuiteId, String> {
+    CipherSuiteId::from_name(&app_config.opaque.cipher_suite).map_err(|error| error.to_string())
+}
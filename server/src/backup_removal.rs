@@ -0,0 +1,56 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use authentication::recovery_file::recovery_file_name;
+use core_domain::{
+    backup_removal_list::backup_removal_list_error::{BackupRemovalListError, Result},
+    domain::username::Username,
+    ports::backup_removal_list::BackupRemovalList,
+};
+use file_storage::file_storage::stored_file_name;
+
+// The server cannot reach the operator's backups of its directories. Each deleted account is appended here, one
+// `<unix seconds> <stored name> <stored recovery name>` line: the hashed names its files have in every directory,
+// which the backups hold already, rather than the username the deletion was meant to remove.
+pub struct FileBackupRemovalList {
+    path: String,
+}
+
+impl FileBackupRemovalList {
+    pub fn new(path: String) -> Self {
+        Self { path }
+    }
+}
+
+impl BackupRemovalList for FileBackupRemovalList {
+    fn flag(&self, username: &Username) -> Result<()> {
+        if let Some(parent) = Path::new(&self.path).parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent).map_err(|error| BackupRemovalListError::WritingToFile(error.to_string()))?;
+        }
+
+        let deleted_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .map_err(|error| BackupRemovalListError::Internal(error.to_string()))?;
+
+        let line = format!(
+            "{} {} {}\n",
+            deleted_at,
+            stored_file_name(username.as_str()),
+            stored_file_name(&recovery_file_name(username.as_str()))
+        );
+
+        OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|error| BackupRemovalListError::WritingToFile(error.to_string()))
+    }
+}
@@ -5,12 +5,30 @@ use serde::Deserialize;
 pub struct AppConfig {
     pub server: ServerInfo,
     pub vault_store: VaultStoreInfo,
-    pub password_file: PasswordFileInfo
+    pub password_file: PasswordFileInfo,
+    #[serde(default)]
+    pub server_key: ServerKeyInfo,
+    #[serde(default)]
+    pub session: SessionInfo,
+    #[serde(default)]
+    pub login: LoginInfo,
+    #[serde(default)]
+    pub throttle: ThrottleInfo,
+    #[serde(default)]
+    pub opaque: OpaqueInfo,
+    #[serde(default)]
+    pub two_factor: TwoFactorInfo,
+    #[serde(default)]
+    pub account_deletion: AccountDeletionInfo
 }
 
 #[derive(Debug, Deserialize, Default)]
 pub struct ServerInfo {
-    pub request_max_ttl: u64
+    pub request_max_ttl: u64,
+    #[serde(default = "default_allowed_clock_skew")]
+    pub allowed_clock_skew: u64,
+    pub identity: Option<String>,
+    pub context: Option<String>
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -23,6 +41,142 @@ pub struct PasswordFileInfo {
     pub path: String
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ServerKeyInfo {
+    pub path: String
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct SessionInfo {
+    pub absolute_ttl: u64,
+    pub idle_ttl: u64,
+    pub sweep_interval: u64,
+    pub refresh_grace_period: u64,
+    pub fresh_login_window: u64,
+    pub store_path: String,
+    pub store_key_path: String
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct LoginInfo {
+    pub pending_ttl: u64,
+    pub max_pending: usize
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ThrottleInfo {
+    pub path: String,
+    pub free_attempts: u32,
+    pub base_delay: u64,
+    pub max_delay: u64,
+    pub lockout_threshold: u32,
+    pub lockout_duration: u64
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct OpaqueInfo {
+    pub cipher_suite: String,
+    pub argon2_memory_cost: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct TwoFactorInfo {
+    pub path: String,
+    pub issuer: String
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct AccountDeletionInfo {
+    pub backup_removal_list: String
+}
+
+// Config files written before a section existed still load, with the values config.yaml documents.
+impl Default for ServerKeyInfo {
+    fn default() -> Self {
+        Self {
+            path: "server_key/server_setup.key".to_string()
+        }
+    }
+}
+
+impl Default for SessionInfo {
+    fn default() -> Self {
+        Self {
+            absolute_ttl: 43200,
+            idle_ttl: 900,
+            sweep_interval: 60,
+            refresh_grace_period: 30,
+            fresh_login_window: 300,
+            store_path: "sessions/sessions".to_string(),
+            store_key_path: "sessions/session_store.key".to_string()
+        }
+    }
+}
+
+impl Default for LoginInfo {
+    fn default() -> Self {
+        Self {
+            pending_ttl: 30,
+            max_pending: 10
+        }
+    }
+}
+
+impl Default for ThrottleInfo {
+    fn default() -> Self {
+        Self {
+            path: "throttle/login_failures".to_string(),
+            free_attempts: 3,
+            base_delay: 1,
+            max_delay: 300,
+            lockout_threshold: 10,
+            lockout_duration: 900
+        }
+    }
+}
+
+// Ristretto255 with SHA-512 and the default Argon2 costs is what the server used before they were configurable.
+impl Default for OpaqueInfo {
+    fn default() -> Self {
+        Self {
+            cipher_suite: "ristretto255-sha512".to_string(),
+            argon2_memory_cost: 19456,
+            argon2_iterations: 2,
+            argon2_parallelism: 1
+        }
+    }
+}
+
+impl Default for TwoFactorInfo {
+    fn default() -> Self {
+        Self {
+            path: "two_factor".to_string(),
+            issuer: "Vault".to_string()
+        }
+    }
+}
+
+impl Default for AccountDeletionInfo {
+    fn default() -> Self {
+        Self {
+            backup_removal_list: "backup_removal.list".to_string()
+        }
+    }
+}
+
+fn default_allowed_clock_skew() -> u64 {
+    30
+}
+
 impl AppConfig {
     pub fn build(args: Vec<String>) -> Result<Self, String> {
         if args.len() < 2 {
@@ -1,83 +1,337 @@
-use std::{env, process::exit, sync::Mutex};
+use std::{env, process::exit, sync::{Arc, Mutex}};
 
-use authentication::opaque_authentication::OpaqueAuthentication;
-use core_domain::{domain::server_domain::{Domain, ServerDomain}, ports::file_storage::FileStorage};
+use authentication::{cipher_suite::{CipherSuiteId, KsfParameters}, login_throttle::{LoginThrottle, ThrottlePolicy}, opaque_authentication::OpaqueAuthentication, pending_login::LoginPolicy, request_signature::RequestPolicy, server_identity::ServerIdentity, server_key::load_or_create_server_keyring, session::SessionPolicy};
+use core_domain::{domain::{password_change::PasswordChange, recovery_setup::RecoverySetup, server_domain::{Domain, ServerDomain}, server_domain_errors::ServerDomainError}, ports::file_storage::FileStorage};
 use file_storage::file_storage::StandardFileStorage;
-use rocket::{http::Status, State};
+use rocket::{fairing::AdHoc, http::Status, serde::json::Json, Build, Rocket, State};
+use session_store::{file_session_store::FileSessionStore, session_store_key::load_or_create_session_store_key};
 use vault_store::directory_vault_store::DirectoryVaultStore;
 
-use crate::{config::AppConfig, requests::{OpaqueRequest, VaultRequest}};
+use crate::{backup_removal::FileBackupRemovalList, config::AppConfig, requests::{LoginFinishRequest, OpaqueRequest, VaultRequest}, responses::{AccountDeletionResponse, ErrorResponse, LoginStartResponse, LoginFinishResponse, ParametersResponse, RecoveryCodesResponse, RefreshResponse, SessionResponse, TwoFactorEnrollmentResponse}, server_state::{ServerState, sweep_expired_sessions}};
 
 #[macro_use]
 extern crate rocket;
 
+mod admin;
+mod backup_removal;
 mod requests;
+mod responses;
 mod config;
+mod server_state;
 
-const POST: &'static str = "POST";
-const GET: &'static str = "GET";
+#[cfg(test)]
+mod tests;
+
+const INVALID_PASSWORD_CHANGE_BODY: &str = "Invalid password change body.";
+const INVALID_RECOVERY_SETUP_BODY: &str = "Invalid recovery setup body.";
+
+#[get("/opaque/parameters")]
+fn opaque_parameters(server_domain: &State<ServerState>) -> Json<ParametersResponse> {
+
+    Json(ParametersResponse::from(server_domain.lock().unwrap().opaque_parameters()))
+}
 
 #[post("/opaque/registration/start", format = "application/octet-stream", data = "<client_message>")]
-fn opaque_registration_start(client_message: &[u8], opaque_request: OpaqueRequest, server_domain: &State<Mutex<ServerDomain<DirectoryVaultStore<StandardFileStorage>, OpaqueAuthentication<StandardFileStorage>>>>) -> (Status, Vec<u8>) {
+fn opaque_registration_start(client_message: &[u8], opaque_request: OpaqueRequest, server_domain: &State<ServerState>) -> (Status, Vec<u8>) {
 
     match server_domain.lock().unwrap().start_server_registration(&opaque_request.username, client_message.to_vec()) {
         Ok(server_registration_start_result) => (Status::Ok, server_registration_start_result),
-        Err(error) => (Status::InternalServerError, error.to_string().into_bytes())
+        Err(error) => (server_domain_error_to_status(&error), error.to_string().into_bytes())
     }
 }
 
 #[post("/opaque/registration/finish", format = "application/octet-stream", data = "<client_message>")]
-fn opaque_registration_finish(client_message: &[u8], opaque_request: OpaqueRequest, server_domain: &State<Mutex<ServerDomain<DirectoryVaultStore<StandardFileStorage>, OpaqueAuthentication<StandardFileStorage>>>>) -> (Status, Vec<u8>) {
+fn opaque_registration_finish(client_message: &[u8], opaque_request: OpaqueRequest, server_domain: &State<ServerState>) -> (Status, Vec<u8>) {
 
     match server_domain.lock().unwrap().finish_server_registration(&opaque_request.username, client_message.to_vec()) {
         Ok(_) => (Status::Ok, vec![]),
-        Err(error) => (Status::InternalServerError, error.to_string().into_bytes())
+        Err(error) => (server_domain_error_to_status(&error), error.to_string().into_bytes())
     }
 }
 
 #[post("/opaque/login/start", format = "application/octet-stream", data = "<client_message>")]
-fn opaque_login_start(client_message: &[u8], opaque_request: OpaqueRequest, server_domain: &State<Mutex<ServerDomain<DirectoryVaultStore<StandardFileStorage>, OpaqueAuthentication<StandardFileStorage>>>>) -> (Status, Vec<u8>) {
+fn opaque_login_start(client_message: &[u8], opaque_request: OpaqueRequest, server_domain: &State<ServerState>) -> Result<LoginStartResponse, ErrorResponse> {
 
-    match server_domain.lock().unwrap().start_server_login(&opaque_request.username, client_message.to_vec()) {
-        Ok(server_login_start_result) => (Status::Ok, server_login_start_result),
-        Err(error) => (Status::InternalServerError, error.to_string().into_bytes())
+    match server_domain.lock().unwrap().start_server_login(&opaque_request.username, opaque_request.client_ip.as_deref(), client_message.to_vec()) {
+        Ok(login_start) => Ok(LoginStartResponse::from(login_start)),
+        Err(error) => Err(server_domain_error_to_response(&error))
     }
 }
 
 #[post("/opaque/login/finish", format = "application/octet-stream", data = "<client_message>")]
-fn opaque_login_finish(client_message: &[u8], opaque_request: OpaqueRequest, server_domain: &State<Mutex<ServerDomain<DirectoryVaultStore<StandardFileStorage>, OpaqueAuthentication<StandardFileStorage>>>>) -> (Status, Vec<u8>) {
+fn opaque_login_finish(client_message: &[u8], login_finish_request: LoginFinishRequest, server_domain: &State<ServerState>) -> Result<LoginFinishResponse, ErrorResponse> {
 
-    match server_domain.lock().unwrap().finish_server_login(&opaque_request.username, client_message.to_vec()) {
-        Ok(_) => (Status::Ok, vec![]),
-        Err(error) => (Status::InternalServerError, error.to_string().into_bytes())
+    match server_domain.lock().unwrap().finish_server_login(&login_finish_request.username, &login_finish_request.login_id, client_message.to_vec(), login_finish_request.client_label.as_deref()) {
+        Ok(login_finish) => Ok(LoginFinishResponse::from(login_finish)),
+        Err(error) => Err(server_domain_error_to_response(&error))
     }
 }
 
 #[post("/vault", format = "application/octet-stream", data = "<vault>")]
-fn save_vault(vault: &[u8], vault_request: VaultRequest, server_domain: &State<Mutex<ServerDomain<DirectoryVaultStore<StandardFileStorage>, OpaqueAuthentication<StandardFileStorage>>>>) -> (Status, Vec<u8>) {
-
-    let uri = format!("{}{}", &vault_request.host, "/vault");
+fn save_vault(vault: &[u8], vault_request: VaultRequest, server_domain: &State<ServerState>) -> (Status, Vec<u8>) {
 
-    match server_domain.lock().unwrap().save_vault(&vault_request.bearer_token, POST, &uri, &vault_request.timestamp, &vault_request.signature, vault.to_vec()) {
+    match server_domain.lock().unwrap().save_vault(&vault_request.signed_request, vault.to_vec()) {
         Ok(_) => (Status::Ok, vec![]),
-        Err(error) => (Status::InternalServerError, error.to_string().into_bytes())
+        Err(error) => (server_domain_error_to_status(&error), error.to_string().into_bytes())
     }
 }
 
 #[get("/vault")]
-fn retrieve_vault(vault_request: VaultRequest, server_domain: &State<Mutex<ServerDomain<DirectoryVaultStore<StandardFileStorage>, OpaqueAuthentication<StandardFileStorage>>>>) -> (Status, Vec<u8>) {
+fn retrieve_vault(vault_request: VaultRequest, server_domain: &State<ServerState>) -> (Status, Vec<u8>) {
 
-    let uri = format!("{}{}", &vault_request.host, "/vault");
-
-    match server_domain.lock().unwrap().get_vault(&vault_request.bearer_token, GET, &uri, &vault_request.timestamp, &vault_request.signature) {
+    match server_domain.lock().unwrap().get_vault(&vault_request.signed_request) {
         Ok(vault) => (Status::Ok, vault),
-        Err(error) => (Status::InternalServerError, error.to_string().into_bytes())
+        Err(error) => (server_domain_error_to_status(&error), error.to_string().into_bytes())
     }
 }
 
+#[post("/account/password/start", format = "application/octet-stream", data = "<client_message>")]
+fn password_change_start(client_message: &[u8], vault_request: VaultRequest, server_domain: &State<ServerState>) -> (Status, Vec<u8>) {
+
+    match server_domain.lock().unwrap().start_password_change(&vault_request.signed_request, client_message.to_vec()) {
+        Ok(server_registration_start_result) => (Status::Ok, server_registration_start_result),
+        Err(error) => (server_domain_error_to_status(&error), error.to_string().into_bytes())
+    }
+}
+
+#[post("/account/password/finish", format = "application/octet-stream", data = "<body>")]
+fn password_change_finish(body: &[u8], vault_request: VaultRequest, server_domain: &State<ServerState>) -> (Status, Vec<u8>) {
+
+    let Some(password_change) = PasswordChange::deserialize(body) else {
+        return (Status::BadRequest, INVALID_PASSWORD_CHANGE_BODY.as_bytes().to_vec());
+    };
+
+    match server_domain.lock().unwrap().finish_password_change(&vault_request.signed_request, password_change) {
+        Ok(revoked_sessions) => (Status::Ok, revoked_sessions.to_string().into_bytes()),
+        Err(error) => (server_domain_error_to_status(&error), error.to_string().into_bytes())
+    }
+}
+
+#[get("/time")]
+fn server_time(server_domain: &State<ServerState>) -> (Status, Vec<u8>) {
+
+    match server_domain.lock().unwrap().server_time() {
+        Ok(server_time) => (Status::Ok, server_time.to_string().into_bytes()),
+        Err(error) => (server_domain_error_to_status(&error), error.to_string().into_bytes())
+    }
+}
+
+#[post("/session/logout")]
+fn logout(vault_request: VaultRequest, server_domain: &State<ServerState>) -> (Status, Vec<u8>) {
+
+    match server_domain.lock().unwrap().logout(&vault_request.signed_request) {
+        Ok(_) => (Status::Ok, vec![]),
+        Err(error) => (server_domain_error_to_status(&error), error.to_string().into_bytes())
+    }
+}
+
+#[post("/session/logout-all")]
+fn logout_all(vault_request: VaultRequest, server_domain: &State<ServerState>) -> (Status, Vec<u8>) {
+
+    match server_domain.lock().unwrap().logout_all(&vault_request.signed_request) {
+        Ok(revoked_sessions) => (Status::Ok, revoked_sessions.to_string().into_bytes()),
+        Err(error) => (server_domain_error_to_status(&error), error.to_string().into_bytes())
+    }
+}
+
+#[post("/session/refresh")]
+fn refresh_session(vault_request: VaultRequest, server_domain: &State<ServerState>) -> Result<Json<RefreshResponse>, (Status, Vec<u8>)> {
+
+    match server_domain.lock().unwrap().refresh_session(&vault_request.signed_request) {
+        Ok(session_refresh) => Ok(Json(RefreshResponse::from(session_refresh))),
+        Err(error) => Err((server_domain_error_to_status(&error), error.to_string().into_bytes()))
+    }
+}
+
+#[post("/account/2fa/enroll")]
+fn two_factor_enroll(vault_request: VaultRequest, server_domain: &State<ServerState>) -> Result<Json<TwoFactorEnrollmentResponse>, (Status, Vec<u8>)> {
+
+    match server_domain.lock().unwrap().start_two_factor_enrollment(&vault_request.signed_request) {
+        Ok(two_factor_enrollment) => Ok(Json(TwoFactorEnrollmentResponse::from(two_factor_enrollment))),
+        Err(error) => Err((server_domain_error_to_status(&error), error.to_string().into_bytes()))
+    }
+}
+
+#[post("/account/2fa/confirm", format = "application/octet-stream", data = "<code>")]
+fn two_factor_confirm(code: &[u8], vault_request: VaultRequest, server_domain: &State<ServerState>) -> Result<Json<RecoveryCodesResponse>, (Status, Vec<u8>)> {
+
+    match server_domain.lock().unwrap().confirm_two_factor_enrollment(&vault_request.signed_request, &String::from_utf8_lossy(code)) {
+        Ok(recovery_codes) => Ok(Json(RecoveryCodesResponse { recovery_codes })),
+        Err(error) => Err((server_domain_error_to_status(&error), error.to_string().into_bytes()))
+    }
+}
+
+#[post("/account/2fa/verify", format = "application/octet-stream", data = "<code>")]
+fn two_factor_verify(code: &[u8], vault_request: VaultRequest, server_domain: &State<ServerState>) -> Result<(), ErrorResponse> {
+
+    match server_domain.lock().unwrap().verify_second_factor(&vault_request.signed_request, &String::from_utf8_lossy(code)) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(server_domain_error_to_response(&error))
+    }
+}
+
+#[post("/account/2fa/disable", format = "application/octet-stream", data = "<code>")]
+fn two_factor_disable(code: &[u8], vault_request: VaultRequest, server_domain: &State<ServerState>) -> Result<(), ErrorResponse> {
+
+    match server_domain.lock().unwrap().disable_two_factor(&vault_request.signed_request, &String::from_utf8_lossy(code)) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(server_domain_error_to_response(&error))
+    }
+}
+
+#[post("/account/recovery/start", format = "application/octet-stream", data = "<client_message>")]
+fn recovery_setup_start(client_message: &[u8], vault_request: VaultRequest, server_domain: &State<ServerState>) -> (Status, Vec<u8>) {
+
+    match server_domain.lock().unwrap().start_recovery_setup(&vault_request.signed_request, client_message.to_vec()) {
+        Ok(server_registration_start_result) => (Status::Ok, server_registration_start_result),
+        Err(error) => (server_domain_error_to_status(&error), error.to_string().into_bytes())
+    }
+}
+
+#[post("/account/recovery/finish", format = "application/octet-stream", data = "<body>")]
+fn recovery_setup_finish(body: &[u8], vault_request: VaultRequest, server_domain: &State<ServerState>) -> (Status, Vec<u8>) {
+
+    let Some(recovery_setup) = RecoverySetup::deserialize(body) else {
+        return (Status::BadRequest, INVALID_RECOVERY_SETUP_BODY.as_bytes().to_vec());
+    };
+
+    match server_domain.lock().unwrap().finish_recovery_setup(&vault_request.signed_request, recovery_setup) {
+        Ok(_) => (Status::Ok, vec![]),
+        Err(error) => (server_domain_error_to_status(&error), error.to_string().into_bytes())
+    }
+}
+
+#[post("/opaque/recovery/login/start", format = "application/octet-stream", data = "<client_message>")]
+fn recovery_login_start(client_message: &[u8], opaque_request: OpaqueRequest, server_domain: &State<ServerState>) -> Result<LoginStartResponse, ErrorResponse> {
+
+    match server_domain.lock().unwrap().start_recovery_login(&opaque_request.username, opaque_request.client_ip.as_deref(), client_message.to_vec()) {
+        Ok(login_start) => Ok(LoginStartResponse::from(login_start)),
+        Err(error) => Err(server_domain_error_to_response(&error))
+    }
+}
+
+#[post("/opaque/recovery/login/finish", format = "application/octet-stream", data = "<client_message>")]
+fn recovery_login_finish(client_message: &[u8], login_finish_request: LoginFinishRequest, server_domain: &State<ServerState>) -> Result<LoginFinishResponse, ErrorResponse> {
+
+    match server_domain.lock().unwrap().finish_recovery_login(&login_finish_request.username, &login_finish_request.login_id, client_message.to_vec(), login_finish_request.client_label.as_deref()) {
+        Ok(login_finish) => Ok(LoginFinishResponse::from(login_finish)),
+        Err(error) => Err(server_domain_error_to_response(&error))
+    }
+}
+
+#[get("/account/recovery/key")]
+fn recovery_key(vault_request: VaultRequest, server_domain: &State<ServerState>) -> (Status, Vec<u8>) {
+
+    match server_domain.lock().unwrap().get_recovery_key(&vault_request.signed_request) {
+        Ok(wrapped_key) => (Status::Ok, wrapped_key),
+        Err(error) => (server_domain_error_to_status(&error), error.to_string().into_bytes())
+    }
+}
+
+#[post("/account/recovery/reset/start", format = "application/octet-stream", data = "<client_message>")]
+fn recovery_reset_start(client_message: &[u8], vault_request: VaultRequest, server_domain: &State<ServerState>) -> (Status, Vec<u8>) {
+
+    match server_domain.lock().unwrap().start_recovery_reset(&vault_request.signed_request, client_message.to_vec()) {
+        Ok(server_registration_start_result) => (Status::Ok, server_registration_start_result),
+        Err(error) => (server_domain_error_to_status(&error), error.to_string().into_bytes())
+    }
+}
+
+#[post("/account/recovery/reset/finish", format = "application/octet-stream", data = "<body>")]
+fn recovery_reset_finish(body: &[u8], vault_request: VaultRequest, server_domain: &State<ServerState>) -> (Status, Vec<u8>) {
+
+    let Some(password_change) = PasswordChange::deserialize(body) else {
+        return (Status::BadRequest, INVALID_PASSWORD_CHANGE_BODY.as_bytes().to_vec());
+    };
+
+    match server_domain.lock().unwrap().finish_recovery_reset(&vault_request.signed_request, password_change) {
+        Ok(revoked_sessions) => (Status::Ok, revoked_sessions.to_string().into_bytes()),
+        Err(error) => (server_domain_error_to_status(&error), error.to_string().into_bytes())
+    }
+}
+
+#[delete("/account")]
+fn delete_account(vault_request: VaultRequest, server_domain: &State<ServerState>) -> Result<Json<AccountDeletionResponse>, (Status, Vec<u8>)> {
+
+    let account_deletion = match server_domain.lock().unwrap().delete_account(&vault_request.signed_request) {
+        Ok(account_deletion) => account_deletion,
+        Err(error) => return Err((server_domain_error_to_status(&error), error.to_string().into_bytes()))
+    };
+
+    // The account is already gone, a failure here is only reported.
+    if let Some(error) = &account_deletion.backup_removal_error {
+        eprintln!("Error flagging the backups of a deleted account for removal: {error}");
+    }
+
+    Ok(Json(AccountDeletionResponse {
+        deleted: account_deletion.deleted,
+        revoked_sessions: account_deletion.revoked_sessions,
+        backups_flagged_for_removal: account_deletion.backup_removal_error.is_none(),
+    }))
+}
+
+#[get("/sessions")]
+fn list_sessions(vault_request: VaultRequest, server_domain: &State<ServerState>) -> Result<Json<Vec<SessionResponse>>, (Status, Vec<u8>)> {
+
+    match server_domain.lock().unwrap().list_sessions(&vault_request.signed_request) {
+        Ok(sessions) => Ok(Json(sessions.into_iter().map(SessionResponse::from).collect())),
+        Err(error) => Err((server_domain_error_to_status(&error), error.to_string().into_bytes()))
+    }
+}
+
+#[delete("/sessions/<session_id>")]
+fn end_session(session_id: &str, vault_request: VaultRequest, server_domain: &State<ServerState>) -> (Status, Vec<u8>) {
+
+    match server_domain.lock().unwrap().end_session(&vault_request.signed_request, session_id) {
+        Ok(_) => (Status::Ok, vec![]),
+        Err(error) => (server_domain_error_to_status(&error), error.to_string().into_bytes())
+    }
+}
+
+fn server_domain_error_to_status(server_domain_error: &ServerDomainError) -> Status {
+    match server_domain_error {
+        ServerDomainError::InvalidUsername(_) => Status::BadRequest,
+        ServerDomainError::Forbidden(_) => Status::Forbidden,
+        ServerDomainError::NotFound(_) => Status::NotFound,
+        ServerDomainError::Conflict(_) => Status::Conflict,
+        ServerDomainError::TooManyRequests(_) => Status::TooManyRequests,
+        ServerDomainError::Throttled(_) => Status::TooManyRequests,
+        ServerDomainError::ReplayedRequest(_) => Status::Conflict,
+        ServerDomainError::ClockSkew(_) => Status::PreconditionFailed,
+        ServerDomainError::SecondFactorRequired(_) => Status::Unauthorized,
+        ServerDomainError::FreshLoginRequired(_) => Status::Unauthorized,
+        ServerDomainError::Internal(_) => Status::InternalServerError,
+    }
+}
+
+fn server_domain_error_to_response(server_domain_error: &ServerDomainError) -> ErrorResponse {
+    let retry_after = match server_domain_error {
+        ServerDomainError::Throttled(retry_after) => Some(*retry_after),
+        _ => None,
+    };
+
+    ErrorResponse {
+        status: server_domain_error_to_status(server_domain_error),
+        message: server_domain_error.to_string().into_bytes(),
+        retry_after,
+    }
+}
+
+// Temporary files left by a crash during a save are removed at every start.
+fn remove_temporary_files(app_config: &AppConfig) -> Result<(), String> {
+    for path in [&app_config.vault_store.path, &app_config.password_file.path, &app_config.two_factor.path] {
+        StandardFileStorage::new(path.clone())
+            .remove_temporary_files()
+            .map_err(|error| error.to_string())?;
+    }
+
+    Ok(())
+}
+
 #[launch]
 fn rocket() -> _ {
     let args: Vec<String> = env::args().collect();
+    let admin_command = args.get(2).cloned();
 
     let app_config = match AppConfig::build(args) {
         Ok(config) => config,
@@ -87,23 +341,160 @@ fn rocket() -> _ {
         }
     };
 
+    if let Err(error) = remove_temporary_files(&app_config) {
+        eprintln!("Error removing temporary files: {error}");
+        exit(1);
+    }
+
+    if let Some(admin_command) = admin_command {
+        match admin::run_admin_command(&admin_command, &app_config) {
+            Ok(report) => {
+                println!("{report}");
+                exit(0);
+            }
+            Err(error) => {
+                eprintln!("Error running {admin_command}: {error}");
+                exit(1);
+            }
+        }
+    }
+
     let vault_file_storage = StandardFileStorage::new(app_config.vault_store.path);
     let authentication_file_storage = StandardFileStorage::new(app_config.password_file.path);
 
     let vault_store = DirectoryVaultStore::new(vault_file_storage);
 
-    let authentication = OpaqueAuthentication::new(
-        authentication_file_storage,
+    let cipher_suite = match CipherSuiteId::from_name(&app_config.opaque.cipher_suite) {
+        Ok(cipher_suite) => cipher_suite,
+        Err(error) => {
+            eprintln!("Error reading the cipher suite: {error}");
+            exit(1);
+        }
+    };
+
+    let ksf_parameters = match KsfParameters::new(
+        app_config.opaque.argon2_memory_cost,
+        app_config.opaque.argon2_iterations,
+        app_config.opaque.argon2_parallelism,
+    ) {
+        Ok(ksf_parameters) => ksf_parameters,
+        Err(error) => {
+            eprintln!("Error reading the Argon2 parameters: {error}");
+            exit(1);
+        }
+    };
+
+    let server_keyring = match load_or_create_server_keyring(&app_config.server_key.path, cipher_suite) {
+        Ok(server_keyring) => server_keyring,
+        Err(error) => {
+            eprintln!("Error loading server key: {error}");
+            exit(1);
+        }
+    };
+
+    let session_store = match load_or_create_session_store_key(&app_config.session.store_key_path)
+        .and_then(|session_store_key| FileSessionStore::load(&app_config.session.store_path, session_store_key))
+    {
+        Ok(session_store) => session_store,
+        Err(error) => {
+            eprintln!("Error loading the session store: {error}");
+            exit(1);
+        }
+    };
+
+    let request_policy = RequestPolicy::new(
         app_config.server.request_max_ttl,
+        app_config.server.allowed_clock_skew,
+    );
+
+    let session_policy = SessionPolicy::new(
+        app_config.session.absolute_ttl,
+        app_config.session.idle_ttl,
+        app_config.session.refresh_grace_period,
+        app_config.session.fresh_login_window,
+    );
+
+    let login_policy = LoginPolicy::new(
+        app_config.login.pending_ttl,
+        app_config.login.max_pending,
+    );
+
+    let throttle_policy = ThrottlePolicy::new(
+        app_config.throttle.free_attempts,
+        app_config.throttle.base_delay,
+        app_config.throttle.max_delay,
+        app_config.throttle.lockout_threshold,
+        app_config.throttle.lockout_duration,
     );
-    let server_domain = ServerDomain::new(vault_store, authentication);
 
+    let login_throttle = match LoginThrottle::load(&app_config.throttle.path, throttle_policy) {
+        Ok(login_throttle) => login_throttle,
+        Err(error) => {
+            eprintln!("Error loading login throttle counters: {error}");
+            exit(1);
+        }
+    };
+
+    let authentication = match OpaqueAuthentication::new(
+        authentication_file_storage,
+        session_store,
+        server_keyring,
+        request_policy,
+        session_policy,
+        login_policy,
+        login_throttle,
+    ) {
+        Ok(authentication) => authentication,
+        Err(error) => {
+            eprintln!("Error starting authentication: {error}");
+            exit(1);
+        }
+    }
+    .with_ksf_parameters(ksf_parameters)
+    .with_server_identity(ServerIdentity::new(app_config.server.identity, app_config.server.context))
+    .with_two_factor(StandardFileStorage::new(app_config.two_factor.path), &app_config.two_factor.issuer);
+    let backup_removal_list = FileBackupRemovalList::new(app_config.account_deletion.backup_removal_list);
+
+    let server_domain = ServerDomain::new(vault_store, authentication, backup_removal_list);
+
+    let server_state: ServerState = Arc::new(Mutex::new(server_domain));
+    let sweeper_state = server_state.clone();
+    let sweep_interval = app_config.session.sweep_interval;
+
+    build_rocket(server_state)
+        .attach(AdHoc::on_liftoff("Expired sessions sweeper", move |_| Box::pin(async move {
+            rocket::tokio::spawn(sweep_expired_sessions(sweeper_state, sweep_interval));
+        })))
+}
+
+fn build_rocket(server_state: ServerState) -> Rocket<Build> {
     rocket::build()
-        .manage(Mutex::new(server_domain))
+        .manage(server_state)
+        .mount("/", routes![opaque_parameters])
         .mount("/", routes![opaque_registration_start])
         .mount("/", routes![opaque_registration_finish])
         .mount("/", routes![opaque_login_start])
         .mount("/", routes![opaque_login_finish])
         .mount("/", routes![retrieve_vault])
         .mount("/", routes![save_vault])
+        .mount("/", routes![password_change_start])
+        .mount("/", routes![password_change_finish])
+        .mount("/", routes![server_time])
+        .mount("/", routes![logout])
+        .mount("/", routes![logout_all])
+        .mount("/", routes![refresh_session])
+        .mount("/", routes![two_factor_enroll])
+        .mount("/", routes![two_factor_confirm])
+        .mount("/", routes![two_factor_verify])
+        .mount("/", routes![two_factor_disable])
+        .mount("/", routes![recovery_setup_start])
+        .mount("/", routes![recovery_setup_finish])
+        .mount("/", routes![recovery_login_start])
+        .mount("/", routes![recovery_login_finish])
+        .mount("/", routes![recovery_key])
+        .mount("/", routes![recovery_reset_start])
+        .mount("/", routes![recovery_reset_finish])
+        .mount("/", routes![delete_account])
+        .mount("/", routes![list_sessions])
+        .mount("/", routes![end_session])
 }
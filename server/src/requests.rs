@@ -1,3 +1,4 @@
+use core_domain::authentication::signed_request::SignedRequest;
 use rocket::{
     Request,
     http::Status,
@@ -7,15 +8,18 @@ use rocket::{
 const AUTHORIZATION: &'static str = "Authorization";
 const X_TIMESTAMP: &'static str = "X-Timestamp";
 const X_SIGNATURE: &'static str = "X-Signature";
+const X_NONCE: &'static str = "X-Nonce";
 const X_USERNAME: &'static str = "X-Username";
+const X_CLIENT_LABEL: &'static str = "X-Client-Label";
+const X_LOGIN_ID: &'static str = "X-Login-Id";
 const BEARER: &'static str = "Bearer ";
 const HOST: &'static str = "Host";
+const CONTENT_DIGEST: &'static str = "Content-Digest";
+const NONCE_MIN_LENGTH: usize = 16;
+const NONCE_MAX_LENGTH: usize = 128;
 
 pub struct VaultRequest {
-    pub bearer_token: String,
-    pub host: String,
-    pub timestamp: String,
-    pub signature: String,
+    pub signed_request: SignedRequest,
 }
 
 #[derive(Debug)]
@@ -25,6 +29,13 @@ pub enum RequestError {
 
 pub struct OpaqueRequest {
     pub username: String,
+    pub client_ip: Option<String>,
+}
+
+pub struct LoginFinishRequest {
+    pub username: String,
+    pub login_id: String,
+    pub client_label: Option<String>,
 }
 
 #[rocket::async_trait]
@@ -50,11 +61,27 @@ impl<'r> FromRequest<'r> for VaultRequest {
             return Outcome::Error((Status::BadRequest, RequestError::Missing));
         };
 
+        let Some(content_digest) = request.headers().get_one(CONTENT_DIGEST) else {
+            return Outcome::Error((Status::BadRequest, RequestError::Missing));
+        };
+
+        let nonce = match request.headers().get_one(X_NONCE) {
+            Some(value) if is_valid_nonce(value) => value,
+            _ => return Outcome::Error((Status::BadRequest, RequestError::Missing))
+        };
+
         Outcome::Success(VaultRequest {
-            bearer_token: bearer_token.to_string(),
-            host: host.to_string(),
-            timestamp: timestamp.to_string(),
-            signature: signature.to_string(),
+            signed_request: SignedRequest {
+                bearer_token: bearer_token.to_string(),
+                method: request.method().as_str().to_string(),
+                authority: host.to_string(),
+                path: request.uri().path().as_str().to_string(),
+                query: request.uri().query().map(|query| query.as_str()).unwrap_or_default().to_string(),
+                content_digest: content_digest.to_string(),
+                timestamp: timestamp.to_string(),
+                nonce: nonce.to_string(),
+                signature: signature.to_string(),
+            },
         })
     }
 }
@@ -70,6 +97,38 @@ impl<'r> FromRequest<'r> for OpaqueRequest {
 
         Outcome::Success(OpaqueRequest {
             username: username.to_string(),
+            client_ip: request.client_ip().map(|client_ip| client_ip.to_string()),
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LoginFinishRequest {
+    type Error = RequestError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(username) = request.headers().get_one(X_USERNAME) else {
+            return Outcome::Error((Status::BadRequest, RequestError::Missing));
+        };
+
+        let Some(login_id) = request.headers().get_one(X_LOGIN_ID) else {
+            return Outcome::Error((Status::BadRequest, RequestError::Missing));
+        };
+
+        let client_label = request.headers().get_one(X_CLIENT_LABEL);
+
+        Outcome::Success(LoginFinishRequest {
+            username: username.to_string(),
+            login_id: login_id.to_string(),
+            client_label: client_label.map(|client_label| client_label.to_string()),
         })
     }
 }
+
+// Enough room for a random value in hex or base64url, the nonce is kept in memory until the request expires.
+fn is_valid_nonce(nonce: &str) -> bool {
+    (NONCE_MIN_LENGTH..=NONCE_MAX_LENGTH).contains(&nonce.len())
+        && nonce
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || character == '-' || character == '_')
+}
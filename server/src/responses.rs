@@ -0,0 +1,163 @@
+use core_domain::authentication::{
+    login_finish::LoginFinish, login_start::LoginStart, opaque_parameters::OpaqueParameters,
+    session_refresh::SessionRefresh, session_summary::SessionSummary, two_factor_enrollment::TwoFactorEnrollment,
+};
+use rocket::{
+    Request, Response,
+    http::{Header, Status},
+    response::{self, Responder},
+};
+use serde::Serialize;
+
+const X_LOGIN_ID: &str = "X-Login-Id";
+const RETRY_AFTER: &str = "Retry-After";
+const X_CIPHER_SUITE: &str = "X-Cipher-Suite";
+const X_KSF_PARAMETERS: &str = "X-Ksf-Parameters";
+const X_SECOND_FACTOR: &str = "X-Second-Factor";
+const TOTP_SECOND_FACTOR: &str = "totp";
+
+#[derive(Responder)]
+pub struct LoginStartResponse {
+    pub message: Vec<u8>,
+    pub login_id: Header<'static>,
+    pub cipher_suite: Header<'static>,
+    pub ksf_parameters: Header<'static>,
+}
+
+pub struct LoginFinishResponse {
+    pub second_factor_required: bool,
+}
+
+pub struct ErrorResponse {
+    pub status: Status,
+    pub message: Vec<u8>,
+    pub retry_after: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParametersResponse {
+    pub cipher_suite: String,
+    pub ksf_parameters: String,
+    pub server_identity: Option<String>,
+    pub context: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionResponse {
+    pub session_id: String,
+    pub created_at: u64,
+    pub last_used_at: u64,
+    pub client_label: Option<String>,
+    pub current: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorEnrollmentResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountDeletionResponse {
+    pub deleted: Vec<String>,
+    pub revoked_sessions: usize,
+    pub backups_flagged_for_removal: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RefreshResponse {
+    pub salt: String,
+    pub previous_token_expires_at: u64,
+}
+
+impl From<OpaqueParameters> for ParametersResponse {
+    fn from(opaque_parameters: OpaqueParameters) -> Self {
+        Self {
+            cipher_suite: opaque_parameters.cipher_suite,
+            ksf_parameters: opaque_parameters.ksf_parameters,
+            server_identity: opaque_parameters.server_identity,
+            context: opaque_parameters.context,
+        }
+    }
+}
+
+impl From<SessionSummary> for SessionResponse {
+    fn from(session_summary: SessionSummary) -> Self {
+        Self {
+            session_id: session_summary.session_id,
+            created_at: session_summary.created_at,
+            last_used_at: session_summary.last_used_at,
+            client_label: session_summary.client_label,
+            current: session_summary.current,
+        }
+    }
+}
+
+impl From<SessionRefresh> for RefreshResponse {
+    fn from(session_refresh: SessionRefresh) -> Self {
+        Self {
+            salt: hex::encode(session_refresh.salt),
+            previous_token_expires_at: session_refresh.previous_token_expires_at,
+        }
+    }
+}
+
+impl From<TwoFactorEnrollment> for TwoFactorEnrollmentResponse {
+    fn from(two_factor_enrollment: TwoFactorEnrollment) -> Self {
+        Self {
+            secret: two_factor_enrollment.secret,
+            otpauth_uri: two_factor_enrollment.otpauth_uri,
+        }
+    }
+}
+
+impl From<LoginFinish> for LoginFinishResponse {
+    fn from(login_finish: LoginFinish) -> Self {
+        Self {
+            second_factor_required: login_finish.second_factor_required,
+        }
+    }
+}
+
+impl From<LoginStart> for LoginStartResponse {
+    fn from(login_start: LoginStart) -> Self {
+        Self {
+            message: login_start.message,
+            login_id: Header::new(X_LOGIN_ID, login_start.login_id),
+            cipher_suite: Header::new(X_CIPHER_SUITE, login_start.parameters.cipher_suite),
+            ksf_parameters: Header::new(X_KSF_PARAMETERS, login_start.parameters.ksf_parameters),
+        }
+    }
+}
+
+// The session exists either way, with a second factor it cannot reach the vault before `/account/2fa/verify`.
+impl<'r> Responder<'r, 'static> for LoginFinishResponse {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = Response::build();
+
+        if self.second_factor_required {
+            response.raw_header(X_SECOND_FACTOR, TOTP_SECOND_FACTOR);
+        }
+
+        response.ok()
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = Response::build_from(self.message.respond_to(request)?);
+
+        response.status(self.status);
+
+        if let Some(retry_after) = self.retry_after {
+            response.raw_header(RETRY_AFTER, retry_after.to_string());
+        }
+
+        response.ok()
+    }
+}
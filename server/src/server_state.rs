@@ -0,0 +1,34 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use authentication::opaque_authentication::OpaqueAuthentication;
+use core_domain::domain::server_domain::{Domain, ServerDomain};
+use file_storage::file_storage::StandardFileStorage;
+use session_store::file_session_store::FileSessionStore;
+use vault_store::directory_vault_store::DirectoryVaultStore;
+
+use crate::backup_removal::FileBackupRemovalList;
+
+pub type ServerState = Arc<
+    Mutex<
+        ServerDomain<
+            DirectoryVaultStore<StandardFileStorage>,
+            OpaqueAuthentication<StandardFileStorage, FileSessionStore>,
+            FileBackupRemovalList,
+        >,
+    >,
+>;
+
+pub async fn sweep_expired_sessions(server_state: ServerState, sweep_interval: u64) {
+    let mut interval = rocket::tokio::time::interval(Duration::from_secs(sweep_interval.max(1)));
+
+    loop {
+        interval.tick().await;
+
+        if let Err(error) = server_state.lock().unwrap().purge_expired_sessions() {
+            eprintln!("Error purging expired sessions: {error}");
+        }
+    }
+}
@@ -0,0 +1,3 @@
+mod admin_tests;
+mod config_tests;
+mod main_tests;
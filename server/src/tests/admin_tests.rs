@@ -0,0 +1,76 @@
+use std::{fs, path::Path};
+
+use core_domain::ports::file_storage::FileStorage;
+use file_storage::file_storage::StandardFileStorage;
+use tempfile::TempDir;
+
+use crate::{admin::run_admin_command, config::AppConfig};
+
+#[test]
+fn should_migrate_legacy_files_with_admin_command() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let vault_store_path = directory.path().join("vault_store");
+
+    fs::create_dir_all(&vault_store_path).unwrap();
+    fs::write(vault_store_path.join("Alice"), b"alice vault").unwrap();
+
+    let app_config = generate_app_config(directory.path());
+
+    // A-ct
+
+    let result = run_admin_command("migrate-legacy-files", &app_config);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    let vault_file_storage = StandardFileStorage::new(vault_store_path.to_str().unwrap().to_string());
+
+    assert_eq!(vault_file_storage.retrieve("alice").unwrap(), b"alice vault");
+    assert!(!vault_store_path.join("Alice").exists());
+}
+
+#[test]
+fn should_not_migrate_legacy_files_when_one_is_not_a_username() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let vault_store_path = directory.path().join("vault_store");
+    let password_file_path = directory.path().join("password_file");
+
+    fs::create_dir_all(&vault_store_path).unwrap();
+    fs::create_dir_all(&password_file_path).unwrap();
+    fs::write(vault_store_path.join("alice"), b"alice vault").unwrap();
+    fs::write(password_file_path.join("notes backup"), b"notes").unwrap();
+
+    let app_config = generate_app_config(directory.path());
+
+    // A-ct
+
+    let result = run_admin_command("migrate-legacy-files", &app_config);
+
+    // A-ssert
+
+    assert!(result.is_err());
+    assert!(vault_store_path.join("alice").exists());
+    assert!(password_file_path.join("notes backup").exists());
+}
+
+fn generate_app_config(directory: &Path) -> AppConfig {
+    let config_path = directory.join("config.yaml");
+
+    fs::write(
+        &config_path,
+        format!(
+            "server:\n  request_max_ttl: 5\nvault_store:\n  path: {:?}\npassword_file:\n  path: {:?}\ntwo_factor:\n  path: {:?}\n",
+            directory.join("vault_store").to_str().unwrap(),
+            directory.join("password_file").to_str().unwrap(),
+            directory.join("two_factor").to_str().unwrap(),
+        ),
+    )
+    .unwrap();
+
+    AppConfig::build(vec!["server".to_string(), config_path.to_str().unwrap().to_string()]).unwrap()
+}
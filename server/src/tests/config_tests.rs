@@ -0,0 +1,67 @@
+use std::fs;
+
+use tempfile::TempDir;
+
+use crate::config::AppConfig;
+
+#[test]
+fn should_load_config_written_before_new_sections() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let config_path = directory.path().join("config.yaml");
+
+    fs::write(
+        &config_path,
+        "server:\n  request_max_ttl: 5\nvault_store:\n  path: \"vault_store\"\npassword_file:\n  path: \"password_file\"\n",
+    )
+    .unwrap();
+
+    // A-ct
+
+    let result = AppConfig::build(vec!["server".to_string(), config_path.to_str().unwrap().to_string()]);
+
+    // A-ssert
+
+    let app_config = result.unwrap();
+
+    assert_eq!(app_config.server.request_max_ttl, 5);
+    assert_eq!(app_config.server.allowed_clock_skew, 30);
+    assert_eq!(app_config.server.identity, None);
+    assert_eq!(app_config.session.absolute_ttl, 43200);
+    assert_eq!(app_config.session.idle_ttl, 900);
+    assert_eq!(app_config.session.refresh_grace_period, 30);
+    assert_eq!(app_config.session.fresh_login_window, 300);
+    assert_eq!(app_config.session.store_path, "sessions/sessions");
+    assert_eq!(app_config.login.max_pending, 10);
+    assert_eq!(app_config.throttle.free_attempts, 3);
+    assert_eq!(app_config.opaque.cipher_suite, "ristretto255-sha512");
+    assert_eq!(app_config.server_key.path, "server_key/server_setup.key");
+}
+
+#[test]
+fn should_fill_missing_fields_of_a_section() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let config_path = directory.path().join("config.yaml");
+
+    fs::write(
+        &config_path,
+        "server:\n  request_max_ttl: 5\nvault_store:\n  path: \"vault_store\"\npassword_file:\n  path: \"password_file\"\nsession:\n  idle_ttl: 60\n",
+    )
+    .unwrap();
+
+    // A-ct
+
+    let result = AppConfig::build(vec!["server".to_string(), config_path.to_str().unwrap().to_string()]);
+
+    // A-ssert
+
+    let app_config = result.unwrap();
+
+    assert_eq!(app_config.session.idle_ttl, 60);
+    assert_eq!(app_config.session.absolute_ttl, 43200);
+    assert_eq!(app_config.session.sweep_interval, 60);
+    assert_eq!(app_config.session.store_path, "sessions/sessions");
+}
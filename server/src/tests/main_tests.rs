@@ -0,0 +1,1142 @@
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use authentication::{
+    login_throttle::{LoginThrottle, ThrottlePolicy},
+    opaque_authentication::{HmacSha512, OpaqueAuthentication, StandardCipherSuite},
+    pending_login::LoginPolicy,
+    request_signature::{RequestPolicy, content_digest, signature_base},
+    server_key::ServerKeyring,
+    session::SessionPolicy,
+};
+use core_domain::{
+    authentication::signed_request::SignedRequest,
+    domain::{password_change::PasswordChange, recovery_setup::RecoverySetup, server_domain::ServerDomain},
+    ports::file_storage::FileStorage,
+};
+use file_storage::file_storage::{StandardFileStorage, stored_file_name};
+use session_store::{file_session_store::FileSessionStore, session_store_key::load_or_create_session_store_key};
+use hkdf::Hkdf;
+use hmac::Mac;
+use opaque_ke::{
+    ClientLogin, ClientLoginFinishParameters, ClientRegistration,
+    ClientRegistrationFinishParameters, CredentialResponse, RegistrationResponse,
+    ServerRegistration, ServerSetup,
+    rand::{RngCore, rngs::OsRng},
+};
+use rocket::{
+    http::{ContentType, Header, Status},
+    local::blocking::{Client, LocalRequest},
+    serde::json::Value,
+};
+use sha2::Sha512;
+use tempfile::TempDir;
+use totp_rs::{Algorithm, Secret, TOTP};
+use vault_store::directory_vault_store::DirectoryVaultStore;
+
+use crate::{backup_removal::FileBackupRemovalList, build_rocket, server_state::ServerState};
+
+const X_USERNAME: &str = "X-Username";
+const X_LOGIN_ID: &str = "X-Login-Id";
+const X_CIPHER_SUITE: &str = "X-Cipher-Suite";
+const X_KSF_PARAMETERS: &str = "X-Ksf-Parameters";
+const X_SECOND_FACTOR: &str = "X-Second-Factor";
+const HOST: &str = "localhost";
+const VAULTS_DIRECTORY: &str = "vaults";
+const PASSWORD_FILES_DIRECTORY: &str = "password_files";
+const SESSIONS_FILE: &str = "sessions";
+const SESSION_STORE_KEY_FILE: &str = "session_store.key";
+const TWO_FACTOR_DIRECTORY: &str = "two_factor";
+const BACKUP_REMOVAL_LIST_FILE: &str = "backups/removal.list";
+
+#[test]
+fn should_register_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    // A-ct
+
+    let status = register(&client, "username", "password");
+
+    // A-ssert
+
+    assert_eq!(status, Status::Ok);
+    assert!(directory.path().join(PASSWORD_FILES_DIRECTORY).join(stored_file_name("username")).exists());
+    assert!(directory.path().join(VAULTS_DIRECTORY).join(stored_file_name("username")).exists());
+}
+
+#[test]
+fn should_canonicalise_username_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "Alice", "password");
+
+    let mut client_rng = OsRng;
+
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, b"password").unwrap();
+
+    // A-ct
+
+    let session_key = login(&client, "ALICE", "password");
+
+    let same_account_status = register(&client, "alice", "other password");
+
+    let invalid_username_response = client
+        .post("/opaque/registration/start")
+        .header(ContentType::Binary)
+        .header(Header::new(X_USERNAME, "../alice"))
+        .body(client_registration_start_result.message.serialize())
+        .dispatch();
+
+    // A-ssert
+
+    let retrieve_response = sign(client.get("/vault"), "GET", "/vault", b"", &session_key).dispatch();
+
+    assert_eq!(retrieve_response.status(), Status::Ok);
+    assert_eq!(same_account_status, Status::Conflict);
+    assert_eq!(invalid_username_response.status(), Status::BadRequest);
+    assert!(directory.path().join(PASSWORD_FILES_DIRECTORY).join(stored_file_name("alice")).exists());
+}
+
+#[test]
+fn should_start_registration_for_existing_account_like_new_one_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "password");
+
+    let mut client_rng = OsRng;
+
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, b"attacker password")
+            .unwrap();
+
+    // A-ct
+
+    let existing_response = client
+        .post("/opaque/registration/start")
+        .header(ContentType::Binary)
+        .header(Header::new(X_USERNAME, "username"))
+        .body(client_registration_start_result.message.serialize())
+        .dispatch();
+
+    let new_response = client
+        .post("/opaque/registration/start")
+        .header(ContentType::Binary)
+        .header(Header::new(X_USERNAME, "other_username"))
+        .body(client_registration_start_result.message.serialize())
+        .dispatch();
+
+    // A-ssert
+
+    assert_eq!(existing_response.status(), Status::Ok);
+    assert_eq!(new_response.status(), Status::Ok);
+}
+
+#[test]
+fn should_not_overwrite_existing_account_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "password");
+
+    let vault_path = directory.path().join(VAULTS_DIRECTORY).join(stored_file_name("username"));
+    let password_file_path = directory.path().join(PASSWORD_FILES_DIRECTORY).join(stored_file_name("username"));
+
+    fs::write(&vault_path, b"encrypted vault").unwrap();
+
+    let original_password_file = fs::read(&password_file_path).unwrap();
+
+    let mut client_rng = OsRng;
+
+    let attacker_server_setup = ServerSetup::<StandardCipherSuite>::new(&mut client_rng);
+
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, b"attacker password")
+            .unwrap();
+
+    let server_registration_start_result = ServerRegistration::<StandardCipherSuite>::start(
+        &attacker_server_setup,
+        client_registration_start_result.message,
+        b"username",
+    )
+    .unwrap();
+
+    let client_finish_registration_result = client_registration_start_result
+        .state
+        .finish(
+            &mut client_rng,
+            b"attacker password",
+            server_registration_start_result.message,
+            ClientRegistrationFinishParameters::default(),
+        )
+        .unwrap();
+
+    // A-ct
+
+    let response = client
+        .post("/opaque/registration/finish")
+        .header(ContentType::Binary)
+        .header(Header::new(X_USERNAME, "username"))
+        .body(client_finish_registration_result.message.serialize())
+        .dispatch();
+
+    // A-ssert
+
+    assert_eq!(response.status(), Status::Conflict);
+    assert_eq!(fs::read(&vault_path).unwrap(), b"encrypted vault");
+    assert_eq!(fs::read(&password_file_path).unwrap(), original_password_file);
+}
+
+#[test]
+fn should_change_password_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "old password");
+
+    let session_key = login(&client, "username", "old password");
+    let other_session_key = login(&client, "username", "old password");
+
+    let mut client_rng = OsRng;
+
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, b"new password").unwrap();
+
+    let server_registration_start_result = sign(
+        client.post("/account/password/start"),
+        "POST",
+        "/account/password/start",
+        &client_registration_start_result.message.serialize(),
+        &session_key,
+    )
+    .header(ContentType::Binary)
+    .dispatch()
+    .into_bytes()
+    .unwrap();
+
+    let client_finish_registration_result = client_registration_start_result
+        .state
+        .finish(
+            &mut client_rng,
+            b"new password",
+            RegistrationResponse::deserialize(&server_registration_start_result).unwrap(),
+            ClientRegistrationFinishParameters::default(),
+        )
+        .unwrap();
+
+    let registration_message = client_finish_registration_result.message.serialize();
+
+    let body = PasswordChange {
+        registration_message: registration_message.to_vec(),
+        vault: b"re-encrypted vault".to_vec(),
+    }
+    .serialize();
+
+    // A-ct
+
+    let response = sign(
+        client.post("/account/password/finish"),
+        "POST",
+        "/account/password/finish",
+        &body,
+        &session_key,
+    )
+    .header(ContentType::Binary)
+    .dispatch();
+
+    // A-ssert
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string().unwrap(), "1");
+    assert_eq!(
+        fs::read(directory.path().join(VAULTS_DIRECTORY).join(stored_file_name("username"))).unwrap(),
+        b"re-encrypted vault"
+    );
+
+    let revoked_session_response = sign(client.get("/vault"), "GET", "/vault", b"", &other_session_key).dispatch();
+
+    assert_eq!(revoked_session_response.status(), Status::Forbidden);
+
+    login(&client, "username", "new password");
+}
+
+#[test]
+fn should_not_finish_password_change_with_malformed_body_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "password");
+
+    let session_key = login(&client, "username", "password");
+
+    // A-ct
+
+    let response = sign(
+        client.post("/account/password/finish"),
+        "POST",
+        "/account/password/finish",
+        &[0, 0, 1, 0, 42],
+        &session_key,
+    )
+    .header(ContentType::Binary)
+    .dispatch();
+
+    // A-ssert
+
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn should_answer_login_start_for_unknown_user_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    let mut client_rng = OsRng;
+
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(&mut client_rng, b"password").unwrap();
+
+    // A-ct
+
+    let response = client
+        .post("/opaque/login/start")
+        .header(ContentType::Binary)
+        .header(Header::new(X_USERNAME, "unknown"))
+        .body(client_login_start_result.message.serialize())
+        .dispatch();
+
+    // A-ssert
+
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response.headers().get_one(X_LOGIN_ID).is_some());
+    assert!(CredentialResponse::<StandardCipherSuite>::deserialize(&response.into_bytes().unwrap()).is_ok());
+}
+
+#[test]
+fn should_throttle_failed_logins_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    for _ in 0..4 {
+        failed_login(&client, "unknown");
+    }
+
+    let mut client_rng = OsRng;
+
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(&mut client_rng, b"password").unwrap();
+
+    // A-ct
+
+    let response = client
+        .post("/opaque/login/start")
+        .header(ContentType::Binary)
+        .header(Header::new(X_USERNAME, "unknown"))
+        .body(client_login_start_result.message.serialize())
+        .dispatch();
+
+    // A-ssert
+
+    assert_eq!(response.status(), Status::TooManyRequests);
+    assert_eq!(response.headers().get_one("Retry-After"), Some("60"));
+}
+
+#[test]
+fn should_save_and_retrieve_vault_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "password");
+
+    let session_key = login(&client, "username", "password");
+
+    // A-ct
+
+    let save_response = sign(client.post("/vault"), "POST", "/vault", b"encrypted vault", &session_key)
+        .header(ContentType::Binary)
+        .dispatch();
+
+    let retrieve_response = sign(client.get("/vault"), "GET", "/vault", b"", &session_key).dispatch();
+
+    // A-ssert
+
+    assert_eq!(save_response.status(), Status::Ok);
+    assert_eq!(retrieve_response.status(), Status::Ok);
+    assert_eq!(retrieve_response.into_bytes().unwrap(), b"encrypted vault");
+}
+
+#[test]
+fn should_keep_session_across_restart_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "password");
+
+    let session_key = login(&client, "username", "password");
+
+    drop(client);
+
+    // A-ct
+
+    let restarted_client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    let response = sign(restarted_client.post("/vault"), "POST", "/vault", b"encrypted vault", &session_key)
+        .header(ContentType::Binary)
+        .dispatch();
+
+    // A-ssert
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let sessions_file = fs::read(directory.path().join(SESSIONS_FILE)).unwrap();
+    let hex_session_key = hex::encode(&session_key);
+
+    assert!(!sessions_file.windows(hex_session_key.len()).any(|window| window == hex_session_key.as_bytes()));
+    assert!(!sessions_file.windows(session_key.len()).any(|window| window == session_key.as_slice()));
+}
+
+#[test]
+fn should_refresh_session_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "password");
+
+    let session_key = login(&client, "username", "password");
+
+    // A-ct
+
+    let response = sign(client.post("/session/refresh"), "POST", "/session/refresh", b"", &session_key).dispatch();
+
+    // A-ssert
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let session_refresh = response.into_json::<Value>().unwrap();
+    let salt = hex::decode(session_refresh["salt"].as_str().unwrap()).unwrap();
+
+    let mut refreshed_session_key = vec![0u8; 64];
+
+    Hkdf::<Sha512>::new(Some(&salt), &session_key)
+        .expand(b"opaque-session-refresh", &mut refreshed_session_key)
+        .unwrap();
+
+    let refreshed_response = sign(client.post("/vault"), "POST", "/vault", b"encrypted vault", &refreshed_session_key)
+        .header(ContentType::Binary)
+        .dispatch();
+    let previous_response = sign(client.post("/vault"), "POST", "/vault", b"encrypted vault", &session_key)
+        .header(ContentType::Binary)
+        .dispatch();
+    let second_refresh_response =
+        sign(client.post("/session/refresh"), "POST", "/session/refresh", b"", &session_key).dispatch();
+
+    assert!(session_refresh["previous_token_expires_at"].as_u64().unwrap().abs_diff(current_timestamp() + 30) <= 1);
+    assert_eq!(refreshed_response.status(), Status::Ok);
+    assert_eq!(previous_response.status(), Status::Ok);
+    assert_eq!(second_refresh_response.status(), Status::Forbidden);
+}
+
+#[test]
+fn should_require_second_factor_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "password");
+
+    let session_key = login(&client, "username", "password");
+
+    let two_factor_enrollment = sign(client.post("/account/2fa/enroll"), "POST", "/account/2fa/enroll", b"", &session_key)
+        .dispatch()
+        .into_json::<Value>()
+        .unwrap();
+    let secret = two_factor_enrollment["secret"].as_str().unwrap();
+
+    // Codes are single use, the confirmation takes the previous step so the step-up below can take the current one.
+    let confirmation_code = generate_totp_code(secret, current_timestamp() - 30);
+    let confirm_response = sign(client.post("/account/2fa/confirm"), "POST", "/account/2fa/confirm", confirmation_code.as_bytes(), &session_key)
+        .header(ContentType::Binary)
+        .dispatch();
+
+    assert_eq!(confirm_response.status(), Status::Ok);
+    assert_eq!(confirm_response.into_json::<Value>().unwrap()["recovery_codes"].as_array().unwrap().len(), 10);
+
+    // A-ct
+
+    let (pending_session_key, second_factor) = login_with_second_factor(&client, "username", "password");
+
+    let pending_response = sign(client.post("/vault"), "POST", "/vault", b"encrypted vault", &pending_session_key)
+        .header(ContentType::Binary)
+        .dispatch();
+
+    let code = generate_totp_code(secret, current_timestamp());
+    let verify_response = sign(client.post("/account/2fa/verify"), "POST", "/account/2fa/verify", code.as_bytes(), &pending_session_key)
+        .header(ContentType::Binary)
+        .dispatch();
+
+    let verified_response = sign(client.post("/vault"), "POST", "/vault", b"encrypted vault", &pending_session_key)
+        .header(ContentType::Binary)
+        .dispatch();
+
+    // A-ssert
+
+    assert_eq!(second_factor.as_deref(), Some("totp"));
+    assert_eq!(pending_response.status(), Status::Unauthorized);
+    assert_eq!(verify_response.status(), Status::Ok);
+    assert_eq!(verified_response.status(), Status::Ok);
+}
+
+#[test]
+fn should_throttle_wrong_second_factor_codes_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "password");
+
+    let session_key = login(&client, "username", "password");
+
+    let two_factor_enrollment = sign(client.post("/account/2fa/enroll"), "POST", "/account/2fa/enroll", b"", &session_key)
+        .dispatch()
+        .into_json::<Value>()
+        .unwrap();
+    let secret = two_factor_enrollment["secret"].as_str().unwrap();
+
+    let confirmation_code = generate_totp_code(secret, current_timestamp());
+    sign(client.post("/account/2fa/confirm"), "POST", "/account/2fa/confirm", confirmation_code.as_bytes(), &session_key)
+        .header(ContentType::Binary)
+        .dispatch();
+
+    let (pending_session_key, _) = login_with_second_factor(&client, "username", "password");
+
+    for _ in 0..4 {
+        sign(client.post("/account/2fa/verify"), "POST", "/account/2fa/verify", b"wrong", &pending_session_key)
+            .header(ContentType::Binary)
+            .dispatch();
+    }
+
+    // A-ct
+
+    let response = sign(client.post("/account/2fa/verify"), "POST", "/account/2fa/verify", b"wrong", &pending_session_key)
+        .header(ContentType::Binary)
+        .dispatch();
+
+    // A-ssert
+
+    assert_eq!(response.status(), Status::TooManyRequests);
+    assert_eq!(response.headers().get_one("Retry-After"), Some("60"));
+}
+
+#[test]
+fn should_reset_password_with_recovery_credential_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "forgotten password");
+
+    let session_key = login(&client, "username", "forgotten password");
+
+    let body = RecoverySetup {
+        registration_message: signed_registration(&client, "/account/recovery/start", "recovery secret", &session_key),
+        wrapped_key: b"wrapped vault key".to_vec(),
+    }
+    .serialize();
+
+    let setup_response = sign(client.post("/account/recovery/finish"), "POST", "/account/recovery/finish", &body, &session_key)
+        .header(ContentType::Binary)
+        .dispatch();
+
+    assert_eq!(setup_response.status(), Status::Ok);
+
+    // A-ct
+
+    let recovery_session_key = recovery_login(&client, "username", "recovery secret");
+
+    let save_vault_response = sign(client.post("/vault"), "POST", "/vault", b"encrypted vault", &recovery_session_key)
+        .header(ContentType::Binary)
+        .dispatch();
+
+    let recovery_key_response =
+        sign(client.get("/account/recovery/key"), "GET", "/account/recovery/key", b"", &recovery_session_key).dispatch();
+
+    let body = PasswordChange {
+        registration_message: signed_registration(&client, "/account/recovery/reset/start", "new password", &recovery_session_key),
+        vault: b"re-encrypted vault".to_vec(),
+    }
+    .serialize();
+
+    let reset_response = sign(client.post("/account/recovery/reset/finish"), "POST", "/account/recovery/reset/finish", &body, &recovery_session_key)
+        .header(ContentType::Binary)
+        .dispatch();
+
+    // A-ssert
+
+    assert_eq!(save_vault_response.status(), Status::Forbidden);
+    assert_eq!(recovery_key_response.status(), Status::Ok);
+    assert_eq!(recovery_key_response.into_bytes().unwrap(), b"wrapped vault key");
+    assert_eq!(reset_response.status(), Status::Ok);
+    assert_eq!(reset_response.into_string().unwrap(), "2");
+
+    let revoked_session_response = sign(client.get("/vault"), "GET", "/vault", b"", &session_key).dispatch();
+
+    assert_eq!(revoked_session_response.status(), Status::Forbidden);
+
+    let new_session_key = login(&client, "username", "new password");
+
+    let vault_response = sign(client.get("/vault"), "GET", "/vault", b"", &new_session_key).dispatch();
+
+    assert_eq!(vault_response.into_bytes().unwrap(), b"re-encrypted vault");
+}
+
+#[test]
+fn should_delete_account_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "password");
+
+    let session_key = login(&client, "username", "password");
+
+    sign(client.post("/vault"), "POST", "/vault", b"encrypted vault", &session_key)
+        .header(ContentType::Binary)
+        .dispatch();
+
+    // A-ct
+
+    let response = sign(client.delete("/account"), "DELETE", "/account", b"", &session_key).dispatch();
+
+    // A-ssert
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let account_deletion = response.into_json::<Value>().unwrap();
+
+    assert_eq!(
+        account_deletion["deleted"].as_array().unwrap(),
+        &vec![Value::from("password_file"), Value::from("vault")]
+    );
+    assert_eq!(account_deletion["revoked_sessions"], 1);
+    assert_eq!(account_deletion["backups_flagged_for_removal"], true);
+
+    let retrieve_response = sign(client.get("/vault"), "GET", "/vault", b"", &session_key).dispatch();
+
+    assert_eq!(retrieve_response.status(), Status::Forbidden);
+    assert!(!directory.path().join(VAULTS_DIRECTORY).join(stored_file_name("username")).exists());
+
+    let backup_removal_list = fs::read_to_string(directory.path().join(BACKUP_REMOVAL_LIST_FILE)).unwrap();
+
+    assert!(backup_removal_list.ends_with(&format!(
+        " {} {}\n",
+        stored_file_name("username"),
+        stored_file_name("username.recovery")
+    )));
+    assert!(!backup_removal_list.contains("username"));
+    assert_eq!(register(&client, "username", "password"), Status::Ok);
+}
+
+#[test]
+fn should_not_save_vault_with_swapped_body_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "password");
+
+    let session_key = login(&client, "username", "password");
+
+    // A-ct
+
+    let response = sign(client.post("/vault"), "POST", "/vault", b"encrypted vault", &session_key)
+        .header(ContentType::Binary)
+        .body(b"attacker vault")
+        .dispatch();
+
+    // A-ssert
+
+    assert_eq!(response.status(), Status::Forbidden);
+    assert_eq!(fs::read(directory.path().join(VAULTS_DIRECTORY).join(stored_file_name("username"))).unwrap(), b"");
+}
+
+#[test]
+fn should_not_accept_signature_for_another_query_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "password");
+
+    let session_key = login(&client, "username", "password");
+
+    // A-ct
+
+    let response = sign(client.get("/vault?other=query"), "GET", "/vault", b"", &session_key).dispatch();
+
+    // A-ssert
+
+    assert_eq!(response.status(), Status::Forbidden);
+}
+
+#[test]
+fn should_not_replay_signed_request_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "password");
+
+    let session_key = login(&client, "username", "password");
+
+    let request = sign(client.post("/vault"), "POST", "/vault", b"encrypted vault", &session_key)
+        .header(ContentType::Binary);
+
+    let first_response = request.clone().dispatch();
+
+    // A-ct
+
+    let replayed_response = request.dispatch();
+
+    // A-ssert
+
+    assert_eq!(first_response.status(), Status::Ok);
+    assert_eq!(replayed_response.status(), Status::Conflict);
+}
+
+#[test]
+fn should_not_accept_invalid_nonce_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "password");
+
+    let session_key = login(&client, "username", "password");
+
+    let mut request = sign(client.get("/vault"), "GET", "/vault", b"", &session_key);
+    request.replace_header(Header::new("X-Nonce", "short"));
+
+    // A-ct
+
+    let response = request.dispatch();
+
+    // A-ssert
+
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn should_give_server_time_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    let before = current_timestamp();
+
+    // A-ct
+
+    let response = client.get("/time").dispatch();
+
+    // A-ssert
+
+    let after = current_timestamp();
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let server_time: u64 = response.into_string().unwrap().parse().unwrap();
+
+    assert!((before..=after).contains(&server_time));
+}
+
+#[test]
+fn should_give_opaque_parameters_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    // A-ct
+
+    let response = client.get("/opaque/parameters").dispatch();
+
+    // A-ssert
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_string().unwrap(),
+        r#"{"cipher_suite":"ristretto255-sha512","ksf_parameters":"m=19456,t=2,p=1","server_identity":null,"context":null}"#
+    );
+}
+
+#[test]
+fn should_give_opaque_parameters_on_login_start_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    let mut client_rng = OsRng;
+
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(&mut client_rng, b"password").unwrap();
+
+    // A-ct
+
+    let response = client
+        .post("/opaque/login/start")
+        .header(ContentType::Binary)
+        .header(Header::new(X_USERNAME, "unknown"))
+        .body(client_login_start_result.message.serialize())
+        .dispatch();
+
+    // A-ssert
+
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.headers().get_one(X_CIPHER_SUITE), Some("ristretto255-sha512"));
+    assert_eq!(response.headers().get_one(X_KSF_PARAMETERS), Some("m=19456,t=2,p=1"));
+}
+
+#[test]
+fn should_accept_request_within_clock_skew_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "password");
+
+    let session_key = login(&client, "username", "password");
+
+    // A-ct
+
+    let ahead_response =
+        sign_at(client.get("/vault"), "GET", "/vault", b"", &session_key, current_timestamp() + 20).dispatch();
+
+    let behind_response =
+        sign_at(client.get("/vault"), "GET", "/vault", b"", &session_key, current_timestamp() - 20).dispatch();
+
+    // A-ssert
+
+    assert_eq!(ahead_response.status(), Status::Ok);
+    assert_eq!(behind_response.status(), Status::Ok);
+}
+
+#[test]
+fn should_not_accept_future_dated_request_through_http() {
+    // A-rrange
+
+    let directory = generate_directory();
+    let client = Client::tracked(build_rocket(generate_server_state(&directory))).unwrap();
+
+    register(&client, "username", "password");
+
+    let session_key = login(&client, "username", "password");
+
+    // A-ct
+
+    let response =
+        sign_at(client.get("/vault"), "GET", "/vault", b"", &session_key, current_timestamp() + 3_600).dispatch();
+
+    // A-ssert
+
+    assert_eq!(response.status(), Status::PreconditionFailed);
+    assert!(response.into_string().unwrap().contains("server time"));
+}
+
+fn generate_directory() -> TempDir {
+    let directory = TempDir::new().unwrap();
+
+    fs::create_dir(directory.path().join(VAULTS_DIRECTORY)).unwrap();
+    fs::create_dir(directory.path().join(PASSWORD_FILES_DIRECTORY)).unwrap();
+    fs::create_dir(directory.path().join(TWO_FACTOR_DIRECTORY)).unwrap();
+
+    directory
+}
+
+fn generate_server_state(directory: &TempDir) -> ServerState {
+    let mut rng = OsRng;
+
+    let vault_store = DirectoryVaultStore::new(StandardFileStorage::new(path_to_string(
+        &directory.path().join(VAULTS_DIRECTORY),
+    )));
+
+    let session_store_key =
+        load_or_create_session_store_key(&path_to_string(&directory.path().join(SESSION_STORE_KEY_FILE))).unwrap();
+
+    let session_store =
+        FileSessionStore::load(&path_to_string(&directory.path().join(SESSIONS_FILE)), session_store_key).unwrap();
+
+    let authentication = OpaqueAuthentication::new(
+        StandardFileStorage::new(path_to_string(&directory.path().join(PASSWORD_FILES_DIRECTORY))),
+        session_store,
+        ServerKeyring::new(ServerSetup::<StandardCipherSuite>::new(&mut rng).into()),
+        RequestPolicy::new(5, 30),
+        SessionPolicy::new(3600, 900, 30, 300),
+        LoginPolicy::new(30, 100),
+        LoginThrottle::new(ThrottlePolicy::new(3, 60, 300, 10, 900)),
+    ).unwrap()
+    .with_two_factor(
+        StandardFileStorage::new(path_to_string(&directory.path().join(TWO_FACTOR_DIRECTORY))),
+        "Vault",
+    );
+
+    let backup_removal_list =
+        FileBackupRemovalList::new(path_to_string(&directory.path().join(BACKUP_REMOVAL_LIST_FILE)));
+
+    Arc::new(Mutex::new(ServerDomain::new(vault_store, authentication, backup_removal_list)))
+}
+
+fn path_to_string(path: &Path) -> String {
+    path.to_str().unwrap().to_string()
+}
+
+fn register(client: &Client, username: &str, password: &str) -> Status {
+    let mut client_rng = OsRng;
+
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes())
+            .unwrap();
+
+    let response = client
+        .post("/opaque/registration/start")
+        .header(ContentType::Binary)
+        .header(Header::new(X_USERNAME, username.to_string()))
+        .body(client_registration_start_result.message.serialize())
+        .dispatch();
+
+    let server_registration_start_result = response.into_bytes().unwrap();
+
+    let client_finish_registration_result = client_registration_start_result
+        .state
+        .finish(
+            &mut client_rng,
+            password.as_bytes(),
+            RegistrationResponse::deserialize(&server_registration_start_result).unwrap(),
+            ClientRegistrationFinishParameters::default(),
+        )
+        .unwrap();
+
+    client
+        .post("/opaque/registration/finish")
+        .header(ContentType::Binary)
+        .header(Header::new(X_USERNAME, username.to_string()))
+        .body(client_finish_registration_result.message.serialize())
+        .dispatch()
+        .status()
+}
+
+fn login(client: &Client, username: &str, password: &str) -> Vec<u8> {
+    login_with_second_factor(client, username, password).0
+}
+
+// Also gives back the `X-Second-Factor` header of the login finish response.
+fn login_with_second_factor(client: &Client, username: &str, password: &str) -> (Vec<u8>, Option<String>) {
+    login_at(client, "/opaque", username, password)
+}
+
+fn recovery_login(client: &Client, username: &str, recovery_secret: &str) -> Vec<u8> {
+    login_at(client, "/opaque/recovery", username, recovery_secret).0
+}
+
+fn login_at(client: &Client, path_prefix: &str, username: &str, password: &str) -> (Vec<u8>, Option<String>) {
+    let mut client_rng = OsRng;
+
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes()).unwrap();
+
+    let response = client
+        .post(format!("{}/login/start", path_prefix))
+        .header(ContentType::Binary)
+        .header(Header::new(X_USERNAME, username.to_string()))
+        .body(client_login_start_result.message.serialize())
+        .dispatch();
+
+    let login_id = response.headers().get_one(X_LOGIN_ID).unwrap().to_string();
+    let server_login_start_result = response.into_bytes().unwrap();
+
+    let client_login_finish_result = client_login_start_result
+        .state
+        .finish(
+            &mut client_rng,
+            password.as_bytes(),
+            CredentialResponse::<StandardCipherSuite>::deserialize(&server_login_start_result).unwrap(),
+            ClientLoginFinishParameters::default(),
+        )
+        .unwrap();
+
+    let response = client
+        .post(format!("{}/login/finish", path_prefix))
+        .header(ContentType::Binary)
+        .header(Header::new(X_USERNAME, username.to_string()))
+        .header(Header::new(X_LOGIN_ID, login_id))
+        .body(client_login_finish_result.message.serialize())
+        .dispatch();
+
+    assert_eq!(response.status(), Status::Ok);
+
+    let second_factor = response.headers().get_one(X_SECOND_FACTOR).map(str::to_string);
+
+    (client_login_finish_result.session_key.to_vec(), second_factor)
+}
+
+// Runs a signed OPAQUE registration start and gives back the client's registration upload.
+fn signed_registration(client: &Client, start_path: &str, password: &str, session_key: &[u8]) -> Vec<u8> {
+    let mut client_rng = OsRng;
+
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes()).unwrap();
+
+    let server_registration_start_result = sign(
+        client.post(start_path.to_string()),
+        "POST",
+        start_path,
+        &client_registration_start_result.message.serialize(),
+        session_key,
+    )
+    .header(ContentType::Binary)
+    .dispatch()
+    .into_bytes()
+    .unwrap();
+
+    client_registration_start_result
+        .state
+        .finish(
+            &mut client_rng,
+            password.as_bytes(),
+            RegistrationResponse::deserialize(&server_registration_start_result).unwrap(),
+            ClientRegistrationFinishParameters::default(),
+        )
+        .unwrap()
+        .message
+        .serialize()
+        .to_vec()
+}
+
+fn generate_totp_code(secret: &str, time: u64) -> String {
+    let secret = Secret::Encoded(secret.to_string()).to_bytes().unwrap();
+
+    TOTP::new(Algorithm::SHA1, 6, 0, 30, secret, None, String::new())
+        .unwrap()
+        .generate(time)
+}
+
+fn sign<'c>(request: LocalRequest<'c>, method: &str, path: &str, body: &[u8], session_key: &[u8]) -> LocalRequest<'c> {
+    sign_at(request, method, path, body, session_key, current_timestamp())
+}
+
+fn sign_at<'c>(
+    request: LocalRequest<'c>,
+    method: &str,
+    path: &str,
+    body: &[u8],
+    session_key: &[u8],
+    timestamp: u64,
+) -> LocalRequest<'c> {
+    let hkdf = Hkdf::<Sha512>::from_prk(session_key).unwrap();
+
+    let mut token = vec![0u8; 64];
+
+    hkdf.expand(b"opaque-session-token", &mut token).unwrap();
+
+    let signed_request = SignedRequest {
+        bearer_token: hex::encode(token),
+        method: method.to_string(),
+        authority: HOST.to_string(),
+        path: path.to_string(),
+        query: String::new(),
+        content_digest: content_digest(body),
+        timestamp: timestamp.to_string(),
+        nonce: generate_nonce(),
+        signature: String::new(),
+    };
+
+    let mut mac = HmacSha512::new_from_slice(session_key).unwrap();
+
+    mac.update(signature_base(&signed_request).as_bytes());
+
+    request
+        .header(Header::new("Host", HOST))
+        .header(Header::new("Authorization", format!("Bearer {}", signed_request.bearer_token)))
+        .header(Header::new("X-Timestamp", signed_request.timestamp))
+        .header(Header::new("Content-Digest", signed_request.content_digest))
+        .header(Header::new("X-Nonce", signed_request.nonce))
+        .header(Header::new("X-Signature", hex::encode(mac.finalize().into_bytes())))
+        .body(body)
+}
+
+fn current_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+fn generate_nonce() -> String {
+    let mut nonce = [0u8; 16];
+
+    OsRng.fill_bytes(&mut nonce);
+
+    hex::encode(nonce)
+}
+
+fn failed_login(client: &Client, username: &str) {
+    let mut client_rng = OsRng;
+
+    let client_login_start_result =
+        ClientLogin::<StandardCipherSuite>::start(&mut client_rng, b"password").unwrap();
+
+    let response = client
+        .post("/opaque/login/start")
+        .header(ContentType::Binary)
+        .header(Header::new(X_USERNAME, username.to_string()))
+        .body(client_login_start_result.message.serialize())
+        .dispatch();
+
+    let login_id = response.headers().get_one(X_LOGIN_ID).unwrap().to_string();
+
+    let status = client
+        .post("/opaque/login/finish")
+        .header(ContentType::Binary)
+        .header(Header::new(X_USERNAME, username.to_string()))
+        .header(Header::new(X_LOGIN_ID, login_id))
+        .body(vec![0; 64])
+        .dispatch()
+        .status();
+
+    assert_ne!(status, Status::Ok);
+}
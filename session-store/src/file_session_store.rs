@@ -0,0 +1,331 @@
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::Path,
+};
+
+use core_domain::{
+    ports::session_store::SessionStore,
+    session_store::{
+        session::Session,
+        session_store_error::{Result, SessionStoreError},
+    },
+};
+
+use crate::{memory_session_store::removed_token_hashes, session_store_key::SessionStoreKey};
+
+const SESSION_STORE_MAGIC: &[u8; 4] = b"FVSS";
+const SESSION_STORE_FORMAT_VERSION: u8 = 1;
+const SESSION_STORE_HEADER_LENGTH: usize = SESSION_STORE_MAGIC.len() + 1;
+const TEMPORARY_EXTENSION: &str = "tmp";
+const NO_CLIENT_LABEL: &str = "-";
+const NOT_RETIRED: &str = "-";
+const SECOND_FACTOR_VERIFIED: &str = "0";
+const SECOND_FACTOR_PENDING: &str = "1";
+const LOGIN_SESSION: &str = "0";
+const RECOVERY_SESSION: &str = "1";
+
+#[cfg(unix)]
+const SESSION_STORE_FILE_MODE: u32 = 0o600;
+
+const NOT_A_SESSION_STORE_FILE: &str = "File is not a session store file (wrong magic bytes).";
+const UNSUPPORTED_FORMAT_VERSION: &str = "Unsupported session store file format version.";
+const CORRUPT_SESSION: &str = "Session store file holds a malformed session.";
+
+// Every change rewrites the whole file, sealed with the session store key: the session keys are secret,
+// and the usernames must not be swapped to take over another account.
+// A save that only moves `last_used_at` stays in memory until the next `retain`, the periodic sweep,
+// so signed requests do not rewrite the file. A crash loses that much idle time, never a session.
+pub struct FileSessionStore {
+    path: String,
+    session_store_key: SessionStoreKey,
+    sessions: HashMap<String, Session>,
+    unsaved_last_use: bool,
+}
+
+impl FileSessionStore {
+    pub fn load(path: &str, session_store_key: SessionStoreKey) -> Result<Self> {
+        let sessions = match fs::read(path) {
+            Ok(content) => decode_sessions(&session_store_key, &content)
+                .map_err(|message| SessionStoreError::Corrupt(format!("{} ({})", message, path)))?,
+            Err(error) if error.kind() == ErrorKind::NotFound => HashMap::new(),
+            Err(error) => return Err(SessionStoreError::ReadingFile(format!("{} ({})", error, path))),
+        };
+
+        Ok(Self {
+            path: path.to_string(),
+            session_store_key,
+            sessions,
+            unsaved_last_use: false,
+        })
+    }
+
+    fn persist(&mut self) -> Result<()> {
+        let path = &self.path;
+
+        if let Some(parent) = Path::new(path).parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent).map_err(|error| write_error(path, &error.to_string()))?;
+        }
+
+        let content = encode_sessions(&self.session_store_key, &self.sessions)?;
+        let temporary_path = Path::new(path).with_extension(TEMPORARY_EXTENSION);
+
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+
+            options.mode(SESSION_STORE_FILE_MODE);
+        }
+
+        let mut file = options
+            .open(&temporary_path)
+            .map_err(|error| write_error(path, &error.to_string()))?;
+
+        file.write_all(&content)
+            .and_then(|_| file.sync_all())
+            .map_err(|error| write_error(path, &error.to_string()))?;
+
+        fs::rename(&temporary_path, path).map_err(|error| write_error(path, &error.to_string()))?;
+
+        sync_parent_directory(path)?;
+
+        self.unsaved_last_use = false;
+
+        Ok(())
+    }
+}
+
+impl Drop for FileSessionStore {
+    fn drop(&mut self) {
+        if self.unsaved_last_use {
+            let _ = self.persist();
+        }
+    }
+}
+
+impl SessionStore for FileSessionStore {
+    fn get(&self, token_hash: &str) -> Result<Option<Session>> {
+        Ok(self.sessions.get(token_hash).cloned())
+    }
+
+    fn save(&mut self, token_hash: &str, session: Session) -> Result<()> {
+        let only_last_use = self
+            .sessions
+            .get(token_hash)
+            .is_some_and(|saved_session| is_same_but_last_use(saved_session, &session));
+
+        self.sessions.insert(token_hash.to_string(), session);
+
+        if only_last_use {
+            self.unsaved_last_use = true;
+
+            return Ok(());
+        }
+
+        self.persist()
+    }
+
+    fn remove(&mut self, token_hash: &str) -> Result<Option<Session>> {
+        let session = self.sessions.remove(token_hash);
+
+        if session.is_some() {
+            self.persist()?;
+        }
+
+        Ok(session)
+    }
+
+    fn list(&self) -> Result<Vec<(String, Session)>> {
+        Ok(self
+            .sessions
+            .iter()
+            .map(|(token_hash, session)| (token_hash.clone(), session.clone()))
+            .collect())
+    }
+
+    fn retain(&mut self, keep: &dyn Fn(&str, &Session) -> bool) -> Result<Vec<String>> {
+        let removed_token_hashes = removed_token_hashes(&self.sessions, keep);
+
+        if removed_token_hashes.is_empty() && !self.unsaved_last_use {
+            return Ok(removed_token_hashes);
+        }
+
+        for token_hash in &removed_token_hashes {
+            self.sessions.remove(token_hash);
+        }
+
+        self.persist()?;
+
+        Ok(removed_token_hashes)
+    }
+}
+
+fn is_same_but_last_use(saved_session: &Session, session: &Session) -> bool {
+    Session {
+        last_used_at: session.last_used_at,
+        ..saved_session.clone()
+    } == *session
+}
+
+// One session per line, text fields are hex encoded since usernames and labels can contain anything.
+fn encode_sessions(session_store_key: &SessionStoreKey, sessions: &HashMap<String, Session>) -> Result<Vec<u8>> {
+    let plaintext: String = sessions
+        .iter()
+        .map(|(token_hash, session)| {
+            format!(
+                "{} {} {} {} {} {} {} {} {} {} {}\n",
+                token_hash,
+                session.session_id,
+                hex::encode(&session.username),
+                session
+                    .client_label
+                    .as_ref()
+                    .map(hex::encode)
+                    .unwrap_or(NO_CLIENT_LABEL.to_string()),
+                session.created_at,
+                session.last_used_at,
+                hex::encode(&session.session_key),
+                session
+                    .retires_at
+                    .map(|retires_at| retires_at.to_string())
+                    .unwrap_or(NOT_RETIRED.to_string()),
+                hex::encode(&session.cipher_suite),
+                match session.second_factor_pending {
+                    true => SECOND_FACTOR_PENDING,
+                    false => SECOND_FACTOR_VERIFIED,
+                },
+                match session.recovery {
+                    true => RECOVERY_SESSION,
+                    false => LOGIN_SESSION,
+                }
+            )
+        })
+        .collect();
+
+    let mut content = SESSION_STORE_MAGIC.to_vec();
+    content.push(SESSION_STORE_FORMAT_VERSION);
+
+    let sealed = session_store_key.seal(&content, plaintext.as_bytes())?;
+    content.extend(sealed);
+
+    Ok(content)
+}
+
+fn decode_sessions(
+    session_store_key: &SessionStoreKey,
+    content: &[u8],
+) -> std::result::Result<HashMap<String, Session>, String> {
+    if content.len() < SESSION_STORE_HEADER_LENGTH || &content[..SESSION_STORE_MAGIC.len()] != SESSION_STORE_MAGIC {
+        return Err(NOT_A_SESSION_STORE_FILE.to_string());
+    }
+
+    if content[SESSION_STORE_MAGIC.len()] != SESSION_STORE_FORMAT_VERSION {
+        return Err(UNSUPPORTED_FORMAT_VERSION.to_string());
+    }
+
+    let (header, sealed) = content.split_at(SESSION_STORE_HEADER_LENGTH);
+
+    let plaintext = session_store_key.open(header, sealed)?;
+    let plaintext = String::from_utf8(plaintext).map_err(|_| CORRUPT_SESSION.to_string())?;
+
+    let mut sessions = HashMap::new();
+
+    for line in plaintext.lines().filter(|line| !line.is_empty()) {
+        let (token_hash, session) = decode_session(line).ok_or(CORRUPT_SESSION.to_string())?;
+
+        sessions.insert(token_hash, session);
+    }
+
+    Ok(sessions)
+}
+
+fn decode_session(line: &str) -> Option<(String, Session)> {
+    let fields: Vec<&str> = line.split(' ').collect();
+
+    let [
+        token_hash,
+        session_id,
+        username,
+        client_label,
+        created_at,
+        last_used_at,
+        session_key,
+        retires_at,
+        cipher_suite,
+        second_factor_pending,
+        recovery,
+    ] = fields[..]
+    else {
+        return None;
+    };
+
+    let retires_at = match retires_at {
+        NOT_RETIRED => None,
+        retires_at => Some(retires_at.parse().ok()?),
+    };
+
+    let second_factor_pending = match second_factor_pending {
+        SECOND_FACTOR_VERIFIED => false,
+        SECOND_FACTOR_PENDING => true,
+        _ => return None,
+    };
+
+    let recovery = match recovery {
+        LOGIN_SESSION => false,
+        RECOVERY_SESSION => true,
+        _ => return None,
+    };
+
+    let client_label = match client_label {
+        NO_CLIENT_LABEL => None,
+        client_label => Some(decode_text(client_label)?),
+    };
+
+    let session = Session {
+        session_key: hex::decode(session_key).ok()?,
+        session_id: session_id.to_string(),
+        username: decode_text(username)?,
+        client_label,
+        created_at: created_at.parse().ok()?,
+        last_used_at: last_used_at.parse().ok()?,
+        retires_at,
+        cipher_suite: decode_text(cipher_suite)?,
+        second_factor_pending,
+        recovery,
+    };
+
+    Some((token_hash.to_string(), session))
+}
+
+fn decode_text(field: &str) -> Option<String> {
+    hex::decode(field).ok().and_then(|text| String::from_utf8(text).ok())
+}
+
+// The rename only survives a power loss once the directory itself is synced.
+#[cfg(unix)]
+fn sync_parent_directory(path: &str) -> Result<()> {
+    let directory = match Path::new(path).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    fs::File::open(directory)
+        .and_then(|directory| directory.sync_all())
+        .map_err(|error| write_error(path, &error.to_string()))
+}
+
+// Directories cannot be opened on Windows, NTFS journals the rename on its own.
+#[cfg(not(unix))]
+fn sync_parent_directory(_: &str) -> Result<()> {
+    Ok(())
+}
+
+fn write_error(path: &str, message: &str) -> SessionStoreError {
+    SessionStoreError::WritingToFile(format!("{} ({})", message, path))
+}
@@ -0,0 +1,6 @@
+pub mod file_session_store;
+pub mod memory_session_store;
+pub mod session_store_key;
+
+#[cfg(test)]
+mod tests;
\ No newline at end of file
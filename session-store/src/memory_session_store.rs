@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+
+use core_domain::{
+    ports::session_store::SessionStore,
+    session_store::{session::Session, session_store_error::Result},
+};
+
+// Sessions are lost on restart, every device has to log in again.
+#[derive(Default)]
+pub struct MemorySessionStore {
+    sessions: HashMap<String, Session>,
+}
+
+impl MemorySessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    fn get(&self, token_hash: &str) -> Result<Option<Session>> {
+        Ok(self.sessions.get(token_hash).cloned())
+    }
+
+    fn save(&mut self, token_hash: &str, session: Session) -> Result<()> {
+        self.sessions.insert(token_hash.to_string(), session);
+
+        Ok(())
+    }
+
+    fn remove(&mut self, token_hash: &str) -> Result<Option<Session>> {
+        Ok(self.sessions.remove(token_hash))
+    }
+
+    fn list(&self) -> Result<Vec<(String, Session)>> {
+        Ok(self
+            .sessions
+            .iter()
+            .map(|(token_hash, session)| (token_hash.clone(), session.clone()))
+            .collect())
+    }
+
+    fn retain(&mut self, keep: &dyn Fn(&str, &Session) -> bool) -> Result<Vec<String>> {
+        let removed_token_hashes = removed_token_hashes(&self.sessions, keep);
+
+        for token_hash in &removed_token_hashes {
+            self.sessions.remove(token_hash);
+        }
+
+        Ok(removed_token_hashes)
+    }
+}
+
+pub(crate) fn removed_token_hashes(
+    sessions: &HashMap<String, Session>,
+    keep: &dyn Fn(&str, &Session) -> bool,
+) -> Vec<String> {
+    sessions
+        .iter()
+        .filter(|(token_hash, session)| !keep(token_hash, session))
+        .map(|(token_hash, _)| token_hash.clone())
+        .collect()
+}
@@ -0,0 +1,167 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Read, Write},
+    path::Path,
+};
+
+use chacha20poly1305::{
+    KeyInit, XChaCha20Poly1305, XNonce,
+    aead::{Aead, Payload},
+};
+use core_domain::session_store::session_store_error::{Result, SessionStoreError};
+use hkdf::Hkdf;
+use sha2::Sha512;
+
+const SESSION_STORE_KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 24;
+const TAG_LENGTH: usize = 16;
+const ENCRYPTION_KEY_INFO: &[u8] = b"session-store-xchacha20poly1305";
+
+#[cfg(unix)]
+const SESSION_STORE_KEY_FILE_MODE: u32 = 0o600;
+
+const WRONG_KEY_LENGTH: &str = "Session store key file does not hold a 32 byte key.";
+const SEALED_CONTENT_TOO_SHORT: &str = "Sealed content is too short.";
+const WRONG_TAG: &str = "Sealed content was modified or sealed with another key.";
+#[cfg(unix)]
+const PERMISSIONS_TOO_OPEN: &str = "Session store key file is accessible by other users, expected mode 0600.";
+
+// XChaCha20-Poly1305, its 24 byte nonces are long enough to be drawn at random for every seal.
+pub struct SessionStoreKey {
+    cipher: XChaCha20Poly1305,
+}
+
+impl SessionStoreKey {
+    pub fn new(key_material: &[u8]) -> Result<Self> {
+        if key_material.len() != SESSION_STORE_KEY_LENGTH {
+            return Err(SessionStoreError::Internal(WRONG_KEY_LENGTH.to_string()));
+        }
+
+        let mut encryption_key = [0u8; SESSION_STORE_KEY_LENGTH];
+
+        Hkdf::<Sha512>::new(None, key_material)
+            .expand(ENCRYPTION_KEY_INFO, &mut encryption_key)
+            .map_err(|error| SessionStoreError::Internal(error.to_string()))?;
+
+        Ok(Self {
+            cipher: XChaCha20Poly1305::new(&encryption_key.into()),
+        })
+    }
+
+    pub fn generate() -> Result<(Self, Vec<u8>)> {
+        let key_material = random_bytes(SESSION_STORE_KEY_LENGTH)?;
+
+        Ok((Self::new(&key_material)?, key_material))
+    }
+
+    pub fn seal(&self, associated_data: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let nonce = random_bytes(NONCE_LENGTH)?;
+
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: associated_data,
+                },
+            )
+            .map_err(|error| SessionStoreError::Internal(error.to_string()))?;
+
+        let mut sealed = nonce;
+        sealed.extend(ciphertext);
+
+        Ok(sealed)
+    }
+
+    pub fn open(&self, associated_data: &[u8], sealed: &[u8]) -> std::result::Result<Vec<u8>, String> {
+        if sealed.len() < NONCE_LENGTH + TAG_LENGTH {
+            return Err(SEALED_CONTENT_TOO_SHORT.to_string());
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+
+        self.cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: associated_data,
+                },
+            )
+            .map_err(|_| WRONG_TAG.to_string())
+    }
+}
+
+// An unreadable key file is reported rather than replaced, the sessions sealed with it would be lost.
+pub fn load_or_create_session_store_key(path: &str) -> Result<SessionStoreKey> {
+    match File::open(path) {
+        Ok(file) => load_session_store_key(path, file),
+        Err(error) if error.kind() == ErrorKind::NotFound => create_session_store_key(path),
+        Err(error) => Err(key_error(path, &error.to_string())),
+    }
+}
+
+fn load_session_store_key(path: &str, mut file: File) -> Result<SessionStoreKey> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let metadata = file
+            .metadata()
+            .map_err(|error| key_error(path, &error.to_string()))?;
+
+        if metadata.permissions().mode() & 0o077 != 0 {
+            return Err(key_error(path, PERMISSIONS_TOO_OPEN));
+        }
+    }
+
+    let mut key_material = Vec::new();
+
+    file.read_to_end(&mut key_material)
+        .map_err(|error| key_error(path, &error.to_string()))?;
+
+    SessionStoreKey::new(&key_material).map_err(|_| key_error(path, WRONG_KEY_LENGTH))
+}
+
+fn create_session_store_key(path: &str) -> Result<SessionStoreKey> {
+    if let Some(parent) = Path::new(path).parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent).map_err(|error| key_error(path, &error.to_string()))?;
+    }
+
+    let (session_store_key, key_material) = SessionStoreKey::generate()?;
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        options.mode(SESSION_STORE_KEY_FILE_MODE);
+    }
+
+    let mut file = options
+        .open(path)
+        .map_err(|error| key_error(path, &error.to_string()))?;
+
+    file.write_all(&key_material)
+        .and_then(|_| file.sync_all())
+        .map_err(|error| key_error(path, &error.to_string()))?;
+
+    Ok(session_store_key)
+}
+
+fn random_bytes(length: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![0u8; length];
+
+    getrandom::getrandom(&mut bytes).map_err(|error| SessionStoreError::Internal(error.to_string()))?;
+
+    Ok(bytes)
+}
+
+fn key_error(path: &str, message: &str) -> SessionStoreError {
+    SessionStoreError::Internal(format!("{} ({})", message, path))
+}
@@ -0,0 +1,3 @@
+mod file_session_store_tests;
+mod memory_session_store_tests;
+mod session_store_key_tests;
\ No newline at end of file
@@ -0,0 +1,254 @@
+use std::fs;
+
+use core_domain::{
+    ports::session_store::SessionStore,
+    session_store::{session::Session, session_store_error::SessionStoreError},
+};
+use tempfile::TempDir;
+
+use crate::{file_session_store::FileSessionStore, session_store_key::SessionStoreKey};
+
+const KEY_MATERIAL: [u8; 32] = [7; 32];
+
+#[test]
+fn should_reload_saved_sessions() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("sessions").join("sessions");
+    let path = path.to_str().unwrap();
+
+    let mut file_session_store = FileSessionStore::load(path, generate_session_store_key()).unwrap();
+
+    let mut retired_session = generate_session("bob", None);
+    retired_session.retires_at = Some(1_130);
+    retired_session.second_factor_pending = true;
+    retired_session.recovery = true;
+
+    file_session_store.save("alice-token-hash", generate_session("alice", Some("laptop"))).unwrap();
+    file_session_store.save("bob-token-hash", retired_session.clone()).unwrap();
+
+    // A-ct
+
+    let reloaded_file_session_store = FileSessionStore::load(path, generate_session_store_key()).unwrap();
+
+    // A-ssert
+
+    assert_eq!(
+        reloaded_file_session_store.get("alice-token-hash").unwrap(),
+        Some(generate_session("alice", Some("laptop")))
+    );
+    assert_eq!(
+        reloaded_file_session_store.get("bob-token-hash").unwrap(),
+        Some(retired_session)
+    );
+}
+
+#[test]
+fn should_persist_removed_sessions() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("sessions");
+    let path = path.to_str().unwrap();
+
+    let mut file_session_store = FileSessionStore::load(path, generate_session_store_key()).unwrap();
+
+    file_session_store.save("alice-token-hash", generate_session("alice", None)).unwrap();
+    file_session_store.save("bob-token-hash", generate_session("bob", None)).unwrap();
+    file_session_store.save("carol-token-hash", generate_session("carol", None)).unwrap();
+
+    // A-ct
+
+    file_session_store.remove("alice-token-hash").unwrap();
+    file_session_store.retain(&|_, session| session.username != "bob").unwrap();
+
+    // A-ssert
+
+    let reloaded_file_session_store = FileSessionStore::load(path, generate_session_store_key()).unwrap();
+
+    assert_eq!(
+        reloaded_file_session_store.list().unwrap(),
+        vec![(String::from("carol-token-hash"), generate_session("carol", None))]
+    );
+}
+
+#[test]
+fn should_not_write_session_keys_in_clear() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("sessions");
+    let path = path.to_str().unwrap();
+
+    let mut file_session_store = FileSessionStore::load(path, generate_session_store_key()).unwrap();
+
+    // A-ct
+
+    file_session_store.save("alice-token-hash", generate_session("alice", None)).unwrap();
+
+    // A-ssert
+
+    let content = fs::read(path).unwrap();
+    let hex_session_key = hex::encode([42; 64]);
+
+    assert!(!content.windows(64).any(|window| window == [42; 64]));
+    assert!(!content.windows(hex_session_key.len()).any(|window| window == hex_session_key.as_bytes()));
+    assert!(!content.windows(16).any(|window| window == b"alice-token-hash"));
+}
+
+#[test]
+fn should_not_load_modified_session_store_file() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("sessions");
+    let path = path.to_str().unwrap();
+
+    let mut file_session_store = FileSessionStore::load(path, generate_session_store_key()).unwrap();
+    file_session_store.save("alice-token-hash", generate_session("alice", None)).unwrap();
+
+    let mut content = fs::read(path).unwrap();
+    let middle = content.len() / 2;
+    content[middle] ^= 1;
+    fs::write(path, content).unwrap();
+
+    // A-ct
+
+    let result = FileSessionStore::load(path, generate_session_store_key());
+
+    // A-ssert
+
+    match result {
+        Err(SessionStoreError::Corrupt(_)) => {}
+        _ => panic!("Test result should be Corrupt."),
+    }
+}
+
+#[test]
+fn should_not_load_session_store_file_with_other_key() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("sessions");
+    let path = path.to_str().unwrap();
+
+    let mut file_session_store = FileSessionStore::load(path, generate_session_store_key()).unwrap();
+    file_session_store.save("alice-token-hash", generate_session("alice", None)).unwrap();
+
+    // A-ct
+
+    let result = FileSessionStore::load(path, SessionStoreKey::new(&[8; 32]).unwrap());
+
+    // A-ssert
+
+    match result {
+        Err(SessionStoreError::Corrupt(_)) => {}
+        _ => panic!("Test result should be Corrupt."),
+    }
+}
+
+#[test]
+fn should_keep_last_use_in_memory_until_retain() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("sessions");
+    let path = path.to_str().unwrap();
+
+    let mut file_session_store = FileSessionStore::load(path, generate_session_store_key()).unwrap();
+    file_session_store.save("alice-token-hash", generate_session("alice", None)).unwrap();
+
+    let saved_content = fs::read(path).unwrap();
+
+    let mut used_session = generate_session("alice", None);
+    used_session.last_used_at = 1_200;
+
+    // A-ct
+
+    file_session_store.save("alice-token-hash", used_session.clone()).unwrap();
+
+    let content_after_use = fs::read(path).unwrap();
+
+    file_session_store.retain(&|_, _| true).unwrap();
+
+    // A-ssert
+
+    assert_eq!(content_after_use, saved_content);
+    assert_eq!(file_session_store.get("alice-token-hash").unwrap(), Some(used_session.clone()));
+
+    let reloaded_file_session_store = FileSessionStore::load(path, generate_session_store_key()).unwrap();
+
+    assert_eq!(reloaded_file_session_store.get("alice-token-hash").unwrap(), Some(used_session));
+}
+
+#[test]
+fn should_save_last_use_when_dropped() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("sessions");
+    let path = path.to_str().unwrap();
+
+    let mut file_session_store = FileSessionStore::load(path, generate_session_store_key()).unwrap();
+    file_session_store.save("alice-token-hash", generate_session("alice", None)).unwrap();
+
+    let mut used_session = generate_session("alice", None);
+    used_session.last_used_at = 1_200;
+
+    file_session_store.save("alice-token-hash", used_session.clone()).unwrap();
+
+    // A-ct
+
+    drop(file_session_store);
+
+    // A-ssert
+
+    let reloaded_file_session_store = FileSessionStore::load(path, generate_session_store_key()).unwrap();
+
+    assert_eq!(reloaded_file_session_store.get("alice-token-hash").unwrap(), Some(used_session));
+}
+
+#[test]
+fn should_save_other_session_changes_at_once() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("sessions");
+    let path = path.to_str().unwrap();
+
+    let mut file_session_store = FileSessionStore::load(path, generate_session_store_key()).unwrap();
+    file_session_store.save("alice-token-hash", generate_session("alice", None)).unwrap();
+
+    let mut relabelled_session = generate_session("alice", None);
+    relabelled_session.client_label = Some("laptop".to_string());
+
+    // A-ct
+
+    file_session_store.save("alice-token-hash", relabelled_session.clone()).unwrap();
+
+    // A-ssert
+
+    let reloaded_file_session_store = FileSessionStore::load(path, generate_session_store_key()).unwrap();
+
+    assert_eq!(reloaded_file_session_store.get("alice-token-hash").unwrap(), Some(relabelled_session));
+}
+
+fn generate_session_store_key() -> SessionStoreKey {
+    SessionStoreKey::new(&KEY_MATERIAL).unwrap()
+}
+
+fn generate_session(username: &str, client_label: Option<&str>) -> Session {
+    let mut session = Session::new(
+        vec![42; 64],
+        format!("{}-session-id", username),
+        username.to_string(),
+        client_label.map(str::to_string),
+        String::from("ristretto255-sha512"),
+        1_000,
+    );
+
+    session.last_used_at = 1_100;
+
+    session
+}
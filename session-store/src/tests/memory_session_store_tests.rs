@@ -0,0 +1,63 @@
+use core_domain::{ports::session_store::SessionStore, session_store::session::Session};
+
+use crate::memory_session_store::MemorySessionStore;
+
+#[test]
+fn should_save_and_get_session() {
+    // A-rrange
+
+    let mut memory_session_store = MemorySessionStore::new();
+
+    // A-ct
+
+    memory_session_store.save("token hash", generate_session("alice")).unwrap();
+
+    // A-ssert
+
+    assert_eq!(memory_session_store.get("token hash").unwrap(), Some(generate_session("alice")));
+    assert_eq!(memory_session_store.get("other token hash").unwrap(), None);
+}
+
+#[test]
+fn should_remove_session() {
+    // A-rrange
+
+    let mut memory_session_store = MemorySessionStore::new();
+
+    memory_session_store.save("token hash", generate_session("alice")).unwrap();
+
+    // A-ct
+
+    let result = memory_session_store.remove("token hash");
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), Some(generate_session("alice")));
+    assert!(memory_session_store.list().unwrap().is_empty());
+}
+
+#[test]
+fn should_retain_sessions() {
+    // A-rrange
+
+    let mut memory_session_store = MemorySessionStore::new();
+
+    memory_session_store.save("alice token hash", generate_session("alice")).unwrap();
+    memory_session_store.save("bob token hash", generate_session("bob")).unwrap();
+
+    // A-ct
+
+    let result = memory_session_store.retain(&|_, session| session.username != "alice");
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), vec![String::from("alice token hash")]);
+    assert_eq!(
+        memory_session_store.list().unwrap(),
+        vec![(String::from("bob token hash"), generate_session("bob"))]
+    );
+}
+
+fn generate_session(username: &str) -> Session {
+    Session::new(vec![42; 64], String::from("session id"), username.to_string(), None, String::from("ristretto255-sha512"), 1_000)
+}
@@ -0,0 +1,136 @@
+use std::fs;
+
+use tempfile::TempDir;
+
+use crate::session_store_key::{SessionStoreKey, load_or_create_session_store_key};
+
+#[test]
+fn should_seal_and_open_content() {
+    // A-rrange
+
+    let (session_store_key, _) = SessionStoreKey::generate().unwrap();
+    let plaintext = vec![42; 1_000];
+
+    // A-ct
+
+    let sealed = session_store_key.seal(b"header", &plaintext).unwrap();
+    let result = session_store_key.open(b"header", &sealed);
+
+    // A-ssert
+
+    assert!(!sealed.windows(64).any(|window| window == &plaintext[..64]));
+    assert_eq!(result.unwrap(), plaintext);
+}
+
+#[test]
+fn should_not_open_modified_content() {
+    // A-rrange
+
+    let (session_store_key, _) = SessionStoreKey::generate().unwrap();
+
+    let mut sealed = session_store_key.seal(b"header", b"session").unwrap();
+    sealed[40] ^= 1;
+
+    // A-ct
+
+    let result = session_store_key.open(b"header", &sealed);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_not_open_content_with_other_associated_data_or_key() {
+    // A-rrange
+
+    let (session_store_key, _) = SessionStoreKey::generate().unwrap();
+    let (other_session_store_key, _) = SessionStoreKey::generate().unwrap();
+
+    let sealed = session_store_key.seal(b"header", b"session").unwrap();
+
+    // A-ct
+
+    let other_associated_data_result = session_store_key.open(b"other header", &sealed);
+    let other_key_result = other_session_store_key.open(b"header", &sealed);
+
+    // A-ssert
+
+    assert!(other_associated_data_result.is_err());
+    assert!(other_key_result.is_err());
+}
+
+#[test]
+fn should_load_same_session_store_key() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("keys").join("session_store.key");
+    let path = path.to_str().unwrap();
+
+    let created_session_store_key = load_or_create_session_store_key(path).unwrap();
+    let sealed = created_session_store_key.seal(b"", b"session").unwrap();
+
+    // A-ct
+
+    let result = load_or_create_session_store_key(path);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().open(b"", &sealed).unwrap(), b"session");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = fs::metadata(path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+#[test]
+fn should_not_load_truncated_session_store_key() {
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("session_store.key");
+    let path = path.to_str().unwrap();
+
+    load_or_create_session_store_key(path).unwrap();
+
+    let mut content = fs::read(path).unwrap();
+    content.truncate(16);
+    fs::write(path, content).unwrap();
+
+    // A-ct
+
+    let result = load_or_create_session_store_key(path);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn should_not_load_session_store_key_readable_by_others() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // A-rrange
+
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("session_store.key");
+    let path = path.to_str().unwrap();
+
+    load_or_create_session_store_key(path).unwrap();
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o644)).unwrap();
+
+    // A-ct
+
+    let result = load_or_create_session_store_key(path);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
@@ -1,4 +1,5 @@
 use core_domain::{
+    domain::username::Username,
     ports::{file_storage::FileStorage, vault_store::VaultStore},
     utils::file_storage_error_to_vault_store_error,
     vault_store::vault_store_error::Result,
@@ -15,15 +16,21 @@ impl<FS: FileStorage> DirectoryVaultStore<FS> {
 }
 
 impl<FS: FileStorage> VaultStore for DirectoryVaultStore<FS> {
-    fn retrieve(&self, username: &str) -> Result<Vec<u8>> {
+    fn retrieve(&self, username: &Username) -> Result<Vec<u8>> {
         self.file_storage
-            .retrieve(username)
+            .retrieve(username.as_str())
             .map_err(file_storage_error_to_vault_store_error)
     }
 
-    fn save(&self, username: &str, vault: Vec<u8>) -> Result<()> {
+    fn save(&self, username: &Username, vault: Vec<u8>) -> Result<()> {
         self.file_storage
-            .save(username, vault)
+            .save(username.as_str(), vault)
+            .map_err(file_storage_error_to_vault_store_error)
+    }
+
+    fn delete(&self, username: &Username) -> Result<()> {
+        self.file_storage
+            .delete(username.as_str())
             .map_err(file_storage_error_to_vault_store_error)
     }
 }
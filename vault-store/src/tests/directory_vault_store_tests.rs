@@ -1,4 +1,8 @@
-use core_domain::ports::{file_storage::FileStorage, vault_store::VaultStore};
+use core_domain::{
+    domain::username::Username,
+    file_storage::{file_listing::FileListing, file_metadata::FileMetadata},
+    ports::{file_storage::FileStorage, vault_store::VaultStore},
+};
 
 use crate::directory_vault_store::DirectoryVaultStore;
 
@@ -6,13 +10,13 @@ use crate::directory_vault_store::DirectoryVaultStore;
 fn should_retrieve_file() {
     // A-rrange
 
-    let username = "username";
+    let username = Username::parse("username").unwrap();
 
     let directory_vault_store = DirectoryVaultStore::new(MockFileStorage::new(String::new()));
 
     // A-ct
 
-    let result = directory_vault_store.retrieve(username);
+    let result = directory_vault_store.retrieve(&username);
 
     // A-ssert
     assert!(result.is_ok());
@@ -23,11 +27,29 @@ fn should_retrieve_file() {
 fn should_save_file() {
     // A-rrange
 
+    let username = Username::parse("test").unwrap();
+
+    let directory_vault_store = DirectoryVaultStore::new(MockFileStorage::new(String::new()));
+
+    // A-ct
+
+    let result = directory_vault_store.save(&username, vec![42]);
+
+    // A-ssert
+    assert!(result.is_ok());
+}
+
+#[test]
+fn should_delete_file() {
+    // A-rrange
+
+    let username = Username::parse("username").unwrap();
+
     let directory_vault_store = DirectoryVaultStore::new(MockFileStorage::new(String::new()));
 
     // A-ct
 
-    let result = directory_vault_store.save("test", vec![42]);
+    let result = directory_vault_store.delete(&username);
 
     // A-ssert
     assert!(result.is_ok());
@@ -51,4 +73,28 @@ impl FileStorage for MockFileStorage {
     ) -> core_domain::file_storage::file_storage_error::Result<()> {
         Ok(())
     }
+
+    fn delete(&self, _: &str) -> core_domain::file_storage::file_storage_error::Result<()> {
+        Ok(())
+    }
+
+    fn exists(&self, _: &str) -> core_domain::file_storage::file_storage_error::Result<bool> {
+        Ok(true)
+    }
+
+    fn list(
+        &self,
+        prefix: &str,
+        start_after: Option<&str>,
+        limit: usize,
+    ) -> core_domain::file_storage::file_storage_error::Result<FileListing> {
+        Ok(FileListing::page(vec![String::from("username")], prefix, start_after, limit))
+    }
+
+    fn metadata(&self, _: &str) -> core_domain::file_storage::file_storage_error::Result<FileMetadata> {
+        Ok(FileMetadata {
+            size: 1,
+            modified_at: 42,
+        })
+    }
 }